@@ -101,7 +101,8 @@ pub async fn setup_services(config: &Option<PathBuf>, container_mode: bool) -> a
                 "api_key": "${ES_API_KEY:}",
                 "username": "${ES_USERNAME:}",
                 "password": "${ES_PASSWORD:}",
-                "ssl_skip_verify": "${ES_SSL_SKIP_VERIFY:false}"
+                "ssl_skip_verify": "${ES_SSL_SKIP_VERIFY:false}",
+                "read_only": "${ES_READ_ONLY:true}"
             }
         }"#
         .to_string()
@@ -123,5 +124,6 @@ pub async fn setup_services(config: &Option<PathBuf>, container_mode: bool) -> a
     };
 
     let handler = elasticsearch::ElasticsearchMcp::new_with_config(config.elasticsearch, container_mode)?;
+    handler.detect_cluster().await;
     Ok(handler)
 }
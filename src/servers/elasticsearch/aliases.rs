@@ -0,0 +1,540 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Aliases: listing, atomic updates, and resolution of index expressions (aliases, data streams
+//! and patterns) to the concrete indices they target.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_index_list, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::safety::{check_confirmation_token, check_not_system_index, confirmation_token};
+use crate::servers::elasticsearch::{internal_error, read_json};
+use elasticsearch::Elasticsearch;
+use elasticsearch::cat::CatAliasesParts;
+use elasticsearch::http::StatusCode;
+use elasticsearch::indices::{IndicesGetAliasParts, IndicesResolveIndexParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Maximum number of concrete index names listed in resolutions
+const MAX_RESOLVED_INDEX_NAMES: usize = 20;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListAliasesParams {
+    /// Alias name pattern (supports * wildcard, default: *)
+    name_pattern: Option<String>,
+    /// Include system aliases, whose name starts with a dot (default: false)
+    include_system: Option<bool>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum AliasActionType {
+    Add,
+    Remove,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+struct AliasAction {
+    /// Action: 'add' or 'remove'
+    action: AliasActionType,
+    /// Concrete index name
+    index: String,
+    /// Alias name
+    alias: String,
+    /// For 'add': make this index the write index of the alias
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_write_index: Option<bool>,
+    /// For 'add': optional query DSL filter restricting the documents visible through the alias
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<Map<String, Value>>,
+    /// For 'add': optional routing value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    routing: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct UpdateAliasesParams {
+    /// Actions, applied atomically (e.g. remove an alias from an index and add it to another one)
+    actions: Vec<AliasAction>,
+    /// Token returned by a first call without it, after reviewing the changes
+    confirmation_token: Option<String>,
+}
+
+#[tool_router(router = alias_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list aliases
+    #[tool(
+        description = "List aliases with the indices they point to, their write index, and whether they have a filter or routing.",
+        annotations(title = "List aliases", read_only_hint = true)
+    )]
+    async fn list_aliases(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ListAliasesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let pattern = params.name_pattern.as_deref().unwrap_or("*");
+
+        let response = es_client
+            .cat()
+            .aliases(CatAliasesParts::Name(&[pattern]))
+            .h(&["alias", "index", "filter", "routing.index", "routing.search", "is_write_index"])
+            .format("json")
+            .send()
+            .await;
+        let rows: Vec<CatAliasResponse> = read_json(response).await?;
+
+        let include_system = params.include_system.unwrap_or(false);
+        let mut aliases = group_aliases(rows.into_iter().filter(|r| include_system || !r.alias.starts_with('.')));
+
+        let total = aliases.len();
+        let max_list = max_index_list();
+        let message = if total > max_list {
+            aliases.truncate(max_list);
+            format!("Found {total} aliases (showing first {max_list}, use name_pattern to filter).")
+        } else {
+            format!("Found {total} aliases.")
+        };
+
+        let data = pack_json_value(&aliases, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: update aliases
+    ///
+    /// Two-step protocol: a first call shows the current and resulting state of the aliases and
+    /// returns a confirmation token, that must be provided in a second call to apply the actions.
+    #[tool(
+        description = "Atomically add and remove aliases (e.g. switch an alias or its write index to a new index). System indices can't be changed, and the server configuration can restrict which indices may be. A first call without confirmation_token shows the current and resulting state of the aliases, with their filter and routing, and returns a token: review it with the user, then call again with the token.",
+        annotations(title = "Update aliases", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn update_aliases(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<UpdateAliasesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        if params.actions.is_empty() {
+            return Err(rmcp::Error::invalid_params("No alias action provided.", None));
+        }
+        for action in &params.actions {
+            check_not_system_index(&action.index)?;
+            check_not_system_index(&action.alias)?;
+            self.write_policy.check(&action.index)?;
+        }
+
+        let names = params.actions.iter().map(|a| a.alias.as_str()).collect::<BTreeSet<_>>();
+        let current = fetch_alias_state(&es_client, &names.into_iter().collect::<Vec<_>>()).await?;
+        let proposed = apply_alias_actions(&current, &params.actions)?;
+
+        // The token is bound to the current state too, so that it expires if the aliases are changed meanwhile
+        let token = confirmation_token(&[
+            "update_aliases",
+            &json!(params.actions).to_string(),
+            &json!(current).to_string(),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "{} alias action(s) will be applied atomically. Review the changes with the user, then call update_aliases again with the confirmation_token.",
+                params.actions.len()
+            );
+            let data = pack_json_value(&json!({ "current": current, "proposed": proposed }), max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "update_aliases without confirmation_token")?;
+
+        let body = params
+            .actions
+            .iter()
+            .map(|a| {
+                let mut action = json!({ "index": a.index, "alias": a.alias });
+                if let Some(is_write_index) = a.is_write_index {
+                    action["is_write_index"] = json!(is_write_index);
+                }
+                if let Some(filter) = &a.filter {
+                    action["filter"] = json!(filter);
+                }
+                if let Some(routing) = &a.routing {
+                    action["routing"] = json!(routing);
+                }
+                match a.action {
+                    AliasActionType::Add => json!({ "add": action }),
+                    AliasActionType::Remove => json!({ "remove": action }),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let response = es_client
+            .indices()
+            .update_aliases()
+            .body(json!({ "actions": body }))
+            .send()
+            .await;
+        let _: Value = read_json(response).await?;
+
+        let data = pack_json_value(&proposed, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Applied {} alias action(s).", params.actions.len()), "data": data }))?
+        ]))
+    }
+}
+
+/// State of aliases: indices of each alias, with the properties of the alias on each index
+type AliasState = BTreeMap<String, BTreeMap<String, AliasLink>>;
+
+/// Properties of an alias on an index, as returned by the get alias API
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct AliasLink {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_write_index: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index_routing: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    search_routing: Option<String>,
+}
+
+impl AliasLink {
+    fn from_action(action: &AliasAction) -> Self {
+        AliasLink {
+            is_write_index: action.is_write_index,
+            filter: action.filter.clone().map(Value::Object),
+            // `routing` sets both the index and search routing
+            index_routing: action.routing.clone(),
+            search_routing: action.routing.clone(),
+        }
+    }
+}
+
+/// Get the indices the aliases point to. Aliases that don't exist have no index.
+async fn fetch_alias_state(es_client: &Elasticsearch, names: &[&str]) -> Result<AliasState, rmcp::Error> {
+    let response = es_client
+        .indices()
+        .get_alias(IndicesGetAliasParts::Name(names))
+        .send()
+        .await
+        .map_err(internal_error)?;
+    // If some aliases don't exist, the response is a 404 that still lists the existing ones
+    let response = if response.status_code() == StatusCode::NOT_FOUND {
+        response
+    } else {
+        response.error_for_status_code().map_err(internal_error)?
+    };
+    let body: Map<String, Value> = response.json().await.map_err(internal_error)?;
+    Ok(alias_state(names, body))
+}
+
+/// Alias state from a get alias response, keyed by index
+fn alias_state(names: &[&str], body: Map<String, Value>) -> AliasState {
+    let mut state = names
+        .iter()
+        .map(|name| (name.to_string(), BTreeMap::new()))
+        .collect::<AliasState>();
+    for (index, entry) in body {
+        let Ok(entry) = serde_json::from_value::<GetAliasEntry>(entry) else {
+            // `error` and `status` of a partial 404 response
+            continue;
+        };
+        for (alias, link) in entry.aliases {
+            state.entry(alias).or_default().insert(index.clone(), link);
+        }
+    }
+    state
+}
+
+/// Compute the state of aliases after applying actions, rejecting actions that can't apply
+fn apply_alias_actions(current: &AliasState, actions: &[AliasAction]) -> Result<AliasState, rmcp::Error> {
+    let mut state = current.clone();
+    for action in actions {
+        let indices = state.entry(action.alias.clone()).or_default();
+        match action.action {
+            AliasActionType::Add => {
+                if action.is_write_index == Some(true) {
+                    for link in indices.values_mut() {
+                        link.is_write_index = Some(false);
+                    }
+                }
+                indices.insert(action.index.clone(), AliasLink::from_action(action));
+            }
+            AliasActionType::Remove => {
+                if indices.remove(&action.index).is_none() {
+                    return Err(rmcp::Error::invalid_params(
+                        format!("Alias '{}' doesn't point to index '{}'.", action.alias, action.index),
+                        None,
+                    ));
+                }
+            }
+        }
+    }
+    Ok(state)
+}
+
+#[derive(Serialize)]
+struct AliasInfo {
+    alias: String,
+    indices: Vec<String>,
+    write_index: Option<String>,
+    filtered: bool,
+    routing: bool,
+}
+
+/// Group `_cat/aliases` rows by alias, sorted by alias name
+fn group_aliases(rows: impl Iterator<Item = CatAliasResponse>) -> Vec<AliasInfo> {
+    let mut aliases = BTreeMap::<String, AliasInfo>::new();
+    for row in rows {
+        let info = aliases.entry(row.alias.clone()).or_insert_with(|| AliasInfo {
+            alias: row.alias.clone(),
+            indices: Vec::new(),
+            write_index: None,
+            filtered: false,
+            routing: false,
+        });
+        if row.is_write_index == "true" {
+            info.write_index = Some(row.index.clone());
+        }
+        info.filtered |= row.filter != "-";
+        info.routing |= row.routing_index != "-" || row.routing_search != "-";
+        info.indices.push(row.index);
+    }
+    aliases
+        .into_values()
+        .map(|mut info| {
+            info.indices.sort();
+            // An alias pointing to a single index without explicit flag writes to it
+            if info.write_index.is_none() && info.indices.len() == 1 {
+                info.write_index = info.indices.first().cloned();
+            }
+            info
+        })
+        .collect()
+}
+
+//----- Resolution of index expressions
+
+/// The indices, aliases and data streams an index expression resolves to
+#[derive(Default, Deserialize)]
+pub struct ResolvedExpression {
+    #[serde(default)]
+    pub indices: Vec<ResolvedIndex>,
+    #[serde(default)]
+    pub aliases: Vec<ResolvedAlias>,
+    #[serde(default)]
+    pub data_streams: Vec<ResolvedDataStream>,
+}
+
+#[derive(Deserialize)]
+pub struct ResolvedIndex {
+    pub name: String,
+    /// Data stream of a backing index
+    #[serde(default)]
+    pub data_stream: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ResolvedAlias {
+    pub name: String,
+    #[serde(default)]
+    pub indices: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ResolvedDataStream {
+    pub name: String,
+    #[serde(default)]
+    pub backing_indices: Vec<String>,
+    pub timestamp_field: Option<String>,
+}
+
+impl ResolvedExpression {
+    /// Concrete indices targeted by the expression, sorted by name
+    pub fn concrete_indices(&self) -> Vec<String> {
+        let indices = self
+            .indices
+            .iter()
+            .map(|i| &i.name)
+            .chain(self.aliases.iter().flat_map(|a| &a.indices))
+            .chain(self.data_streams.iter().flat_map(|ds| &ds.backing_indices))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        indices.into_iter().collect()
+    }
+
+    /// The write index of data streams, that is their last backing index
+    pub fn write_indices(&self) -> Vec<&str> {
+        self.data_streams
+            .iter()
+            .filter_map(|ds| ds.backing_indices.last().map(|i| i.as_str()))
+            .collect()
+    }
+
+    /// A compact description of the resolution, to be included in tool results
+    pub fn summary(&self) -> Value {
+        let indices = self.concrete_indices();
+        let mut summary = json!({
+            "index_count": indices.len(),
+            "indices": indices.iter().take(MAX_RESOLVED_INDEX_NAMES).collect::<Vec<_>>(),
+        });
+        if !self.aliases.is_empty() {
+            summary["aliases"] = json!(self.aliases.iter().map(|a| &a.name).collect::<Vec<_>>());
+        }
+        if !self.data_streams.is_empty() {
+            summary["data_streams"] = json!(self.data_streams.iter().map(|ds| &ds.name).collect::<Vec<_>>());
+        }
+        summary
+    }
+}
+
+/// Resolve an index expression (index names, aliases, data streams and patterns)
+pub async fn resolve_expression(es_client: &Elasticsearch, expression: &str) -> Result<ResolvedExpression, rmcp::Error> {
+    let response = es_client
+        .indices()
+        .resolve_index(IndicesResolveIndexParts::Name(&[expression]))
+        .send()
+        .await;
+    read_json(response).await
+}
+
+/// Resolve an index expression for reporting purposes: resolution may not be supported (e.g. older
+/// versions), in which case nothing is returned.
+pub async fn try_resolve_expression(es_client: &Elasticsearch, expression: &str) -> Option<ResolvedExpression> {
+    match resolve_expression(es_client, expression).await {
+        Ok(resolved) => Some(resolved),
+        Err(e) => {
+            tracing::debug!("Could not resolve '{expression}': {}", e.message);
+            None
+        }
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct GetAliasEntry {
+    aliases: BTreeMap<String, AliasLink>,
+}
+
+#[derive(Deserialize)]
+struct CatAliasResponse {
+    alias: String,
+    index: String,
+    filter: String,
+    #[serde(rename = "routing.index")]
+    routing_index: String,
+    #[serde(rename = "routing.search")]
+    routing_search: String,
+    is_write_index: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cat_row(alias: &str, index: &str, is_write_index: &str) -> CatAliasResponse {
+        CatAliasResponse {
+            alias: alias.to_string(),
+            index: index.to_string(),
+            filter: "-".to_string(),
+            routing_index: "-".to_string(),
+            routing_search: "-".to_string(),
+            is_write_index: is_write_index.to_string(),
+        }
+    }
+
+    #[test]
+    fn group_cat_aliases() {
+        let mut filtered = cat_row("errors", "logs-2", "-");
+        filtered.filter = "*".to_string();
+        let rows = vec![
+            cat_row("logs", "logs-2", "true"),
+            cat_row("logs", "logs-1", "false"),
+            filtered,
+        ];
+        let aliases = group_aliases(rows.into_iter());
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(aliases[0].alias, "errors");
+        assert!(aliases[0].filtered);
+        assert_eq!(aliases[0].write_index.as_deref(), Some("logs-2"));
+        assert_eq!(aliases[1].indices, vec!["logs-1", "logs-2"]);
+        assert_eq!(aliases[1].write_index.as_deref(), Some("logs-2"));
+    }
+
+    #[test]
+    fn apply_actions() {
+        let current = alias_state(
+            &["logs", "errors"],
+            serde_json::from_value(json!({
+                "logs-1": { "aliases": { "logs": { "is_write_index": true } } },
+                "error": "alias [errors] missing",
+                "status": 404
+            }))
+            .unwrap(),
+        );
+        assert_eq!(current["logs"]["logs-1"].is_write_index, Some(true));
+        assert!(current["errors"].is_empty());
+
+        let actions: Vec<AliasAction> = serde_json::from_value(json!([
+            { "action": "add", "index": "logs-2", "alias": "logs", "is_write_index": true },
+            { "action": "remove", "index": "logs-1", "alias": "logs" },
+            { "action": "add", "index": "logs-2", "alias": "errors", "filter": { "term": { "level": "error" } }, "routing": "1" }
+        ]))
+        .unwrap();
+
+        let proposed = apply_alias_actions(&current, &actions).unwrap();
+        assert_eq!(proposed["logs"].keys().collect::<Vec<_>>(), vec!["logs-2"]);
+        assert_eq!(proposed["logs"]["logs-2"].is_write_index, Some(true));
+        assert_eq!(
+            serde_json::to_value(&proposed["errors"]["logs-2"]).unwrap(),
+            json!({ "filter": { "term": { "level": "error" } }, "index_routing": "1", "search_routing": "1" })
+        );
+
+        let remove_missing: Vec<AliasAction> = serde_json::from_value(json!([
+            { "action": "remove", "index": "logs-3", "alias": "logs" }
+        ]))
+        .unwrap();
+        assert!(apply_alias_actions(&current, &remove_missing).is_err());
+    }
+
+    #[test]
+    fn concrete_indices() {
+        let resolved: ResolvedExpression = serde_json::from_value(json!({
+            "indices": [{ "name": "metrics", "attributes": ["open"] }],
+            "aliases": [{ "name": "all-logs", "indices": ["logs-1", "metrics"] }],
+            "data_streams": [{ "name": "logs-app", "backing_indices": [".ds-logs-app-1", ".ds-logs-app-2"], "timestamp_field": "@timestamp" }]
+        }))
+        .unwrap();
+        assert_eq!(
+            resolved.concrete_indices(),
+            vec![".ds-logs-app-1", ".ds-logs-app-2", "logs-1", "metrics"]
+        );
+        assert_eq!(resolved.write_indices(), vec![".ds-logs-app-2"]);
+        assert_eq!(resolved.summary()["index_count"], 4);
+    }
+}
@@ -0,0 +1,377 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Text analysis: the tokens an analyzer produces for a text, to debug why searches match or not.
+
+use crate::servers::elasticsearch::aliases::try_resolve_expression;
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::Elasticsearch;
+use elasticsearch::indices::{IndicesAnalyzeParts, IndicesGetFieldMappingParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct AnalyzeTextParams {
+    /// Text to analyze
+    text: String,
+    /// Index whose analyzers are used (required with `field`, and for custom analyzers defined in an index)
+    index: Option<String>,
+    /// Field whose analyzer is used, as defined in the index mappings
+    field: Option<String>,
+    /// With `field`, use the field's search analyzer, that analyzes query text, instead of its index analyzer (default: false)
+    search_time: Option<bool>,
+    /// Analyzer name, e.g. standard, english or a custom analyzer of the index
+    analyzer: Option<String>,
+    /// Tokenizer name or definition, to build an analyzer with `filters` and `char_filters`
+    tokenizer: Option<Value>,
+    /// Token filter names or definitions, e.g. ["lowercase", "asciifolding"]
+    filters: Option<Vec<Value>>,
+    /// Character filter names or definitions, e.g. ["html_strip"]
+    char_filters: Option<Vec<Value>>,
+    /// Also return the tokens produced by each character filter, tokenizer and token filter (default: false)
+    explain: Option<bool>,
+}
+
+#[tool_router(router = analysis_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: analyze text
+    #[tool(
+        description = "Analyze a text and return its tokens with their position and type, to understand why a search matches or not. Uses the analyzer of an index field (resolved from its mappings), a named analyzer, or a tokenizer with filters. With explain, also returns the tokens after each analysis step.",
+        annotations(title = "Analyze text", read_only_hint = true)
+    )]
+    async fn analyze_text(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<AnalyzeTextParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let explain = params.explain.unwrap_or(false);
+        let mut body = json!({ "text": params.text, "explain": explain });
+        let mut index = params.index.clone();
+        let mut field_analyzers = None;
+
+        match (&params.field, &params.analyzer, &params.tokenizer) {
+            (Some(field), None, None) => {
+                let Some(expression) = &params.index else {
+                    return Err(rmcp::Error::invalid_params("'field' requires an 'index'.", None));
+                };
+                let analyzers = fetch_field_analyzers(&es_client, expression, field).await?;
+                // Analyzing with the field applies its index analyzer: search analyzers must be named
+                match &analyzers.search_analyzer {
+                    Some(search_analyzer) if params.search_time.unwrap_or(false) => {
+                        body["analyzer"] = json!(search_analyzer);
+                    }
+                    _ => body["field"] = json!(field),
+                }
+                index = Some(analyzers.index.clone());
+                field_analyzers = Some(analyzers);
+            }
+            (None, Some(analyzer), None) => body["analyzer"] = json!(analyzer),
+            (None, None, Some(tokenizer)) => {
+                body["tokenizer"] = tokenizer.clone();
+                body["filter"] = json!(params.filters.unwrap_or_default());
+                body["char_filter"] = json!(params.char_filters.unwrap_or_default());
+            }
+            (None, None, None) => body["analyzer"] = json!("standard"),
+            _ => {
+                return Err(rmcp::Error::invalid_params(
+                    "Provide only one of 'field', 'analyzer' or 'tokenizer'.",
+                    None,
+                ));
+            }
+        }
+
+        let parts = match &index {
+            Some(index) => IndicesAnalyzeParts::Index(index),
+            None => IndicesAnalyzeParts::None,
+        };
+        let response = es_client.indices().analyze(parts).body(body).send().await;
+        let response: AnalyzeResponse = read_json(response).await?;
+
+        let tokens = match &response.detail {
+            Some(detail) => detail.final_tokens(),
+            None => response.tokens.iter().map(Token::summary).collect(),
+        };
+        let mut message = format!("{} token(s)", tokens.len());
+        if let Some(analyzers) = &field_analyzers {
+            let analyzer = if params.search_time.unwrap_or(false) {
+                analyzers.search_analyzer()
+            } else {
+                analyzers.analyzer()
+            };
+            message.push_str(&format!(
+                ", analyzed with the {} analyzer of field {} in index {}",
+                analyzer, analyzers.field, analyzers.index
+            ));
+        }
+        message.push('.');
+
+        let mut data = json!({ "tokens": tokens });
+        if let Some(analyzers) = field_analyzers {
+            data["field"] = json!(analyzers);
+        }
+        if explain && let Some(detail) = response.detail {
+            data["steps"] = json!(detail.steps());
+        }
+        let data = pack_json_value(&data, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+/// Analyzers of a field, from its mapping in a concrete index
+#[derive(Serialize)]
+struct FieldAnalyzers {
+    index: String,
+    field: String,
+    #[serde(rename = "type")]
+    type_: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analyzer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    search_analyzer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalizer: Option<String>,
+}
+
+impl FieldAnalyzers {
+    /// Analyzer used at index time. Keyword fields aren't analyzed, but can have a normalizer.
+    fn analyzer(&self) -> &str {
+        match (&self.analyzer, &self.type_) {
+            (Some(analyzer), _) => analyzer,
+            (None, Some(t)) if t == "keyword" => self.normalizer.as_deref().unwrap_or("keyword"),
+            _ => "standard",
+        }
+    }
+
+    /// Analyzer used for query text, which defaults to the index analyzer
+    fn search_analyzer(&self) -> &str {
+        self.search_analyzer.as_deref().unwrap_or_else(|| self.analyzer())
+    }
+}
+
+/// Get the analyzers of a field. Aliases, data streams and patterns are resolved to a single
+/// index: the write index of data streams, or else the last one by name, usually the most recent.
+async fn fetch_field_analyzers(
+    es_client: &Elasticsearch,
+    expression: &str,
+    field: &str,
+) -> Result<FieldAnalyzers, rmcp::Error> {
+    let response = es_client
+        .indices()
+        .get_field_mapping(IndicesGetFieldMappingParts::IndexFields(&[expression], &[field]))
+        .send()
+        .await;
+    let response: HashMap<String, FieldMappings> = read_json(response).await?;
+
+    let resolved = try_resolve_expression(es_client, expression).await;
+    let write_indices = resolved.as_ref().map(|r| r.write_indices()).unwrap_or_default();
+    let (index, mappings) = response
+        .into_iter()
+        .filter(|(_, m)| m.mappings.contains_key(field))
+        .max_by(|(a, _), (b, _)| {
+            (write_indices.contains(&a.as_str()), a).cmp(&(write_indices.contains(&b.as_str()), b))
+        })
+        .ok_or_else(|| rmcp::Error::invalid_params(format!("Field {field} not found in {expression}."), None))?;
+
+    Ok(field_analyzers(index, field, &mappings.mappings[field].mapping))
+}
+
+fn field_analyzers(index: String, field: &str, mapping: &HashMap<String, Value>) -> FieldAnalyzers {
+    // The mapping is keyed by the last part of the field name
+    let leaf = mapping.values().next().cloned().unwrap_or_default();
+    let string = |key: &str| leaf.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+    FieldAnalyzers {
+        index,
+        field: field.to_string(),
+        type_: string("type"),
+        analyzer: string("analyzer"),
+        search_analyzer: string("search_analyzer"),
+        normalizer: string("normalizer"),
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct FieldMappings {
+    mappings: HashMap<String, FieldMapping>,
+}
+
+#[derive(Deserialize)]
+struct FieldMapping {
+    mapping: HashMap<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct AnalyzeResponse {
+    #[serde(default)]
+    tokens: Vec<Token>,
+    detail: Option<AnalyzeDetail>,
+}
+
+#[derive(Deserialize)]
+struct Token {
+    token: String,
+    #[serde(default)]
+    position: u64,
+    #[serde(rename = "type")]
+    type_: Option<String>,
+}
+
+impl Token {
+    fn summary(&self) -> TokenSummary {
+        TokenSummary {
+            token: self.token.clone(),
+            position: self.position,
+            type_: self.type_.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TokenSummary {
+    token: String,
+    position: u64,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    type_: Option<String>,
+}
+
+/// Output of `_analyze` with `explain`: the steps of a custom analyzer, or a built-in analyzer
+#[derive(Deserialize)]
+struct AnalyzeDetail {
+    #[serde(default)]
+    charfilter: Vec<CharFilterStep>,
+    tokenizer: Option<TokenStep>,
+    #[serde(default)]
+    tokenfilters: Vec<TokenStep>,
+    analyzer: Option<TokenStep>,
+}
+
+#[derive(Deserialize)]
+struct CharFilterStep {
+    name: String,
+    #[serde(default)]
+    filtered_text: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenStep {
+    name: String,
+    #[serde(default)]
+    tokens: Vec<Token>,
+}
+
+impl AnalyzeDetail {
+    /// Tokens at the end of the analysis chain
+    fn final_tokens(&self) -> Vec<TokenSummary> {
+        self.analyzer
+            .iter()
+            .chain(self.tokenfilters.last())
+            .chain(self.tokenizer.iter())
+            .next()
+            .map(|step| step.tokens.iter().map(Token::summary).collect())
+            .unwrap_or_default()
+    }
+
+    /// Output of each step, with tokens as plain strings to keep it compact
+    fn steps(&self) -> Vec<Value> {
+        let token_step = |kind: &str, step: &TokenStep| {
+            json!({ "step": format!("{kind} {}", step.name), "tokens": step.tokens.iter().map(|t| &t.token).collect::<Vec<_>>() })
+        };
+        self.charfilter
+            .iter()
+            .map(|c| json!({ "step": format!("char_filter {}", c.name), "text": c.filtered_text }))
+            .chain(self.tokenizer.iter().map(|t| token_step("tokenizer", t)))
+            .chain(self.tokenfilters.iter().map(|t| token_step("filter", t)))
+            .chain(self.analyzer.iter().map(|a| token_step("analyzer", a)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analyzers_of_fields() {
+        let text: HashMap<String, Value> =
+            serde_json::from_value(json!({ "title": { "type": "text", "analyzer": "ik_max_word", "search_analyzer": "ik_smart" } }))
+                .unwrap();
+        let analyzers = field_analyzers("docs".to_string(), "content.title", &text);
+        assert_eq!(analyzers.analyzer(), "ik_max_word");
+        assert_eq!(analyzers.search_analyzer(), "ik_smart");
+
+        let default: HashMap<String, Value> = serde_json::from_value(json!({ "body": { "type": "text" } })).unwrap();
+        let analyzers = field_analyzers("docs".to_string(), "body", &default);
+        assert_eq!(analyzers.analyzer(), "standard");
+        assert_eq!(analyzers.search_analyzer(), "standard");
+
+        let keyword: HashMap<String, Value> =
+            serde_json::from_value(json!({ "code": { "type": "keyword", "normalizer": "lowercase" } })).unwrap();
+        assert_eq!(field_analyzers("docs".to_string(), "code", &keyword).analyzer(), "lowercase");
+    }
+
+    #[test]
+    fn explained_analysis() {
+        let detail: AnalyzeDetail = serde_json::from_value(json!({
+            "custom_analyzer": true,
+            "charfilter": [{ "name": "html_strip", "filtered_text": ["Bank of China"] }],
+            "tokenizer": { "name": "standard", "tokens": [
+                { "token": "Bank", "position": 0, "type": "<ALPHANUM>" },
+                { "token": "of", "position": 1, "type": "<ALPHANUM>" },
+                { "token": "China", "position": 2, "type": "<ALPHANUM>" },
+            ]},
+            "tokenfilters": [
+                { "name": "lowercase", "tokens": [
+                    { "token": "bank", "position": 0, "type": "<ALPHANUM>" },
+                    { "token": "of", "position": 1, "type": "<ALPHANUM>" },
+                    { "token": "china", "position": 2, "type": "<ALPHANUM>" },
+                ]},
+                { "name": "stop", "tokens": [
+                    { "token": "bank", "position": 0, "type": "<ALPHANUM>" },
+                    { "token": "china", "position": 2, "type": "<ALPHANUM>" },
+                ]},
+            ]
+        }))
+        .unwrap();
+
+        let tokens = detail.final_tokens();
+        assert_eq!(tokens.iter().map(|t| t.token.as_str()).collect::<Vec<_>>(), vec!["bank", "china"]);
+        assert_eq!(tokens[1].position, 2);
+
+        assert_eq!(
+            detail.steps(),
+            vec![
+                json!({ "step": "char_filter html_strip", "text": ["Bank of China"] }),
+                json!({ "step": "tokenizer standard", "tokens": ["Bank", "of", "China"] }),
+                json!({ "step": "filter lowercase", "tokens": ["bank", "of", "china"] }),
+                json!({ "step": "filter stop", "tokens": ["bank", "china"] }),
+            ]
+        );
+    }
+}
@@ -15,24 +15,34 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::servers::elasticsearch::{EsClientProvider, read_json};
+use crate::servers::elasticsearch::aliases::try_resolve_expression;
+use crate::servers::elasticsearch::compat::{ClusterInfo, ClusterInfoCache, OpenSearchQueryLanguage};
+use crate::servers::elasticsearch::data_streams::{fetch_data_streams, group_backing_indices};
+use crate::servers::elasticsearch::fields::{FieldsBackend, fetch_merged_fields};
+use crate::servers::elasticsearch::safety::IndexPolicy;
+use crate::servers::elasticsearch::{ElasticsearchMcpConfig, EsClientProvider, read_json};
 use elasticsearch::cat::{CatIndicesParts, CatShardsParts};
 use elasticsearch::cluster::ClusterHealthParts;
-use elasticsearch::indices::{IndicesGetMappingParts, IndicesGetTemplateParts};
+use elasticsearch::http::Method;
+use elasticsearch::http::headers::HeaderMap;
+use elasticsearch::http::request::JsonBody;
+use elasticsearch::indices::IndicesGetMappingParts;
+use elasticsearch::nodes::NodesInfoParts;
+use elasticsearch::params::Bytes;
 use elasticsearch::{Elasticsearch, SearchParts};
 use indexmap::IndexMap;
-use rmcp::handler::server::tool::{Parameters, ToolRouter};
+use rmcp::handler::server::tool::{Parameters, ToolCallContext, ToolRouter};
 use rmcp::model::{
-    CallToolResult, Content, Implementation, JsonObject, ProtocolVersion, ServerCapabilities, ServerInfo,
+    CallToolRequestParam, CallToolResult, Content, Implementation, JsonObject, ListToolsResult,
+    PaginatedRequestParam, ProtocolVersion, ServerCapabilities, ServerInfo,
 };
 use rmcp::service::RequestContext;
 use rmcp::{RoleServer, ServerHandler};
-use rmcp_macros::{tool, tool_handler, tool_router};
+use rmcp_macros::{tool, tool_router};
 use serde::{Deserialize, Serialize};
 use serde_aux::prelude::*;
 use serde_json::{Map, Value, json};
-use std::collections::HashMap;
-use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
 
 //------------------------------------------------------------------------------------------------
 // Safety limits (configurable via environment variables)
@@ -40,7 +50,7 @@ use regex::Regex;
 /// Maximum characters for a single tool response (default 8000 ≈ 4K tokens)
 /// A typical LLM session calls 3-8 tools; 8 × 8000 = 64K chars ≈ 32K tokens,
 /// leaving enough room for system prompt + reasoning within a 32K context.
-fn max_response_chars() -> usize {
+pub(crate) fn max_response_chars() -> usize {
     std::env::var("MCP_MAX_RESPONSE_CHARS")
         .ok()
         .and_then(|v| v.parse().ok())
@@ -48,7 +58,7 @@ fn max_response_chars() -> usize {
 }
 
 /// Maximum search size hard cap
-fn max_search_size() -> u64 {
+pub(crate) fn max_search_size() -> u64 {
     std::env::var("MCP_MAX_SEARCH_SIZE")
         .ok()
         .and_then(|v| v.parse().ok())
@@ -56,13 +66,134 @@ fn max_search_size() -> u64 {
 }
 
 /// Maximum index list entries
-fn max_index_list() -> usize {
+pub(crate) fn max_index_list() -> usize {
     std::env::var("MCP_MAX_INDEX_LIST")
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(100)
 }
 
+/// Maximum primary shards a template can set (`upsert_template`)
+pub(crate) fn max_template_shards() -> u64 {
+    std::env::var("MCP_TEMPLATE_MAX_SHARDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(50)
+}
+
+/// Minimum replicas a template must set (`upsert_template`)
+pub(crate) fn min_template_replicas() -> u64 {
+    std::env::var("MCP_TEMPLATE_MIN_REPLICAS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1)
+}
+
+/// Maximum `index.max_result_window` a template can set (`upsert_template`)
+pub(crate) fn max_template_result_window() -> u64 {
+    std::env::var("MCP_TEMPLATE_MAX_RESULT_WINDOW")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10_000_000)
+}
+
+/// Maximum number of documents a single `bulk_ingest` call can load
+pub(crate) fn max_bulk_docs() -> usize {
+    std::env::var("MCP_MAX_BULK_DOCS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10_000)
+}
+
+/// Check the shape of a query DSL `query`: an object with a single query clause, e.g.
+/// `{"match": {"title": "foo"}}`. Catches queries sent as strings or with several top-level clauses,
+/// that Elasticsearch would reject with a less helpful parsing error. Used by the tools that take a
+/// query alone (e.g. `count`, or writes of the documents matching a query), but not by `search` and
+/// `msearch` that forward query bodies as-is.
+pub(crate) fn check_query(query: &Value) -> Result<(), rmcp::Error> {
+    match query {
+        Value::Object(clauses) if clauses.len() == 1 && clauses.values().all(|v| v.is_object()) => Ok(()),
+        Value::Object(clauses) if clauses.len() > 1 => Err(rmcp::Error::invalid_params(
+            format!(
+                "The query has {} top-level clauses ({}): combine them with a 'bool' query.",
+                clauses.len(),
+                clauses.keys().map(|k| k.as_str()).collect::<Vec<_>>().join(", ")
+            ),
+            None,
+        )),
+        _ => Err(rmcp::Error::invalid_params(
+            "The query must be a query DSL object with a single clause, e.g. {\"match\": {\"title\": \"foo\"}} or {\"match_all\": {}}.",
+            None,
+        )),
+    }
+}
+
+/// Enforce the search size limit of a search request body and add the requested fields to `_source`
+pub(crate) fn prepare_search_body(query_body: &mut Map<String, Value>, fields: Option<Vec<String>>) {
+    // Enforce max size limit
+    let hard_max = max_search_size();
+    if let Some(Value::Number(n)) = query_body.get("size") {
+        if let Some(size) = n.as_u64() {
+            if size > hard_max {
+                query_body.insert("size".to_string(), json!(hard_max));
+            }
+        }
+    }
+
+    if let Some(fields) = fields {
+        // Augment _source if it exists
+        if let Some(Value::Array(values)) = query_body.get_mut("_source") {
+            for field in fields.into_iter() {
+                values.push(Value::String(field))
+            }
+        } else {
+            query_body.insert("_source".to_string(), json!(fields));
+        }
+    }
+}
+
+/// Shape a search response into a message with the total number of hits, the sources of hits and
+/// the aggregations, that share `max_chars` when both are present
+pub(crate) fn search_payload(response: &SearchResult, max_chars: usize) -> Result<Map<String, Value>, rmcp::Error> {
+    let mut payload = Map::new();
+    let max_chars = if response.hits.hits.is_empty() || response.aggregations.is_empty() {
+        max_chars
+    } else {
+        max_chars / 2
+    };
+
+    if response.aggregations.is_empty() || !response.hits.hits.is_empty() {
+        let total = response
+            .hits
+            .total
+            .as_ref()
+            .map(|t| t.value.to_string())
+            .unwrap_or("unknown".to_string());
+        payload.insert(
+            "message".to_string(),
+            Value::String(format!("Total results: {}, showing {}.", total, response.hits.hits.len())),
+        );
+    }
+
+    if !response.hits.hits.is_empty() {
+        let sources = response.hits.hits.iter().map(|hit| &hit.source).collect::<Vec<_>>();
+        let data = pack_json_value(&sources, max_chars)?;
+        payload.insert("hits".to_string(), data);
+    }
+
+    if !response.aggregations.is_empty() {
+        let data = pack_json_value(&response.aggregations, max_chars)?;
+        payload.insert("aggregations".to_string(), data);
+    }
+
+    Ok(payload)
+}
+
+/// Concrete indices the hits of a search come from, sorted by name
+fn hit_indices(response: &SearchResult) -> BTreeSet<&str> {
+    response.hits.hits.iter().filter_map(|hit| hit.index.as_deref()).collect()
+}
+
 /// Truncate a serialized JSON string if it exceeds max_chars, appending a hint.
 /// Returns a String for embedding in a JSON response (e.g. { data: { truncated, preview } }).
 fn maybe_truncate(json_str: String, max_chars: usize) -> String {
@@ -81,7 +212,7 @@ fn maybe_truncate(json_str: String, max_chars: usize) -> String {
     }
 }
 
-fn pack_json_value<T: Serialize>(data: &T, max_chars: usize) -> Result<Value, rmcp::Error> {
+pub(crate) fn pack_json_value<T: Serialize>(data: &T, max_chars: usize) -> Result<Value, rmcp::Error> {
     let json_str = serde_json::to_string(data)
         .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?;
     if json_str.len() <= max_chars {
@@ -96,17 +227,92 @@ fn pack_json_value<T: Serialize>(data: &T, max_chars: usize) -> Result<Value, rm
 
 #[derive(Clone)]
 pub struct EsBaseTools {
-    es_client: EsClientProvider,
+    pub(crate) es_client: EsClientProvider,
+    pub(crate) cluster: ClusterInfoCache,
+    pub(crate) delete_policy: IndexPolicy,
+    pub(crate) write_policy: IndexPolicy,
+    opensearch_query_language: Option<OpenSearchQueryLanguage>,
     tool_router: ToolRouter<EsBaseTools>,
 }
 
 impl EsBaseTools {
-    pub fn new(es_client: Elasticsearch) -> Self {
+    pub fn new(es_client: Elasticsearch, config: &ElasticsearchMcpConfig) -> Self {
+        let mut tool_router = Self::tool_router()
+            + Self::template_tools_router()
+            + Self::template_change_tools_router()
+            + Self::vitality_tools_router()
+            + Self::diagnostic_tools_router()
+            + Self::nodes_tools_router()
+            + Self::task_tools_router()
+            + Self::ilm_tools_router()
+            + Self::data_stream_tools_router()
+            + Self::alias_tools_router()
+            + Self::field_tools_router()
+            + Self::snapshot_tools_router()
+            + Self::ingest_tools_router()
+            + Self::index_management_tools_router()
+            + Self::document_tools_router()
+            + Self::bulk_tools_router()
+            + Self::by_query_tools_router()
+            + Self::reindex_tools_router()
+            + Self::query_tools_router()
+            + Self::analysis_tools_router();
+
+        if config.read_only {
+            // Tools that aren't annotated as read-only modify the cluster
+            tool_router
+                .map
+                .retain(|_, route| route.attr.annotations.as_ref().and_then(|a| a.read_only_hint) == Some(true));
+        }
+
         Self {
             es_client: EsClientProvider::new(es_client),
-            tool_router: Self::tool_router(),
+            cluster: ClusterInfoCache::default(),
+            delete_policy: IndexPolicy::new(
+                "delete",
+                config.allow_delete_patterns.clone(),
+                config.deny_delete_patterns.clone(),
+            ),
+            write_policy: IndexPolicy::new(
+                "write",
+                config.allow_write_patterns.clone(),
+                config.deny_write_patterns.clone(),
+            ),
+            opensearch_query_language: config.opensearch_query_language,
+            tool_router,
         }
     }
+
+    /// Detect the cluster version using the credentials from the configuration. If this fails
+    /// (e.g. credentials are only provided by http requests), detection will happen on the first request.
+    pub async fn detect_cluster(&self) {
+        self.cluster
+            .get_or_detect(EsClientProvider::DEFAULT_CLIENT_KEY, self.es_client.default_client())
+            .await;
+    }
+
+    /// Get the cluster information, detecting it with the request's credentials if needed.
+    async fn cluster_info(&self, req_ctx: &RequestContext<RoleServer>) -> Option<ClusterInfo> {
+        let client_key = self.es_client.key(req_ctx);
+        if let Some(info) = self.cluster.get(client_key) {
+            return Some(info);
+        }
+        let es_client = self.es_client.get(req_ctx.clone());
+        self.cluster.get_or_detect(client_key, &es_client).await
+    }
+
+    /// The cluster information detected for the request's credentials, if any.
+    pub(crate) fn cached_cluster_info(&self, req_ctx: &RequestContext<RoleServer>) -> Option<ClusterInfo> {
+        self.cluster.get(self.es_client.key(req_ctx))
+    }
+
+    /// Can the cluster serve this tool? On OpenSearch, `esql` is available if routed to SQL or PPL.
+    fn is_tool_available(&self, info: &ClusterInfo, tool: &str) -> bool {
+        if tool == "esql" && info.is_opensearch() {
+            return self.opensearch_query_language.is_some();
+        }
+        info.is_tool_supported(tool)
+    }
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -117,13 +323,19 @@ struct ListIndicesParams {
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 struct GetMappingsParams {
-    /// Name of the Elasticsearch index to get mappings for
+    /// Name of the Elasticsearch index, alias, data stream or index pattern to get mappings for
     index: String,
+    /// Merge the mappings of all matching indices into a single field list, flagging fields whose
+    /// type differs between indices (default: false)
+    merge: Option<bool>,
+    /// Source of merged field lists: 'mappings' (default) or 'field_caps' (cheaper on large clusters).
+    /// Implies merge.
+    backend: Option<FieldsBackend>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 struct SearchParams {
-    /// Name of the Elasticsearch index to search
+    /// Name of the Elasticsearch index, alias, data stream or index pattern to search
     index: String,
 
     /// Name of the fields that need to be returned (optional)
@@ -162,6 +374,9 @@ struct ListIndicesDetailedParams {
     pub health: Option<String>,
     /// Sort by field (docs.count, store.size)
     pub sort_by: Option<String>,
+    /// Group the backing indices of data streams into a single row per data stream, named after the
+    /// data stream, with summed document counts and sizes in bytes (default: false)
+    pub group_data_streams: Option<bool>,
 }
 
 fn default_index_pattern() -> String {
@@ -170,18 +385,61 @@ fn default_index_pattern() -> String {
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 struct NodesInfoParams {
-    /// Optional node ID to filter (default: _all)
+    /// Optional node filter: comma-separated node IDs or names (supports * wildcard), or node specifications
+    /// such as _master, _local or data:true (default: _all)
     node_id: Option<String>,
-    /// Optional metrics to return (heap, cpu, load, etc.)
+    /// Optional comma-separated metrics to return (heap, ram, cpu, load, disk, roles, ip, version, uptime, jdk). Defaults to heap, ram, cpu, load, roles and ip.
     metrics: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-struct GetTemplatesParams {
-    /// Optional template name filter (supports * wildcard)
-    name: Option<String>,
-    /// Optional index name to find which template(s) match it
-    matching_index: Option<String>,
+/// `_cat/nodes` columns for each metric of `get_nodes_info`
+const NODE_METRICS: &[(&str, &[&str])] = &[
+    ("heap", &["heap.percent", "heap.current", "heap.max"]),
+    ("ram", &["ram.percent"]),
+    ("cpu", &["cpu"]),
+    ("load", &["load_1m", "load_5m", "load_15m"]),
+    ("disk", &["disk.used_percent", "disk.avail", "disk.total"]),
+    ("roles", &["node.role", "master"]),
+    ("ip", &["ip"]),
+    ("version", &["version"]),
+    ("uptime", &["uptime"]),
+    ("jdk", &["jdk"]),
+];
+
+/// Default columns, to match the plan example: "name,ip,heap.percent,ram.percent,cpu,load_1m,node.role,master"
+const DEFAULT_NODE_COLUMNS: &[&str] = &[
+    "id", "name", "ip", "heap.percent", "ram.percent", "cpu", "load_1m", "node.role", "master",
+];
+
+/// Keep the `_cat/nodes` rows of the nodes whose ID is a key of `selected`
+fn retain_nodes<T>(nodes: &mut Vec<Map<String, Value>>, selected: &HashMap<String, T>) {
+    nodes.retain(|node| node.get("id").and_then(|id| id.as_str()).is_some_and(|id| selected.contains_key(id)));
+}
+
+/// Map a comma-separated list of metrics to `_cat/nodes` columns
+fn node_info_columns(metrics: Option<&str>) -> Result<Vec<&'static str>, rmcp::Error> {
+    let Some(metrics) = metrics.filter(|m| !m.trim().is_empty()) else {
+        return Ok(DEFAULT_NODE_COLUMNS.to_vec());
+    };
+
+    let mut columns = vec!["id", "name"];
+    for metric in metrics.split(',').map(|m| m.trim().to_lowercase()) {
+        let Some((_, metric_columns)) = NODE_METRICS.iter().find(|(name, _)| *name == metric) else {
+            return Err(rmcp::Error::invalid_params(
+                format!(
+                    "Unknown metric '{metric}'. Valid metrics: {}.",
+                    NODE_METRICS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+                ),
+                None,
+            ));
+        };
+        for column in metric_columns.iter() {
+            if !columns.contains(column) {
+                columns.push(column);
+            }
+        }
+    }
+    Ok(columns)
 }
 
 #[tool_router]
@@ -189,7 +447,7 @@ impl EsBaseTools {
     //---------------------------------------------------------------------------------------------
     /// Tool: list indices (detailed)
     #[tool(
-        description = "List Elasticsearch indices with detailed health and size information",
+        description = "List Elasticsearch indices with detailed health and size information, optionally grouping the backing indices of data streams",
         annotations(title = "List indices (detailed)", read_only_hint = true)
     )]
     async fn list_indices_detailed(
@@ -220,6 +478,12 @@ impl EsBaseTools {
              indices_request = indices_request.s(&sort_arr);
         }
         
+        // Sizes are summed when grouping data streams
+        let group_data_streams = params.group_data_streams.unwrap_or(false);
+        if group_data_streams {
+            indices_request = indices_request.bytes(Bytes::B);
+        }
+
         let response = indices_request
             .h(&["index", "health", "status", "pri", "rep", "docs.count", "store.size", "pri.store.size"])
             .format("json")
@@ -228,6 +492,15 @@ impl EsBaseTools {
 
         let mut response: Vec<serde_json::Value> = read_json(response).await?;
 
+        if group_data_streams {
+            // Rows stay ungrouped if no data stream matches (a 404 for a plain index name) or if the
+            // cluster doesn't support data streams
+            match fetch_data_streams(&es_client, &params.index_pattern).await {
+                Ok(data_streams) => response = group_backing_indices(response, &data_streams),
+                Err(e) => tracing::debug!("No data streams to group indices: {}", e.message),
+            }
+        }
+
         let total_count = response.len();
         let max_list = max_index_list();
         let truncated = total_count > max_list;
@@ -276,15 +549,20 @@ impl EsBaseTools {
     //---------------------------------------------------------------------------------------------
     /// Tool: get mappings for an index
     #[tool(
-        description = "Get field mappings for a specific Elasticsearch index",
+        description = "Get field mappings for a specific Elasticsearch index. Aliases, data streams and patterns are resolved, and the concrete indices they target are reported. With merge, returns a single field list across all matching indices, with fields whose type conflicts between indices.",
         annotations(title = "Get ES index mappings", read_only_hint = true)
     )]
     async fn get_mappings(
         &self,
         req_ctx: RequestContext<RoleServer>,
-        Parameters(GetMappingsParams { index }): Parameters<GetMappingsParams>,
+        Parameters(GetMappingsParams { index, merge, backend }): Parameters<GetMappingsParams>,
     ) -> Result<CallToolResult, rmcp::Error> {
         let es_client = self.es_client.get(req_ctx);
+
+        if merge.unwrap_or(false) || backend.is_some() {
+            return merged_mappings(&es_client, &index, backend.unwrap_or_default()).await;
+        }
+
         let response = es_client
             .indices()
             .get_mapping(IndicesGetMappingParts::Index(&[&index]))
@@ -292,18 +570,34 @@ impl EsBaseTools {
             .await;
 
         let response: MappingResponse = read_json(response).await?;
+        let resolved = try_resolve_expression(&es_client, &index).await;
 
-        // use the first mapping (we can have many if the name is a wildcard)
-        let mapping = response.values().next()
+        // Several indices match aliases, data streams and patterns: use the write index of data
+        // streams, or else the last one by name, usually the most recent
+        let write_indices = resolved.as_ref().map(|r| r.write_indices()).unwrap_or_default();
+        let (name, mapping) = response
+            .iter()
+            .max_by_key(|(name, _)| (write_indices.contains(&name.as_str()), name.as_str()))
             .ok_or_else(|| rmcp::Error::internal_error(
                 "No mapping found for the specified index. Please verify the index name exists.",
                 None,
             ))?;
 
+        let message = if response.len() > 1 {
+            format!(
+                "Mappings of index {name}, out of {} indices matching {index}. Mappings of the other indices may differ.",
+                response.len()
+            )
+        } else {
+            format!("Mappings for index {}.", index)
+        };
+
         let data = pack_json_value(mapping, max_response_chars())?;
-        Ok(CallToolResult::success(vec![
-            Content::json(json!({ "message": format!("Mappings for index {}.", index), "data": data }))?
-        ]))
+        let mut result = json!({ "message": message, "data": data });
+        if let Some(resolved) = resolved {
+            result["resolved"] = resolved.summary();
+        }
+        Ok(CallToolResult::success(vec![Content::json(result)?]))
     }
 
     //---------------------------------------------------------------------------------------------
@@ -312,7 +606,7 @@ impl EsBaseTools {
     /// The additional 'fields' parameter helps some LLMs that don't know about the `_source`
     /// request property to narrow down the data returned and reduce their context size
     #[tool(
-        description = "Perform an Elasticsearch search with the provided query DSL.",
+        description = "Perform an Elasticsearch search with the provided query DSL. When searching aliases, data streams or patterns, the concrete indices of the hits are reported.",
         annotations(title = "Elasticsearch search DSL query", read_only_hint = true)
     )]
     async fn search(
@@ -327,27 +621,7 @@ impl EsBaseTools {
         let es_client = self.es_client.get(req_ctx);
 
         let mut query_body = query_body;
-
-        // Enforce max size limit
-        let hard_max = max_search_size();
-        if let Some(Value::Number(n)) = query_body.get("size") {
-            if let Some(size) = n.as_u64() {
-                if size > hard_max {
-                    query_body.insert("size".to_string(), json!(hard_max));
-                }
-            }
-        }
-
-        if let Some(fields) = fields {
-            // Augment _source if it exists
-            if let Some(Value::Array(values)) = query_body.get_mut("_source") {
-                for field in fields.into_iter() {
-                    values.push(Value::String(field))
-                }
-            } else {
-                query_body.insert("_source".to_string(), json!(fields));
-            }
-        }
+        prepare_search_body(&mut query_body, fields);
 
         let response = es_client
             .search(SearchParts::Index(&[&index]))
@@ -357,30 +631,12 @@ impl EsBaseTools {
 
         let response: SearchResult = read_json(response).await?;
 
-        let max_chars = max_response_chars();
-        let mut payload = Map::new();
-
-        if response.aggregations.is_empty() || !response.hits.hits.is_empty() {
-            let total = response
-                .hits
-                .total
-                .map(|t| t.value.to_string())
-                .unwrap_or("unknown".to_string());
-            payload.insert(
-                "message".to_string(),
-                Value::String(format!("Total results: {}, showing {}.", total, response.hits.hits.len())),
-            );
-        }
-
-        if !response.hits.hits.is_empty() {
-            let sources = response.hits.hits.iter().map(|hit| &hit.source).collect::<Vec<_>>();
-            let data = pack_json_value(&sources, max_chars)?;
-            payload.insert("hits".to_string(), data);
-        }
-
-        if !response.aggregations.is_empty() {
-            let data = pack_json_value(&response.aggregations, max_chars)?;
-            payload.insert("aggregations".to_string(), data);
+        let mut payload = search_payload(&response, max_response_chars())?;
+        // Report the concrete indices of the hits when they differ from the requested name (aliases,
+        // data streams and patterns), rather than resolving the expression with another request
+        let hit_indices = hit_indices(&response);
+        if !hit_indices.is_empty() && hit_indices.iter().any(|i| *i != index) {
+            payload.insert("indices".to_string(), json!(hit_indices));
         }
 
         Ok(CallToolResult::success(vec![Content::json(Value::Object(payload))?]))
@@ -397,22 +653,21 @@ impl EsBaseTools {
         req_ctx: RequestContext<RoleServer>,
         Parameters(EsqlQueryParams { query }): Parameters<EsqlQueryParams>,
     ) -> Result<CallToolResult, rmcp::Error> {
+        let cluster = self.cached_cluster_info(&req_ctx);
         let es_client = self.es_client.get(req_ctx);
 
+        if let Some(info) = cluster
+            && info.is_opensearch()
+        {
+            return self.opensearch_query(&es_client, query).await;
+        }
+
         let request = EsqlQueryRequest { query };
 
         let response = es_client.esql().query().body(request).send().await;
         let response: EsqlQueryResponse = read_json(response).await?;
 
-        // Transform response into an array of objects
-        let mut objects: Vec<Value> = Vec::new();
-        for row in response.values.into_iter() {
-            let mut obj = Map::new();
-            for (i, value) in row.into_iter().enumerate() {
-                obj.insert(response.columns[i].name.clone(), value);
-            }
-            objects.push(Value::Object(obj));
-        }
+        let objects = rows_to_objects(&response.columns, response.values);
 
         let data = pack_json_value(&objects, max_response_chars())?;
         Ok(CallToolResult::success(vec![
@@ -544,8 +799,12 @@ impl EsBaseTools {
 
     //---------------------------------------------------------------------------------------------
     /// Tool: Get detailed information about Elasticsearch cluster nodes
+    ///
+    /// # Arguments
+    /// * `node_id` - Optional node ID or name to filter (supports * wildcard)
+    /// * `metrics` - Optional comma-separated metrics, see `NODE_METRICS`
     #[tool(
-        description = "Get detailed information about Elasticsearch cluster nodes",
+        description = "Get detailed information about Elasticsearch cluster nodes. Metrics can be selected among: heap, ram, cpu, load, disk, roles, ip, version, uptime, jdk.",
         annotations(title = "Get nodes info", read_only_hint = true)
     )]
     async fn get_nodes_info(
@@ -554,174 +813,140 @@ impl EsBaseTools {
         Parameters(params): Parameters<NodesInfoParams>,
     ) -> Result<CallToolResult, rmcp::Error> {
         let es_client = self.es_client.get(req_ctx);
-        
+
         // We use the CAT Nodes API to get tabular information which is often more useful for diagnostics
         // like in the ansible playbook example
-        let cat = es_client.cat();
-        let nodes_request = cat.nodes();
-        
-        // Default headers to match the plan example
-        // "name,ip,heap.percent,ram.percent,cpu,load_1m,node.role,master"
-        let headers = vec![
-            "name", "ip", "heap.percent", "ram.percent", "cpu", "load_1m", "node.role", "master"
-        ];
-
-        if let Some(_metrics) = &params.metrics {
-            // If metrics are provided, we could try to map them to headers, 
-            // but for now we'll stick to the default set or append? 
-            // The plan says metrics="heap,cpu,load", which implies we might want to filter.
-            // For simplicity and matching the CAT API behavior, we'll keep the standard useful set
-            // or perhaps allow extending it if needed. 
-            // But let's stick to the plan's return example which shows these fields.
-        }
-        
-        let _ = &params.node_id; // Suppress warning
+        let headers = node_info_columns(params.metrics.as_deref())?;
 
-        let response = nodes_request
+        let response = es_client
+            .cat()
+            .nodes()
             .h(&headers)
+            .full_id(true)
             .format("json")
             .send()
             .await;
-            
-        let nodes: serde_json::Value = read_json(response).await?;
-        
-        // The plan example shows a dictionary keyed by node name, but CAT API returns a list.
-        // We can transform it or just return the list.
-        // Return example in plan: {"nodes": {"es7-node1": {...}}}
-        // CAT API response: [{"name": "es7-node1", ...}, ...]
-        // Let's return the list directly as it's more standard for tools to return what the API gives,
-        // unless we strictly need to match the specific format. 
-        // Given this is a new tool replacing Ansible, a list is fine and easier to process usually.
-        // However, to be helpful, let's wrap it.
-        
-        let data = pack_json_value(&json!({ "nodes": nodes }), max_response_chars())?;
-        Ok(CallToolResult::success(vec![
-            Content::json(json!({ "message": "Nodes info.", "data": data }))?
-        ]))
-    }
 
-    //---------------------------------------------------------------------------------------------
-    /// Tool: Get index templates
-    ///
-    /// Comprehensive template query tool that supports:
-    /// - Filtering by template name (with wildcard support)
-    /// - Finding which template(s) match a specific index name
-    ///
-    /// # Arguments
-    /// * `name` - Optional template name filter (supports * wildcard)
-    /// * `matching_index` - Optional index name to find matching templates
-    ///
-    /// # Returns
-    /// Template definitions with matching logic applied
-    #[tool(
-        description = "Get index templates with optional filtering by name or matching index. Supports wildcard patterns and can determine which template applies to a specific index.",
-        annotations(title = "Get index templates", read_only_hint = true)
-    )]
-    async fn get_templates(
-        &self,
-        req_ctx: RequestContext<RoleServer>,
-        Parameters(params): Parameters<GetTemplatesParams>,
-    ) -> Result<CallToolResult, rmcp::Error> {
-        let es_client = self.es_client.get(req_ctx);
-        
-        // Determine the template name pattern to query
-        let template_name = params.name.as_deref().unwrap_or("*");
-        
-        // Get templates from Elasticsearch
-        let response = es_client
-            .indices()
-            .get_template(IndicesGetTemplateParts::Name(&[template_name]))
-            .send()
-            .await;
-        
-        let templates: HashMap<String, TemplateDefinition> = read_json(response).await?;
-        
-        // If matching_index is specified, filter and sort templates by matching logic
-        if let Some(index_name) = params.matching_index {
-            let matching_templates = self.find_matching_templates(&templates, &index_name);
-            
-            if matching_templates.is_empty() {
-                return Ok(CallToolResult::success(vec![
-                    Content::json(json!({ "message": format!("No templates match index '{}'.", index_name), "data": {} }))?
-                ]));
+        let mut nodes: Vec<Map<String, Value>> = read_json(response).await?;
+
+        // The CAT API can't filter nodes: resolve the node specification with the nodes info API,
+        // that supports IDs, names, wildcards and filters like `_master` or `data:true`
+        if let Some(node_id) = params.node_id.as_deref()
+            && node_id != "_all"
+        {
+            let node_ids = node_id.split(',').map(|id| id.trim()).collect::<Vec<_>>();
+            let response = es_client
+                .nodes()
+                .info(NodesInfoParts::NodeIdMetric(&node_ids, &["_none"]))
+                .send()
+                .await;
+            let response: NodesInfoResponse = read_json(response).await?;
+            retain_nodes(&mut nodes, &response.nodes);
+            if nodes.is_empty() {
+                return Err(rmcp::Error::invalid_params(
+                    format!("No node matches '{node_id}'."),
+                    None,
+                ));
             }
-            
-            // Return templates sorted by order (highest priority first)
-            let result: HashMap<String, &TemplateDefinition> = matching_templates
-                .into_iter()
-                .map(|(name, template)| (name.to_string(), template))
-                .collect();
-            
-            let data = pack_json_value(&result, max_response_chars())?;
-            let message = format!(
-                "Found {} template(s) matching index '{}' (sorted by priority).",
-                result.len(), index_name
-            );
-            return Ok(CallToolResult::success(vec![
-                Content::json(json!({ "message": message, "data": data }))?
-            ]));
         }
-        
-        // Return all templates (or filtered by name)
-        let data = pack_json_value(&templates, max_response_chars())?;
+
+        let data = pack_json_value(&json!({ "nodes": nodes }), max_response_chars())?;
         Ok(CallToolResult::success(vec![
-            Content::json(json!({ "message": format!("Found {} template(s).", templates.len()), "data": data }))?
+            Content::json(json!({ "message": format!("Found {} node(s).", nodes.len()), "data": data }))?
         ]))
     }
 }
 
 impl EsBaseTools {
-    /// Find templates that match the given index name
-    /// Returns templates sorted by order (highest first)
-    fn find_matching_templates<'a>(
-        &self,
-        templates: &'a HashMap<String, TemplateDefinition>,
-        index_name: &str,
-    ) -> Vec<(&'a str, &'a TemplateDefinition)> {
-        let mut matching: Vec<(&str, &TemplateDefinition)> = templates
-            .iter()
-            .filter(|(_, template)| {
-                template.index_patterns.iter().any(|pattern| {
-                    self.matches_pattern(index_name, pattern)
-                })
-            })
-            .map(|(name, template)| (name.as_str(), template))
-            .collect();
-        
-        // Sort by order (descending - higher order = higher priority)
-        matching.sort_by(|a, b| {
-            let order_a = a.1.order.unwrap_or(0);
-            let order_b = b.1.order.unwrap_or(0);
-            order_b.cmp(&order_a)
-        });
-        
-        matching
-    }
-    
-    /// Check if an index name matches a pattern (supports * wildcard)
-    fn matches_pattern(&self, index_name: &str, pattern: &str) -> bool {
-        // Convert ES wildcard pattern to regex
-        // Escape special regex chars except *
-        let escaped = regex::escape(pattern).replace(r"\*", ".*");
-        let regex_pattern = format!("^{}$", escaped);
-        
-        if let Ok(re) = Regex::new(&regex_pattern) {
-            re.is_match(index_name)
-        } else {
-            false
-        }
+    /// Run a query with the OpenSearch SQL or PPL plugin, in place of an ES|QL query
+    async fn opensearch_query(&self, es_client: &Elasticsearch, query: String) -> Result<CallToolResult, rmcp::Error> {
+        let Some(language) = self.opensearch_query_language else {
+            return Err(rmcp::Error::invalid_params(
+                "ES|QL is not supported by OpenSearch. Set 'opensearch_query_language' to 'sql' or 'ppl' in the configuration to route queries to OpenSearch.",
+                None,
+            ));
+        };
+
+        let response = es_client
+            .send(
+                Method::Post,
+                language.endpoint(),
+                HeaderMap::new(),
+                Option::<&()>::None,
+                Some(JsonBody::new(json!({ "query": query }))),
+                None,
+            )
+            .await;
+        let response: OpenSearchQueryResponse = read_json(response).await?;
+
+        let objects = rows_to_objects(&response.schema, response.datarows);
+
+        let data = pack_json_value(&objects, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Results (OpenSearch {language})."), "data": data }))?
+        ]))
     }
 }
 
-#[tool_handler]
 impl ServerHandler for EsBaseTools {
     fn get_info(&self) -> ServerInfo {
+        let mut instructions = "Provides access to Elasticsearch".to_string();
+        if let Some(info) = self.cluster.get(EsClientProvider::DEFAULT_CLIENT_KEY) {
+            instructions.push_str(". ");
+            instructions.push_str(&info.describe());
+
+            let mut unavailable = self
+                .tool_router
+                .list_all()
+                .into_iter()
+                .filter(|tool| !self.is_tool_available(&info, &tool.name))
+                .map(|tool| tool.name)
+                .collect::<Vec<_>>();
+            unavailable.sort();
+            if !unavailable.is_empty() {
+                instructions.push_str(&format!(" Tools not available on this cluster: {}.", unavailable.join(", ")));
+            }
+            if info.is_opensearch() && let Some(language) = self.opensearch_query_language {
+                instructions.push_str(&format!(" The esql tool runs OpenSearch {language} queries."));
+            }
+        }
         ServerInfo {
             protocol_version: ProtocolVersion::V_2025_03_26,
             capabilities: ServerCapabilities::builder().enable_tools().build(),
             server_info: Implementation::from_build_env(),
-            instructions: Some("Provides access to Elasticsearch".to_string()),
+            instructions: Some(instructions),
+        }
+    }
+
+    // Same as what #[tool_handler] generates, hiding tools that the cluster can't serve.
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        if let Some(info) = self.cluster_info(&context).await
+            && !self.is_tool_available(&info, &request.name)
+        {
+            return Err(rmcp::Error::invalid_params(
+                format!("Tool '{}' is not supported by {} {}", request.name, info.distribution, info.version),
+                None,
+            ));
+        }
+        let tcc = ToolCallContext::new(self, request, context);
+        self.tool_router.call(tcc).await
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, rmcp::Error> {
+        let mut tools = self.tool_router.list_all();
+        if let Some(info) = self.cluster_info(&context).await {
+            tools.retain(|tool| self.is_tool_available(&info, &tool.name));
         }
+        Ok(ListToolsResult::with_all_items(tools))
     }
 }
 
@@ -744,16 +969,43 @@ pub struct Hits {
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(from = "TotalHitsRepr")]
 pub struct TotalHits {
     pub value: u64,
 }
 
+/// `hits.total` is an integer on OpenSearch 1.x (and Elasticsearch with `rest_total_hits_as_int`)
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TotalHitsRepr {
+    Count(u64),
+    Object { value: u64 },
+}
+
+impl From<TotalHitsRepr> for TotalHits {
+    fn from(repr: TotalHitsRepr) -> Self {
+        match repr {
+            TotalHitsRepr::Count(value) | TotalHitsRepr::Object { value } => TotalHits { value },
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Hit {
+    #[serde(rename = "_index", default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    #[serde(rename = "_id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     #[serde(rename = "_source")]
     pub source: Value,
 }
 
+/// Responses of `_msearch`: search responses, or errors
+#[derive(Deserialize)]
+pub struct MsearchResponse {
+    pub responses: Vec<Value>,
+}
+
 //----- Cat responses
 
 #[derive(Serialize, Deserialize)]
@@ -777,6 +1029,15 @@ pub struct CatShardsResponse {
     pub node: Option<String>,
 }
 
+//----- Nodes info
+
+/// Nodes matching a node specification, by ID
+#[derive(Deserialize)]
+struct NodesInfoResponse {
+    #[serde(default)]
+    nodes: HashMap<String, Value>,
+}
+
 //----- Index mappings
 
 pub type MappingResponse = HashMap<String, Mappings>;
@@ -799,12 +1060,55 @@ pub struct Mapping {
 
 #[derive(Serialize, Deserialize)]
 pub struct MappingProperty {
-    #[serde(rename = "type")]
-    pub type_: String,
+    /// Absent for object fields
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
     #[serde(flatten)]
     pub settings: HashMap<String, serde_json::Value>,
 }
 
+impl MappingProperty {
+    /// The field type, `object` if not set explicitly
+    pub fn field_type(&self) -> &str {
+        self.type_.as_deref().unwrap_or("object")
+    }
+}
+
+/// Mappings of all indices matching an index expression, merged into a single field list
+async fn merged_mappings(
+    es_client: &Elasticsearch,
+    index: &str,
+    backend: FieldsBackend,
+) -> Result<CallToolResult, rmcp::Error> {
+    let merged = fetch_merged_fields(es_client, index, backend).await?;
+
+    let conflicts = merged.conflicts().map(|f| f.field.as_str()).collect::<Vec<_>>();
+    let mut message = format!(
+        "{} fields across {} indices matching {index}, {} with conflicting types.",
+        merged.fields.len(),
+        merged.index_count,
+        conflicts.len()
+    );
+    if !conflicts.is_empty() {
+        message.push_str(&format!(
+            " Aggregations and sorts on conflicting fields ({}) may fail or give partial results.",
+            conflicts.join(", ")
+        ));
+    }
+
+    // Conflicts first, as they would be lost if the response is truncated
+    let (conflicts, others): (Vec<_>, Vec<_>) = merged.fields.iter().partition(|f| f.is_conflict());
+    let fields = others
+        .iter()
+        .map(|f| (f.field.as_str(), f.types.join(",")))
+        .collect::<IndexMap<_, _>>();
+
+    let data = pack_json_value(&json!({ "conflicts": conflicts, "fields": fields }), max_response_chars())?;
+    Ok(CallToolResult::success(vec![
+        Content::json(json!({ "message": message, "data": data }))?
+    ]))
+}
+
 //----- ES|QL
 
 #[derive(Serialize, Deserialize)]
@@ -826,29 +1130,96 @@ pub struct EsqlQueryResponse {
     pub values: Vec<Vec<Value>>,
 }
 
-//----- Index Templates
-
+/// Response of the OpenSearch SQL and PPL plugins (default `jdbc` format)
 #[derive(Serialize, Deserialize)]
-pub struct TemplateDefinition {
-    /// Index patterns that this template applies to
-    pub index_patterns: Vec<String>,
-    
-    /// Template priority order (higher = higher priority)
-    pub order: Option<i32>,
-    
-    /// Index settings
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub settings: Option<Value>,
-    
-    /// Index mappings
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub mappings: Option<Value>,
-    
-    /// Index aliases
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub aliases: Option<Value>,
-    
-    /// Template version
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub version: Option<i64>,
+pub struct OpenSearchQueryResponse {
+    pub schema: Vec<Column>,
+    pub datarows: Vec<Vec<Value>>,
+}
+
+/// Transform tabular query results into an array of objects
+fn rows_to_objects(columns: &[Column], rows: Vec<Vec<Value>>) -> Vec<Value> {
+    let mut objects: Vec<Value> = Vec::new();
+    for row in rows.into_iter() {
+        let mut obj = Map::new();
+        for (i, value) in row.into_iter().enumerate() {
+            obj.insert(columns[i].name.clone(), value);
+        }
+        objects.push(Value::Object(obj));
+    }
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_checks() {
+        assert!(check_query(&json!({ "match_all": {} })).is_ok());
+        assert!(check_query(&json!({ "bool": { "filter": [{ "term": { "status": "pending" } }] } })).is_ok());
+        assert!(check_query(&json!({ "term": { "a": 1 }, "range": { "b": { "gt": 1 } } })).is_err());
+        assert!(check_query(&json!("status:pending")).is_err());
+        assert!(check_query(&json!({})).is_err());
+    }
+
+    #[test]
+    fn node_metrics() {
+        assert_eq!(node_info_columns(None).unwrap(), DEFAULT_NODE_COLUMNS);
+        assert_eq!(node_info_columns(Some(" ")).unwrap(), DEFAULT_NODE_COLUMNS);
+        assert_eq!(
+            node_info_columns(Some("heap, CPU,heap")).unwrap(),
+            vec!["id", "name", "heap.percent", "heap.current", "heap.max", "cpu"]
+        );
+        let error = node_info_columns(Some("heap,threads")).unwrap_err();
+        assert!(error.message.contains("Unknown metric 'threads'"));
+    }
+
+    #[test]
+    fn nodes_filtered_by_id() {
+        let mut nodes = ["abc", "def", "ghi"]
+            .iter()
+            .map(|id| json!({ "id": id, "name": format!("node-{id}") }).as_object().unwrap().clone())
+            .collect::<Vec<_>>();
+        let selected = HashMap::from([("def".to_string(), json!({ "name": "node-def" }))]);
+
+        retain_nodes(&mut nodes, &selected);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0]["name"], "node-def");
+    }
+
+    #[test]
+    fn total_hits_as_object_or_integer() {
+        let result: SearchResult = serde_json::from_value(json!({
+            "hits": { "total": { "value": 42, "relation": "eq" }, "hits": [] }
+        }))
+        .unwrap();
+        assert_eq!(result.hits.total.unwrap().value, 42);
+
+        // OpenSearch 1.x and rest_total_hits_as_int
+        let result: SearchResult = serde_json::from_value(json!({
+            "hits": { "total": 42, "max_score": null, "hits": [{ "_index": "logs", "_id": "1", "_source": {} }] }
+        }))
+        .unwrap();
+        assert_eq!(result.hits.total.unwrap().value, 42);
+        assert_eq!(result.hits.hits[0].index.as_deref(), Some("logs"));
+    }
+
+    #[test]
+    fn hits_and_aggregations_share_budget() {
+        let text = "a".repeat(120);
+        let result: SearchResult = serde_json::from_value(json!({
+            "hits": { "total": 1, "hits": [{ "_source": { "text": text } }] },
+            "aggregations": { "texts": { "buckets": [{ "key": text }] } }
+        }))
+        .unwrap();
+
+        let payload = search_payload(&result, 400).unwrap();
+        assert!(payload["hits"].is_array() && payload["aggregations"].is_object());
+        assert!(payload["aggregations"].get("truncated").is_none());
+
+        let payload = search_payload(&result, 200).unwrap();
+        assert_eq!(payload["hits"]["truncated"], true);
+        assert_eq!(payload["aggregations"]["truncated"], true);
+    }
 }
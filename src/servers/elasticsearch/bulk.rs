@@ -0,0 +1,532 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Bulk ingestion of documents provided as a JSON array, NDJSON or CSV text.
+//!
+//! Documents are sent in chunks with the `_bulk` API, and failed items are summarized by error
+//! type instead of returning the raw bulk responses.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_bulk_docs, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::safety::{check_confirmation_token, confirmation_token};
+use elasticsearch::BulkParts;
+use elasticsearch::http::request::JsonBody;
+use elasticsearch::params::Refresh;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Default maximum number of documents in a `_bulk` request
+const DEFAULT_CHUNK_DOCS: usize = 1_000;
+
+/// Default maximum size of the documents in a `_bulk` request
+const DEFAULT_CHUNK_BYTES: usize = 5 * 1024 * 1024;
+
+/// Number of documents shown in the preview
+const SAMPLE_DOCS: usize = 3;
+
+/// Number of document ids listed for each error type
+const MAX_ERROR_IDS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum DataFormat {
+    /// A JSON array of objects
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Comma-separated values, with a header line
+    Csv,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct BulkIngestParams {
+    /// Index, alias or data stream to load documents into
+    index: String,
+    /// Documents, as JSON objects
+    documents: Option<Vec<Map<String, Value>>>,
+    /// Documents as text, in the format set by `format`, if `documents` isn't provided
+    data: Option<String>,
+    /// Format of `data`: json (array), ndjson or csv (with a header line). Detected if not provided.
+    format: Option<DataFormat>,
+    /// Parse CSV numbers and booleans instead of keeping all values as strings (default: true).
+    /// Values with leading zeros, like zip codes, are kept as strings.
+    csv_infer_types: Option<bool>,
+    /// Field whose value is used as the document `_id`. Ids are generated if not provided.
+    id_field: Option<String>,
+    /// Replace existing documents with the same id instead of reporting a conflict (default: false)
+    overwrite: Option<bool>,
+    /// Ingest pipeline to process documents with
+    pipeline: Option<String>,
+    /// Maximum number of documents per bulk request (default: 1000)
+    chunk_docs: Option<usize>,
+    /// Maximum size in bytes of the documents of a bulk request (default: 5MB)
+    chunk_bytes: Option<usize>,
+    /// Wait until the documents are visible to searches (default: false)
+    refresh: Option<bool>,
+    /// Token returned by a first call without it, after reviewing the documents to load
+    confirmation_token: Option<String>,
+}
+
+#[tool_router(router = bulk_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: bulk ingest documents
+    #[tool(
+        description = "Load documents into an index, provided as a JSON array, NDJSON or CSV text, optionally through an ingest pipeline. Errors are summarized by type. A first call without confirmation_token parses the documents, shows a sample and returns a token: review it with the user, then call again with the token.",
+        annotations(title = "Bulk ingest", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn bulk_ingest(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<BulkIngestParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        self.check_write_targets(&es_client, &params.index).await?;
+
+        let docs = match (params.documents, &params.data) {
+            (Some(docs), None) => docs,
+            (None, Some(data)) => parse_documents(data, params.format, params.csv_infer_types.unwrap_or(true))?,
+            _ => {
+                return Err(rmcp::Error::invalid_params(
+                    "Provide the documents either as 'documents' or as 'data' text.",
+                    None,
+                ));
+            }
+        };
+        if docs.is_empty() {
+            return Err(rmcp::Error::invalid_params("No document to load.", None));
+        }
+        let max_docs = max_bulk_docs();
+        if docs.len() > max_docs {
+            return Err(rmcp::Error::invalid_params(
+                format!(
+                    "{} documents provided, at most {max_docs} can be loaded at once. Split them in several calls.",
+                    docs.len()
+                ),
+                None,
+            ));
+        }
+
+        let ids = match &params.id_field {
+            Some(field) => Some(document_ids(&docs, field)?),
+            None => None,
+        };
+
+        let overwrite = params.overwrite.unwrap_or(false);
+        let serialized = docs.iter().map(|d| Value::Object(d.clone()).to_string()).collect::<Vec<_>>();
+        let chunks = chunk_ranges(
+            serialized.iter().map(|d| d.len()),
+            params.chunk_docs.unwrap_or(DEFAULT_CHUNK_DOCS).max(1),
+            params.chunk_bytes.unwrap_or(DEFAULT_CHUNK_BYTES),
+        );
+
+        let token = confirmation_token(&[
+            "bulk_ingest",
+            &params.index,
+            params.pipeline.as_deref().unwrap_or_default(),
+            params.id_field.as_deref().unwrap_or_default(),
+            &overwrite.to_string(),
+            &serialized.join("\n"),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "{} document(s) will be loaded into {} in {} bulk request(s){}. Review the sample with the user, then call bulk_ingest again with the same documents and the confirmation_token.",
+                docs.len(),
+                params.index,
+                chunks.len(),
+                params.pipeline.as_ref().map(|p| format!(" through pipeline {p}")).unwrap_or_default(),
+            );
+            let data = pack_json_value(
+                &json!({ "document_count": docs.len(), "sample": &docs[..docs.len().min(SAMPLE_DOCS)] }),
+                max_response_chars(),
+            )?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "bulk_ingest without confirmation_token")?;
+
+        // Existing documents are only replaced if requested, `create` also works with data streams
+        let op_type = if overwrite { "index" } else { "create" };
+        let refresh = if params.refresh.unwrap_or(false) { Refresh::WaitFor } else { Refresh::False };
+
+        let mut summary = BulkSummary::default();
+        for (chunk_number, range) in chunks.iter().enumerate() {
+            let mut body: Vec<JsonBody<Value>> = Vec::with_capacity(range.len() * 2);
+            for i in range.clone() {
+                let action = match &ids {
+                    Some(ids) => json!({ op_type: { "_id": ids[i] } }),
+                    None => json!({ op_type: {} }),
+                };
+                body.push(JsonBody::new(action));
+                body.push(JsonBody::new(Value::Object(docs[i].clone())));
+            }
+
+            let mut request = es_client.bulk(BulkParts::Index(&params.index)).refresh(refresh).body(body);
+            if let Some(pipeline) = &params.pipeline {
+                request = request.pipeline(pipeline);
+            }
+            let response: Result<BulkResponse, _> = read_json(request.send().await).await;
+            match response {
+                Ok(response) => summary.add(response),
+                Err(err) => {
+                    return Err(rmcp::Error::internal_error(
+                        format!(
+                            "Bulk request {} of {} failed, {} document(s) were loaded before: {}",
+                            chunk_number + 1,
+                            chunks.len(),
+                            summary.loaded,
+                            err.message
+                        ),
+                        None,
+                    ));
+                }
+            }
+        }
+
+        let message = format!(
+            "{} document(s) loaded into {}, {} failed.",
+            summary.loaded, params.index, summary.failed
+        );
+        let data = pack_json_value(&summary.into_report(), max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Parsing
+
+fn parse_documents(
+    data: &str,
+    format: Option<DataFormat>,
+    infer_types: bool,
+) -> Result<Vec<Map<String, Value>>, rmcp::Error> {
+    let format = format.unwrap_or_else(|| detect_format(data));
+    let invalid = |msg: String| rmcp::Error::invalid_params(msg, None);
+
+    match format {
+        DataFormat::Json => serde_json::from_str(data).map_err(|e| invalid(format!("Invalid JSON array of objects: {e}"))),
+        DataFormat::Ndjson => data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(n, line)| {
+                serde_json::from_str(line).map_err(|e| invalid(format!("Invalid JSON object on line {}: {e}", n + 1)))
+            })
+            .collect(),
+        DataFormat::Csv => {
+            let mut records = parse_csv(data).map_err(invalid)?.into_iter();
+            let Some(header) = records.next() else {
+                return Ok(Vec::new());
+            };
+            records
+                .enumerate()
+                .map(|(n, record)| {
+                    if record.len() != header.len() {
+                        return Err(invalid(format!(
+                            "CSV record {} has {} values, but the header has {} columns.",
+                            n + 1,
+                            record.len(),
+                            header.len()
+                        )));
+                    }
+                    Ok(header
+                        .iter()
+                        .zip(record)
+                        .filter(|(_, value)| !value.is_empty())
+                        .map(|(name, value)| (name.clone(), csv_value(value, infer_types)))
+                        .collect())
+                })
+                .collect()
+        }
+    }
+}
+
+fn detect_format(data: &str) -> DataFormat {
+    match data.trim_start().chars().next() {
+        Some('[') => DataFormat::Json,
+        Some('{') => DataFormat::Ndjson,
+        _ => DataFormat::Csv,
+    }
+}
+
+/// Parse CSV text (RFC 4180): quoted values can contain commas, line breaks and doubled quotes.
+/// Empty lines are ignored.
+fn parse_csv(data: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut value = String::new();
+    let mut in_quotes = false;
+    let mut chars = data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                value.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if value.is_empty() => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut value)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut value));
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+            }
+            (c, _) => value.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("Invalid CSV: unterminated quoted value.".to_string());
+    }
+    if !record.is_empty() || !value.is_empty() {
+        record.push(value);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn csv_value(value: String, infer_types: bool) -> Value {
+    // Values with leading zeros (zip codes, account numbers...) aren't numbers
+    if infer_types && !has_leading_zero(&value) {
+        if let Ok(b) = value.parse::<bool>() {
+            return Value::Bool(b);
+        }
+        if let Ok(i) = value.parse::<i64>() {
+            return Value::from(i);
+        }
+        if let Ok(f) = value.parse::<f64>()
+            && f.is_finite()
+        {
+            return Value::from(f);
+        }
+    }
+    Value::String(value)
+}
+
+/// Does a number have a leading zero, like `01234`? `0` and `0.5` don't.
+fn has_leading_zero(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+/// Document ids from the value of a field, that must be a string or a number
+fn document_ids(docs: &[Map<String, Value>], field: &str) -> Result<Vec<String>, rmcp::Error> {
+    docs.iter()
+        .enumerate()
+        .map(|(n, doc)| match doc.get(field) {
+            Some(Value::String(s)) if !s.is_empty() => Ok(s.clone()),
+            Some(Value::Number(n)) => Ok(n.to_string()),
+            _ => Err(rmcp::Error::invalid_params(
+                format!("Document {} has no string or number '{field}' field to use as id.", n + 1),
+                None,
+            )),
+        })
+        .collect()
+}
+
+/// Split documents into chunks of at most `max_docs` documents and `max_bytes` bytes. A document
+/// larger than `max_bytes` is sent alone.
+fn chunk_ranges(sizes: impl Iterator<Item = usize>, max_docs: usize, max_bytes: usize) -> Vec<Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+    let mut end = 0;
+    for size in sizes {
+        if end > start && (end - start >= max_docs || bytes + size > max_bytes) {
+            chunks.push(start..end);
+            start = end;
+            bytes = 0;
+        }
+        bytes += size;
+        end += 1;
+    }
+    if end > start {
+        chunks.push(start..end);
+    }
+    chunks
+}
+
+//------------------------------------------------------------------------------------------------
+// Results
+
+#[derive(Deserialize)]
+struct BulkResponse {
+    items: Vec<HashMap<String, BulkItem>>,
+}
+
+#[derive(Deserialize)]
+struct BulkItem {
+    #[serde(rename = "_id")]
+    id: Option<String>,
+    error: Option<BulkError>,
+}
+
+#[derive(Deserialize)]
+struct BulkError {
+    #[serde(rename = "type")]
+    type_: String,
+    reason: Option<String>,
+}
+
+#[derive(Default)]
+struct BulkSummary {
+    loaded: usize,
+    failed: usize,
+    errors: HashMap<String, ErrorGroup>,
+}
+
+/// Failed items with the same error type
+#[derive(Serialize)]
+struct ErrorGroup {
+    #[serde(rename = "type")]
+    type_: String,
+    count: usize,
+    /// Reason of the first failed item
+    reason: Option<String>,
+    ids: Vec<String>,
+}
+
+impl BulkSummary {
+    fn add(&mut self, response: BulkResponse) {
+        for item in response.items.into_iter().flat_map(|i| i.into_values()) {
+            let Some(error) = item.error else {
+                self.loaded += 1;
+                continue;
+            };
+            self.failed += 1;
+            let group = self.errors.entry(error.type_.clone()).or_insert_with(|| ErrorGroup {
+                type_: error.type_,
+                count: 0,
+                reason: error.reason,
+                ids: Vec::new(),
+            });
+            group.count += 1;
+            if let Some(id) = item.id
+                && group.ids.len() < MAX_ERROR_IDS
+            {
+                group.ids.push(id);
+            }
+        }
+    }
+
+    fn into_report(self) -> Value {
+        let mut errors = self.errors.into_values().collect::<Vec<_>>();
+        errors.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.type_.cmp(&b.type_)));
+        json!({ "loaded": self.loaded, "failed": self.failed, "errors": errors })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_records() {
+        let csv = "name,city,note\r\nJane,Paris,\"likes \"\"quotes\"\", commas\"\n\nJohn,\"New\nYork\",\n";
+        assert_eq!(
+            parse_csv(csv).unwrap(),
+            vec![
+                vec!["name", "city", "note"],
+                vec!["Jane", "Paris", "likes \"quotes\", commas"],
+                vec!["John", "New\nYork", ""],
+            ]
+        );
+        assert!(parse_csv("name\n\"Jane").is_err());
+    }
+
+    #[test]
+    fn documents_from_all_formats() {
+        let expected = vec![
+            json!({ "id": 1, "name": "Jane", "active": true }),
+            json!({ "id": 2, "name": "John", "score": 1.5 }),
+        ];
+        let as_values = |docs: Vec<Map<String, Value>>| docs.into_iter().map(Value::Object).collect::<Vec<_>>();
+
+        let json_array = r#"[{"id": 1, "name": "Jane", "active": true}, {"id": 2, "name": "John", "score": 1.5}]"#;
+        assert_eq!(as_values(parse_documents(json_array, None, true).unwrap()), expected);
+
+        let ndjson = "{\"id\": 1, \"name\": \"Jane\", \"active\": true}\n\n{\"id\": 2, \"name\": \"John\", \"score\": 1.5}\n";
+        assert_eq!(as_values(parse_documents(ndjson, None, true).unwrap()), expected);
+
+        let csv = "id,name,active,score\n1,Jane,true,\n2,John,,1.5\n";
+        assert_eq!(as_values(parse_documents(csv, None, true).unwrap()), expected);
+        assert_eq!(
+            as_values(parse_documents(csv, Some(DataFormat::Csv), false).unwrap())[0],
+            json!({ "id": "1", "name": "Jane", "active": "true" })
+        );
+
+        assert!(parse_documents("{\"id\": 1}\n{oops}", None, true).is_err());
+
+        assert_eq!(csv_value("01234".to_string(), true), json!("01234"));
+        assert_eq!(csv_value("-007".to_string(), true), json!("-007"));
+        assert_eq!(csv_value("0".to_string(), true), json!(0));
+        assert_eq!(csv_value("0.5".to_string(), true), json!(0.5));
+        assert_eq!(csv_value("-10".to_string(), true), json!(-10));
+        assert!(parse_documents("a,b\n1,2,3", None, true).is_err());
+    }
+
+    #[test]
+    fn chunks() {
+        let sizes = [10, 10, 10, 10, 10];
+        assert_eq!(chunk_ranges(sizes.into_iter(), 2, 1000), vec![0..2, 2..4, 4..5]);
+        assert_eq!(chunk_ranges(sizes.into_iter(), 10, 25), vec![0..2, 2..4, 4..5]);
+        // Oversized documents are sent alone
+        assert_eq!(chunk_ranges([5, 50, 5].into_iter(), 10, 20), vec![0..1, 1..2, 2..3]);
+        assert!(chunk_ranges(std::iter::empty(), 10, 20).is_empty());
+    }
+
+    #[test]
+    fn errors_grouped_by_type() {
+        let response: BulkResponse = serde_json::from_value(json!({ "items": [
+            { "create": { "_id": "1", "status": 201, "result": "created" } },
+            { "create": { "_id": "2", "status": 409, "error": { "type": "version_conflict_engine_exception", "reason": "[2]: version conflict" } } },
+            { "create": { "_id": "3", "status": 400, "error": { "type": "document_parsing_exception", "reason": "failed to parse field [age]" } } },
+            { "create": { "_id": "4", "status": 400, "error": { "type": "document_parsing_exception", "reason": "failed to parse field [date]" } } },
+        ]}))
+        .unwrap();
+
+        let mut summary = BulkSummary::default();
+        summary.add(response);
+        assert_eq!(
+            summary.into_report(),
+            json!({
+                "loaded": 1,
+                "failed": 3,
+                "errors": [
+                    { "type": "document_parsing_exception", "count": 2, "reason": "failed to parse field [age]", "ids": ["3", "4"] },
+                    { "type": "version_conflict_engine_exception", "count": 1, "reason": "[2]: version conflict", "ids": ["2"] },
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,384 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Update by query and delete by query.
+//!
+//! A first call counts the matching documents and shows a sample of them. Once confirmed, the
+//! operation runs as a background task whose progress can be followed with `get_task`.
+
+use crate::servers::elasticsearch::aliases::ResolvedExpression;
+use crate::servers::elasticsearch::base_tools::{
+    EsBaseTools, SearchResult, check_query, max_response_chars, pack_json_value,
+};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::safety::{IndexPolicy, check_confirmation_token, confirmation_token};
+use elasticsearch::http::StatusCode;
+use elasticsearch::indices::IndicesResolveIndexParts;
+use elasticsearch::params::Conflicts;
+use elasticsearch::{CountParts, DeleteByQueryParts, Elasticsearch, SearchParts, UpdateByQueryParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::collections::HashSet;
+
+/// Number of matching documents shown in the preview
+const SAMPLE_DOCS: usize = 3;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct UpdateByQueryParams {
+    /// Index, alias, data stream or index pattern
+    index: String,
+    /// Query DSL selecting the documents to update, e.g. {"term": {"status": "pending"}}
+    query: Map<String, Value>,
+    /// Optional Painless script applied to each document, e.g. {"source": "ctx._source.status = params.s", "params": {"s": "done"}}.
+    /// Without script nor pipeline, documents are rewritten as-is (e.g. to pick up mapping changes).
+    script: Option<Map<String, Value>>,
+    /// Optional ingest pipeline documents are processed with
+    pipeline: Option<String>,
+    /// Maximum number of documents to update (default: all matching documents)
+    max_docs: Option<u64>,
+    /// Skip documents modified during the operation instead of aborting (default: false)
+    proceed_on_conflicts: Option<bool>,
+    /// Token returned by a first call without it, after reviewing the matching documents
+    confirmation_token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct DeleteByQueryParams {
+    /// Index, alias, data stream or index pattern
+    index: String,
+    /// Query DSL selecting the documents to delete, e.g. {"range": {"@timestamp": {"lt": "now-90d"}}}
+    query: Map<String, Value>,
+    /// Maximum number of documents to delete (default: all matching documents)
+    max_docs: Option<u64>,
+    /// Skip documents modified during the operation instead of aborting (default: false)
+    proceed_on_conflicts: Option<bool>,
+    /// Token returned by a first call without it, after reviewing the matching documents
+    confirmation_token: Option<String>,
+}
+
+#[tool_router(router = by_query_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: update by query
+    #[tool(
+        description = "Update all documents matching a query with a script or an ingest pipeline. A first call without confirmation_token returns the number of matching documents, a sample of them and a token: review them with the user, then call again with the token. The update then runs as a background task: follow it with get_task.",
+        annotations(title = "Update by query", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn update_by_query(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<UpdateByQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let query = Value::Object(params.query);
+        check_query(&query)?;
+        self.check_write_targets(&es_client, &params.index).await?;
+
+        let script = params.script.map(Value::Object);
+        let token = confirmation_token(&[
+            "update_by_query",
+            &params.index,
+            &query.to_string(),
+            &json!(script).to_string(),
+            params.pipeline.as_deref().unwrap_or_default(),
+            &json!(params.max_docs).to_string(),
+            &json!(params.proceed_on_conflicts).to_string(),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let preview = preview_matches(&es_client, &params.index, &query).await?;
+            return preview.result("update_by_query", "updated", params.max_docs, token);
+        };
+        check_confirmation_token(&provided, &token, "update_by_query without confirmation_token")?;
+        let count = count_matches(&es_client, &params.index, &query).await?;
+
+        let mut body = json!({ "query": query });
+        if let Some(script) = script {
+            body["script"] = script;
+        }
+        if let Some(max_docs) = params.max_docs {
+            body["max_docs"] = json!(max_docs);
+        }
+        let mut request = es_client
+            .update_by_query(UpdateByQueryParts::Index(&[&params.index]))
+            .conflicts(conflicts(params.proceed_on_conflicts))
+            .wait_for_completion(false)
+            .body(body);
+        if let Some(pipeline) = &params.pipeline {
+            request = request.pipeline(pipeline);
+        }
+        let response: TaskResponse = read_json(request.send().await).await?;
+
+        task_started("Update by query", &response.task, Some(count))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: delete by query
+    #[tool(
+        description = "Delete all documents matching a query. A first call without confirmation_token returns the number of matching documents, a sample of them and a token: review them with the user, then call again with the token. The deletion then runs as a background task: follow it with get_task.",
+        annotations(title = "Delete by query", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn delete_by_query(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<DeleteByQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let query = Value::Object(params.query);
+        check_query(&query)?;
+        self.check_write_targets(&es_client, &params.index).await?;
+
+        let token = confirmation_token(&[
+            "delete_by_query",
+            &params.index,
+            &query.to_string(),
+            &json!(params.max_docs).to_string(),
+            &json!(params.proceed_on_conflicts).to_string(),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let preview = preview_matches(&es_client, &params.index, &query).await?;
+            return preview.result("delete_by_query", "deleted", params.max_docs, token);
+        };
+        check_confirmation_token(&provided, &token, "delete_by_query without confirmation_token")?;
+        let count = count_matches(&es_client, &params.index, &query).await?;
+
+        let mut body = json!({ "query": query });
+        if let Some(max_docs) = params.max_docs {
+            body["max_docs"] = json!(max_docs);
+        }
+        let response = es_client
+            .delete_by_query(DeleteByQueryParts::Index(&[&params.index]))
+            .conflicts(conflicts(params.proceed_on_conflicts))
+            .wait_for_completion(false)
+            .body(body)
+            .send()
+            .await;
+        let response: TaskResponse = read_json(response).await?;
+
+        task_started("Delete by query", &response.task, Some(count))
+    }
+}
+
+impl EsBaseTools {
+    /// Check an index expression and the indices it resolves to against the write policy. Targets
+    /// that don't exist yet (e.g. a new destination index) are only checked by name.
+    pub(crate) async fn check_write_targets(&self, es_client: &Elasticsearch, expression: &str) -> Result<(), rmcp::Error> {
+        let targets = expression.split(',').map(str::trim).collect::<Vec<_>>();
+        // Backing indices are checked with their data stream once resolved
+        for target in targets.iter().filter(|t| !is_backing_index(t)) {
+            self.write_policy.check(target)?;
+        }
+        let response = es_client
+            .indices()
+            .resolve_index(IndicesResolveIndexParts::Name(&[expression]))
+            .send()
+            .await;
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            for target in targets.iter().filter(|t| is_backing_index(t)) {
+                self.write_policy.check(target)?;
+            }
+            return Ok(());
+        }
+        let resolved: ResolvedExpression = read_json(response).await.map_err(|e| {
+            rmcp::Error::invalid_params(
+                format!("Could not resolve {expression} to check it against the write policy: {}", e.message),
+                None,
+            )
+        })?;
+        check_resolved_targets(&self.write_policy, &resolved)
+    }
+}
+
+fn is_backing_index(name: &str) -> bool {
+    name.starts_with(".ds-")
+}
+
+/// Check resolved targets against a policy. Backing indices of a data stream are system-like
+/// `.ds-` indices: they are allowed if the data stream itself is.
+fn check_resolved_targets(policy: &IndexPolicy, resolved: &ResolvedExpression) -> Result<(), rmcp::Error> {
+    let mut backing_indices = HashSet::new();
+    for data_stream in &resolved.data_streams {
+        policy.check(&data_stream.name)?;
+        backing_indices.extend(data_stream.backing_indices.iter().map(String::as_str));
+    }
+    // Backing indices targeted by name
+    for index in &resolved.indices {
+        if let Some(data_stream) = &index.data_stream {
+            policy.check(data_stream)?;
+            backing_indices.insert(index.name.as_str());
+        }
+    }
+    for index in resolved.concrete_indices() {
+        if !backing_indices.contains(index.as_str()) {
+            policy.check(&index)?;
+        }
+    }
+    Ok(())
+}
+
+/// Number of documents matching a query, and a sample of them
+struct MatchPreview {
+    count: u64,
+    sample: Vec<Value>,
+}
+
+impl MatchPreview {
+    fn result(self, tool: &str, verb: &str, max_docs: Option<u64>, token: String) -> Result<CallToolResult, rmcp::Error> {
+        if self.count == 0 {
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": "No document matches the query, there is nothing to do." }))?
+            ]));
+        }
+        let affected = max_docs.map_or(self.count, |max| max.min(self.count));
+        let message = format!(
+            "{affected} document(s) will be {verb} ({} matching the query). Review the sample with the user, then call {tool} again with the confirmation_token.",
+            self.count
+        );
+        let data = pack_json_value(&json!({ "count": self.count, "sample": self.sample }), max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+        ]))
+    }
+}
+
+/// Number of documents matching a query. It isn't part of confirmation tokens as it may change
+/// between the preview and the confirmation (e.g. documents still being ingested).
+async fn count_matches(es_client: &Elasticsearch, index: &str, query: &Value) -> Result<u64, rmcp::Error> {
+    let response = es_client
+        .count(CountParts::Index(&[index]))
+        .body(json!({ "query": query }))
+        .send()
+        .await;
+    let count: CountResponse = read_json(response).await?;
+    Ok(count.count)
+}
+
+async fn preview_matches(es_client: &Elasticsearch, index: &str, query: &Value) -> Result<MatchPreview, rmcp::Error> {
+    let count = count_matches(es_client, index, query).await?;
+
+    let response = es_client
+        .search(SearchParts::Index(&[index]))
+        .body(json!({ "query": query, "size": SAMPLE_DOCS, "track_total_hits": false }))
+        .send()
+        .await;
+    let search: SearchResult = read_json(response).await?;
+    let sample = search
+        .hits
+        .hits
+        .into_iter()
+        .map(|hit| json!({ "_index": hit.index, "_id": hit.id, "_source": hit.source }))
+        .collect();
+
+    Ok(MatchPreview { count, sample })
+}
+
+fn conflicts(proceed: Option<bool>) -> Conflicts {
+    if proceed.unwrap_or(false) {
+        Conflicts::Proceed
+    } else {
+        Conflicts::Abort
+    }
+}
+
+/// Result of a write operation started as a background task, with the number of documents it
+/// applies to when it was started if known
+pub(crate) fn task_started(
+    operation: &str,
+    task_id: &str,
+    document_count: Option<u64>,
+) -> Result<CallToolResult, rmcp::Error> {
+    let documents = document_count.map(|count| format!(" on {count} document(s)")).unwrap_or_default();
+    let message = format!(
+        "{operation} started as task {task_id}{documents}. Use get_task to follow its progress, and cancel_task to stop it."
+    );
+    let mut result = json!({ "message": message, "task_id": task_id });
+    if let Some(count) = document_count {
+        result["document_count"] = json!(count);
+    }
+    Ok(CallToolResult::success(vec![Content::json(result)?]))
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct CountResponse {
+    count: u64,
+}
+
+/// Response of operations run with `wait_for_completion=false`
+#[derive(Deserialize)]
+pub(crate) struct TaskResponse {
+    pub task: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_stream_backing_indices_are_allowed() {
+        let policy = IndexPolicy::new("write", vec!["logs-*".to_string()], vec![]);
+        let resolved: ResolvedExpression = serde_json::from_value(json!({
+            "indices": [{ "name": ".ds-logs-app-1" }],
+            "data_streams": [{ "name": "logs-app", "backing_indices": [".ds-logs-app-1", ".ds-logs-app-2"] }]
+        }))
+        .unwrap();
+        assert!(check_resolved_targets(&policy, &resolved).is_ok());
+
+        let resolved: ResolvedExpression = serde_json::from_value(json!({
+            "data_streams": [{ "name": "metrics-app", "backing_indices": [".ds-metrics-app-1"] }]
+        }))
+        .unwrap();
+        assert!(check_resolved_targets(&policy, &resolved).is_err());
+
+        let resolved: ResolvedExpression = serde_json::from_value(json!({
+            "aliases": [{ "name": "logs-all", "indices": ["logs-a", ".security-7"] }]
+        }))
+        .unwrap();
+        assert!(check_resolved_targets(&policy, &resolved).is_err());
+
+        let resolved: ResolvedExpression = serde_json::from_value(json!({
+            "indices": [{ "name": ".ds-logs-app-1", "data_stream": "logs-app" }]
+        }))
+        .unwrap();
+        assert!(check_resolved_targets(&policy, &resolved).is_ok());
+
+        let resolved: ResolvedExpression = serde_json::from_value(json!({
+            "indices": [{ "name": ".ds-metrics-app-1", "data_stream": "metrics-app" }]
+        }))
+        .unwrap();
+        assert!(check_resolved_targets(&policy, &resolved).is_err());
+    }
+
+    #[test]
+    fn preview_of_max_docs() {
+        let preview = MatchPreview { count: 120, sample: vec![] };
+        let result = preview.result("delete_by_query", "deleted", Some(50), "token".to_string()).unwrap();
+        let text = serde_json::to_string(&result).unwrap();
+        assert!(text.contains("50 document(s) will be deleted (120 matching the query)"));
+    }
+}
@@ -0,0 +1,357 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Cluster version detection and compatibility checks.
+//!
+//! The version is read from the root endpoint (`GET /`) and used to hide tools the cluster
+//! can't serve and to pick between APIs that changed across major versions. OpenSearch clusters
+//! are also recognized, and tools relying on Elasticsearch-only APIs are disabled for them.
+
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::Elasticsearch;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Timeout for the root endpoint request, kept short so that an unreachable cluster doesn't delay startup.
+const DETECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Minimum cluster version for tools relying on recent APIs. Tools not listed here are always available.
+const TOOL_MIN_VERSIONS: &[(&str, Version)] = &[
+    ("esql", Version::new(8, 11, 0)),
+    ("list_data_streams", Version::new(7, 9, 0)),
+    ("get_data_stream", Version::new(7, 9, 0)),
+];
+
+/// Tools relying on Elasticsearch-only APIs (OpenSearch has ISM instead of ILM and SLM)
+const OPENSEARCH_UNSUPPORTED_TOOLS: &[&str] = &["esql", "ilm_explain", "get_ilm_policy", "ilm_retry", "get_slm_policies"];
+
+/// Composable index templates (`_index_template`) were introduced in 7.8
+const COMPOSABLE_TEMPLATES_VERSION: Version = Version::new(7, 8, 0);
+
+/// The simulate index API (`_index_template/_simulate_index`) was introduced in 7.9
+const SIMULATE_INDEX_VERSION: Version = Version::new(7, 9, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Version { major, minor, patch }
+    }
+
+    /// Parse a version number like `8.11.1` or `8.0.0-SNAPSHOT`. Missing minor and patch numbers default to 0.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.split(['-', '+']).next()?;
+        let mut parts = s.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map(|p| p.parse()).transpose().ok()?.unwrap_or(0);
+        let patch = parts.next().map(|p| p.parse()).transpose().ok()?.unwrap_or(0);
+        Some(Version { major, minor, patch })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Product running the cluster
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    Elasticsearch,
+    OpenSearch,
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Distribution::Elasticsearch => f.write_str("Elasticsearch"),
+            Distribution::OpenSearch => f.write_str("OpenSearch"),
+        }
+    }
+}
+
+/// Query language the `esql` tool is routed to when connected to OpenSearch
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenSearchQueryLanguage {
+    Sql,
+    Ppl,
+}
+
+impl OpenSearchQueryLanguage {
+    pub fn endpoint(&self) -> &'static str {
+        match self {
+            OpenSearchQueryLanguage::Sql => "/_plugins/_sql",
+            OpenSearchQueryLanguage::Ppl => "/_plugins/_ppl",
+        }
+    }
+}
+
+impl fmt::Display for OpenSearchQueryLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenSearchQueryLanguage::Sql => f.write_str("SQL"),
+            OpenSearchQueryLanguage::Ppl => f.write_str("PPL"),
+        }
+    }
+}
+
+/// Index template API to use with a cluster, also used to tell apart the two kinds of templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateApi {
+    /// `_template`
+    #[default]
+    Legacy,
+    /// `_index_template`
+    Composable,
+}
+
+/// Information about the target cluster, as reported by its root endpoint.
+#[derive(Debug, Clone)]
+pub struct ClusterInfo {
+    pub cluster_name: String,
+    pub distribution: Distribution,
+    pub version: Version,
+    /// `default`, `oss` or `serverless` (Elasticsearch only)
+    pub build_flavor: String,
+}
+
+impl ClusterInfo {
+    pub async fn detect(client: &Elasticsearch) -> Result<Self, rmcp::Error> {
+        let response = client.info().request_timeout(DETECT_TIMEOUT).send().await;
+        let root: RootResponse = read_json(response).await?;
+        Self::from_root(root)
+    }
+
+    fn from_root(root: RootResponse) -> Result<Self, rmcp::Error> {
+        let version = Version::parse(&root.version.number).ok_or_else(|| {
+            rmcp::Error::internal_error(format!("Invalid cluster version '{}'", root.version.number), None)
+        })?;
+
+        let distribution = match root.version.distribution.as_deref() {
+            Some("opensearch") => Distribution::OpenSearch,
+            _ => Distribution::Elasticsearch,
+        };
+
+        Ok(ClusterInfo {
+            cluster_name: root.cluster_name,
+            distribution,
+            version,
+            build_flavor: root.version.build_flavor.unwrap_or_else(|| "default".to_string()),
+        })
+    }
+
+    pub fn is_opensearch(&self) -> bool {
+        self.distribution == Distribution::OpenSearch
+    }
+
+    pub fn template_api(&self) -> TemplateApi {
+        // OpenSearch forked from 7.10 and has always supported composable templates
+        if self.is_opensearch() || self.version >= COMPOSABLE_TEMPLATES_VERSION {
+            TemplateApi::Composable
+        } else {
+            TemplateApi::Legacy
+        }
+    }
+
+    /// Can the cluster tell which templates apply to a new index?
+    pub fn supports_simulate_index(&self) -> bool {
+        self.is_opensearch() || self.version >= SIMULATE_INDEX_VERSION
+    }
+
+    /// Is this tool supported by the cluster?
+    pub fn is_tool_supported(&self, tool: &str) -> bool {
+        match self.distribution {
+            Distribution::Elasticsearch => TOOL_MIN_VERSIONS
+                .iter()
+                .filter(|(name, _)| *name == tool)
+                .all(|(_, min_version)| self.version >= *min_version),
+            Distribution::OpenSearch => !OPENSEARCH_UNSUPPORTED_TOOLS.contains(&tool),
+        }
+    }
+
+    /// A short description of the cluster, for the server instructions
+    pub fn describe(&self) -> String {
+        let mut text = match self.distribution {
+            Distribution::Elasticsearch => format!(
+                "Connected to Elasticsearch {} (cluster '{}', build flavor '{}').",
+                self.version, self.cluster_name, self.build_flavor
+            ),
+            Distribution::OpenSearch => format!(
+                "Connected to OpenSearch {} (cluster '{}').",
+                self.version, self.cluster_name
+            ),
+        };
+        if self.template_api() == TemplateApi::Legacy {
+            text.push_str(" Only legacy index templates (_template) are supported.");
+        }
+        text
+    }
+}
+
+/// Delay before detecting the cluster again after a failure, so that calls aren't all slowed down
+/// by the detection timeout while the cluster is unreachable.
+const DETECT_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+enum Detection {
+    Detected(ClusterInfo),
+    Failed(Instant),
+}
+
+/// Caches the detected cluster information of each client (see `EsClientProvider::key`), since
+/// the cluster may be unreachable at startup or only accept per-request credentials. A failed
+/// detection is retried after `DETECT_RETRY_DELAY`.
+#[derive(Clone, Default)]
+pub struct ClusterInfoCache(Arc<RwLock<HashMap<u64, Detection>>>);
+
+impl ClusterInfoCache {
+    pub fn get(&self, client_key: u64) -> Option<ClusterInfo> {
+        match self.0.read().ok()?.get(&client_key)? {
+            Detection::Detected(info) => Some(info.clone()),
+            Detection::Failed(_) => None,
+        }
+    }
+
+    pub async fn get_or_detect(&self, client_key: u64, client: &Elasticsearch) -> Option<ClusterInfo> {
+        let retry = match self.0.read().ok()?.get(&client_key) {
+            Some(Detection::Detected(info)) => return Some(info.clone()),
+            Some(Detection::Failed(at)) => at.elapsed() >= DETECT_RETRY_DELAY,
+            None => true,
+        };
+        if !retry {
+            return None;
+        }
+
+        let (detection, result) = match ClusterInfo::detect(client).await {
+            Ok(info) => {
+                tracing::info!("Detected {}", info.describe());
+                (Detection::Detected(info.clone()), Some(info))
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not detect cluster version (retrying in {}s): {}",
+                    DETECT_RETRY_DELAY.as_secs(),
+                    e.message
+                );
+                (Detection::Failed(Instant::now()), None)
+            }
+        };
+        if let Ok(mut cache) = self.0.write() {
+            cache.insert(client_key, detection);
+        }
+        result
+    }
+}
+
+//----- Root endpoint response
+
+#[derive(Deserialize)]
+struct RootResponse {
+    cluster_name: String,
+    version: RootVersion,
+}
+
+#[derive(Deserialize)]
+struct RootVersion {
+    number: String,
+    build_flavor: Option<String>,
+    distribution: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(version: &str) -> ClusterInfo {
+        ClusterInfo {
+            cluster_name: "test".to_string(),
+            distribution: Distribution::Elasticsearch,
+            version: Version::parse(version).unwrap(),
+            build_flavor: "default".to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(Version::parse("8.11.1"), Some(Version::new(8, 11, 1)));
+        assert_eq!(Version::parse("8.0.0-SNAPSHOT"), Some(Version::new(8, 0, 0)));
+        assert_eq!(Version::parse("7.17"), Some(Version::new(7, 17, 0)));
+        assert_eq!(Version::parse("foo"), None);
+        assert!(Version::new(8, 11, 0) > Version::new(8, 9, 2));
+    }
+
+    #[test]
+    fn version_dependent_features() {
+        assert!(!cluster("7.17.3").is_tool_supported("esql"));
+        assert!(!cluster("8.10.4").is_tool_supported("esql"));
+        assert!(cluster("8.11.0").is_tool_supported("esql"));
+        assert!(cluster("7.10.0").is_tool_supported("search"));
+
+        assert_eq!(cluster("7.7.1").template_api(), TemplateApi::Legacy);
+        assert_eq!(cluster("7.8.0").template_api(), TemplateApi::Composable);
+    }
+
+    #[test]
+    fn opensearch_features() {
+        let info = ClusterInfo {
+            distribution: Distribution::OpenSearch,
+            ..cluster("2.11.0")
+        };
+        assert!(!info.is_tool_supported("esql"));
+        assert!(info.is_tool_supported("search"));
+        assert_eq!(info.template_api(), TemplateApi::Composable);
+        assert!(info.describe().starts_with("Connected to OpenSearch 2.11.0"));
+    }
+
+    #[test]
+    fn opensearch_root_response() {
+        let root: RootResponse = serde_json::from_value(serde_json::json!({
+            "name": "opensearch-node1",
+            "cluster_name": "opensearch-cluster",
+            "cluster_uuid": "4TdHb1AHSlWqzT5CclyF2w",
+            "version": {
+                "distribution": "opensearch",
+                "number": "2.11.0",
+                "build_type": "tar",
+                "build_hash": "4dcad6dd1fd45b6bd91f041a041829c8687278fa",
+                "build_date": "2023-10-13T02:55:55.511945994Z",
+                "build_snapshot": false,
+                "lucene_version": "9.7.0",
+                "minimum_wire_compatibility_version": "7.10.0",
+                "minimum_index_compatibility_version": "7.0.0"
+            },
+            "tagline": "The OpenSearch Project: https://opensearch.org/"
+        }))
+        .unwrap();
+
+        let info = ClusterInfo::from_root(root).unwrap();
+        assert_eq!(info.distribution, Distribution::OpenSearch);
+        assert_eq!(info.version, Version::new(2, 11, 0));
+        assert_eq!(info.cluster_name, "opensearch-cluster");
+        assert!(!info.is_tool_supported("ilm_explain"));
+    }
+}
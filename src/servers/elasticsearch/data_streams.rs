@@ -0,0 +1,428 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Data streams: listing with their backing indices, lifecycle and freshness.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_index_list, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::Elasticsearch;
+use elasticsearch::cat::CatIndicesParts;
+use elasticsearch::indices::{IndicesDataStreamsStatsParts, IndicesGetDataStreamParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListDataStreamsParams {
+    /// Data stream name pattern (supports * wildcard, default: *)
+    name_pattern: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetDataStreamParams {
+    /// Data stream name
+    name: String,
+}
+
+#[tool_router(router = data_stream_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list data streams
+    #[tool(
+        description = "List data streams with their number of backing indices, generation, index template, lifecycle (ILM policy or data stream lifecycle), health, size and time of the last write. Use it instead of listing the .ds-* backing indices.",
+        annotations(title = "List data streams", read_only_hint = true)
+    )]
+    async fn list_data_streams(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ListDataStreamsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        let pattern = params.name_pattern.as_deref().unwrap_or("*");
+
+        let mut summaries = fetch_data_stream_summaries(&es_client, pattern).await?;
+
+        let total = summaries.len();
+        let max_list = max_index_list();
+        let message = if total > max_list {
+            summaries.truncate(max_list);
+            format!("Found {total} data streams (showing first {max_list}, use name_pattern to filter).")
+        } else {
+            format!("Found {total} data streams.")
+        };
+
+        let data = pack_json_value(&summaries, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get a data stream
+    #[tool(
+        description = "Get the details of a data stream: timestamp field, index template, lifecycle, and its backing indices with their health, documents, size, creation date and lifecycle management.",
+        annotations(title = "Get data stream", read_only_hint = true)
+    )]
+    async fn get_data_stream(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetDataStreamParams { name }): Parameters<GetDataStreamParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let data_streams = fetch_data_streams(&es_client, &name).await?;
+        let Some(data_stream) = data_streams.into_iter().find(|ds| ds.name == name) else {
+            return Err(rmcp::Error::invalid_params(format!("Data stream '{name}' not found."), None));
+        };
+        let stats = fetch_data_stream_stats(&es_client, &name).await?.remove(&name);
+
+        // Backing indices, newest (the write index) first
+        let response = es_client
+            .cat()
+            .indices(CatIndicesParts::Index(&[&name]))
+            .h(&["index", "health", "status", "docs.count", "store.size", "creation.date.string"])
+            .format("json")
+            .send()
+            .await;
+        let mut cat_indices: HashMap<String, Value> = read_json::<Vec<Value>>(response)
+            .await?
+            .into_iter()
+            .filter_map(|row| Some((row["index"].as_str()?.to_string(), row)))
+            .collect();
+
+        let backing_indices = data_stream
+            .indices
+            .iter()
+            .rev()
+            .map(|index| {
+                let mut row = cat_indices
+                    .remove(&index.index_name)
+                    .unwrap_or_else(|| json!({ "index": index.index_name }));
+                row["ilm_policy"] = json!(index.ilm_policy);
+                row["managed_by"] = json!(index.managed_by);
+                row
+            })
+            .collect::<Vec<_>>();
+
+        let summary = data_stream_summary(&data_stream, stats.as_ref(), now_millis());
+        let message = format!(
+            "Data stream '{name}' has {} backing indices, its write index is '{}'.",
+            summary.backing_indices,
+            summary.write_index.as_deref().unwrap_or_default()
+        );
+        let result = json!({
+            "summary": summary,
+            "timestamp_field": data_stream.timestamp_field.map(|f| f.name),
+            "hidden": data_stream.hidden,
+            "system": data_stream.system,
+            "lifecycle": data_stream.lifecycle,
+            "backing_indices": backing_indices,
+        });
+
+        let data = pack_json_value(&result, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+/// Get the data streams matching a pattern
+pub async fn fetch_data_streams(es_client: &Elasticsearch, pattern: &str) -> Result<Vec<DataStream>, rmcp::Error> {
+    let response = es_client
+        .indices()
+        .get_data_stream(IndicesGetDataStreamParts::Name(&[pattern]))
+        .send()
+        .await;
+    let response: DataStreamsResponse = read_json(response).await?;
+    Ok(response.data_streams)
+}
+
+/// Get the storage stats of the data streams matching a pattern, by data stream name
+async fn fetch_data_stream_stats(
+    es_client: &Elasticsearch,
+    pattern: &str,
+) -> Result<HashMap<String, DataStreamStats>, rmcp::Error> {
+    let response = es_client
+        .indices()
+        .data_streams_stats(IndicesDataStreamsStatsParts::Name(&[pattern]))
+        .human(true)
+        .send()
+        .await;
+    let response: DataStreamsStatsResponse = read_json(response).await?;
+    Ok(response
+        .data_streams
+        .into_iter()
+        .map(|stats| (stats.data_stream.clone(), stats))
+        .collect())
+}
+
+/// Get a summary of the data streams matching a pattern, sorted by name
+async fn fetch_data_stream_summaries(
+    es_client: &Elasticsearch,
+    pattern: &str,
+) -> Result<Vec<DataStreamSummary>, rmcp::Error> {
+    let data_streams = fetch_data_streams(es_client, pattern).await?;
+    let stats = fetch_data_stream_stats(es_client, pattern).await?;
+    let now = now_millis();
+
+    let mut summaries = data_streams
+        .iter()
+        .map(|ds| data_stream_summary(ds, stats.get(&ds.name), now))
+        .collect::<Vec<_>>();
+    summaries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(summaries)
+}
+
+/// Replace the backing indices in `_cat/indices` rows by a row per data stream, named after the data
+/// stream and placed at the position of its first backing index. Document counts and sizes (that
+/// must be in bytes) are summed.
+pub fn group_backing_indices(rows: Vec<Value>, data_streams: &[DataStream]) -> Vec<Value> {
+    let backing_indices = data_streams
+        .iter()
+        .flat_map(|ds| ds.indices.iter().map(move |index| (index.index_name.as_str(), ds)))
+        .collect::<HashMap<_, _>>();
+
+    let mut result = Vec::<Value>::with_capacity(rows.len());
+    let mut positions = HashMap::<&str, usize>::new();
+    for row in rows {
+        let Some(ds) = row["index"].as_str().and_then(|index| backing_indices.get(index)) else {
+            result.push(row);
+            continue;
+        };
+        let position = *positions.entry(ds.name.as_str()).or_insert_with(|| {
+            result.push(json!({
+                "index": ds.name,
+                "health": "green",
+                "status": row["status"],
+                "docs.count": 0,
+                "store.size": 0,
+                "pri.store.size": 0,
+                "backing_indices": 0,
+                "write_index": ds.indices.last().map(|i| &i.index_name),
+            }));
+            result.len() - 1
+        });
+        let group = &mut result[position];
+        if health_rank(row["health"].as_str()) > health_rank(group["health"].as_str()) {
+            group["health"] = row["health"].clone();
+        }
+        for field in ["backing_indices", "docs.count", "store.size", "pri.store.size"] {
+            let value = if field == "backing_indices" { 1 } else { number(&row[field]) };
+            group[field] = json!(number(&group[field]) + value);
+        }
+    }
+    result
+}
+
+fn health_rank(health: Option<&str>) -> u8 {
+    match health {
+        Some("red") => 2,
+        Some("yellow") => 1,
+        _ => 0,
+    }
+}
+
+/// Cat APIs return numbers as strings
+fn number(value: &Value) -> u64 {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+        .unwrap_or(0)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[derive(Serialize)]
+struct DataStreamSummary {
+    name: String,
+    backing_indices: usize,
+    generation: u64,
+    write_index: Option<String>,
+    template: Option<String>,
+    /// ILM policy name, or "data stream lifecycle"
+    lifecycle: Option<String>,
+    health: String,
+    store_size: Option<String>,
+    minutes_since_last_write: Option<u64>,
+}
+
+fn data_stream_summary(ds: &DataStream, stats: Option<&DataStreamStats>, now: u64) -> DataStreamSummary {
+    // Since 8.11, new backing indices can be managed by the data stream lifecycle instead of ILM
+    let lifecycle = match ds.next_generation_managed_by.as_deref() {
+        Some("Data stream lifecycle") => Some("data stream lifecycle".to_string()),
+        _ => ds.ilm_policy.clone(),
+    };
+    DataStreamSummary {
+        name: ds.name.clone(),
+        backing_indices: ds.indices.len(),
+        generation: ds.generation,
+        write_index: ds.indices.last().map(|i| i.index_name.clone()),
+        template: ds.template.clone(),
+        lifecycle,
+        health: ds.status.to_lowercase(),
+        store_size: stats.and_then(|s| s.store_size.clone()),
+        minutes_since_last_write: stats
+            .and_then(|s| s.maximum_timestamp)
+            .filter(|ts| *ts > 0)
+            .map(|ts| now.saturating_sub(ts) / 60_000),
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct DataStreamsResponse {
+    #[serde(default)]
+    data_streams: Vec<DataStream>,
+}
+
+#[derive(Deserialize)]
+pub struct DataStream {
+    pub name: String,
+    pub timestamp_field: Option<TimestampField>,
+    /// Backing indices, oldest first
+    #[serde(default)]
+    pub indices: Vec<BackingIndex>,
+    pub generation: u64,
+    pub status: String,
+    pub template: Option<String>,
+    pub ilm_policy: Option<String>,
+    pub next_generation_managed_by: Option<String>,
+    pub lifecycle: Option<Value>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub system: bool,
+}
+
+#[derive(Deserialize)]
+pub struct TimestampField {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct BackingIndex {
+    pub index_name: String,
+    pub ilm_policy: Option<String>,
+    pub managed_by: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DataStreamsStatsResponse {
+    #[serde(default)]
+    data_streams: Vec<DataStreamStats>,
+}
+
+#[derive(Deserialize)]
+struct DataStreamStats {
+    data_stream: String,
+    store_size: Option<String>,
+    maximum_timestamp: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_data_stream() {
+        let ds: DataStream = serde_json::from_value(json!({
+            "name": "logs-nginx-default",
+            "timestamp_field": { "name": "@timestamp" },
+            "indices": [
+                { "index_name": ".ds-logs-nginx-default-2024.01.01-000001", "ilm_policy": "logs", "managed_by": "Index Lifecycle Management" },
+                { "index_name": ".ds-logs-nginx-default-2024.01.08-000002", "ilm_policy": "logs", "managed_by": "Index Lifecycle Management" }
+            ],
+            "generation": 2,
+            "status": "YELLOW",
+            "template": "logs",
+            "ilm_policy": "logs",
+            "hidden": false
+        }))
+        .unwrap();
+        let stats = DataStreamStats {
+            data_stream: "logs-nginx-default".to_string(),
+            store_size: Some("1.2gb".to_string()),
+            maximum_timestamp: Some(1_704_067_200_000),
+        };
+
+        let summary = data_stream_summary(&ds, Some(&stats), 1_704_067_200_000 + 5 * 60_000);
+        assert_eq!(summary.backing_indices, 2);
+        assert_eq!(summary.write_index.as_deref(), Some(".ds-logs-nginx-default-2024.01.08-000002"));
+        assert_eq!(summary.lifecycle.as_deref(), Some("logs"));
+        assert_eq!(summary.health, "yellow");
+        assert_eq!(summary.minutes_since_last_write, Some(5));
+
+        let ds = DataStream {
+            next_generation_managed_by: Some("Data stream lifecycle".to_string()),
+            ..ds
+        };
+        let summary = data_stream_summary(&ds, None, 0);
+        assert_eq!(summary.lifecycle.as_deref(), Some("data stream lifecycle"));
+        assert_eq!(summary.minutes_since_last_write, None);
+    }
+
+    #[test]
+    fn group_rows_by_data_stream() {
+        let ds: DataStream = serde_json::from_value(json!({
+            "name": "logs-app",
+            "indices": [{ "index_name": ".ds-logs-app-000001" }, { "index_name": ".ds-logs-app-000002" }],
+            "generation": 2,
+            "status": "GREEN"
+        }))
+        .unwrap();
+        let row = |index: &str, health: &str, docs: &str, size: &str, pri_size: &str| {
+            json!({
+                "index": index, "health": health, "status": "open",
+                "docs.count": docs, "store.size": size, "pri.store.size": pri_size
+            })
+        };
+        let rows = vec![
+            row(".ds-logs-app-000001", "green", "10", "100", "50"),
+            row("metrics", "green", "5", "50", "25"),
+            row(".ds-logs-app-000002", "yellow", "3", "30", "30"),
+        ];
+
+        let grouped = group_backing_indices(rows, &[ds]);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(
+            grouped[0],
+            json!({
+                "index": "logs-app",
+                "health": "yellow",
+                "status": "open",
+                "docs.count": 13,
+                "store.size": 130,
+                "pri.store.size": 80,
+                "backing_indices": 2,
+                "write_index": ".ds-logs-app-000002",
+            })
+        );
+        assert_eq!(grouped[1]["index"], "metrics");
+    }
+}
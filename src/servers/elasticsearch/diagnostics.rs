@@ -0,0 +1,1010 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Cluster diagnostics: explains why shards are unassigned, and runs a root-cause analysis
+//! combining health, shards, nodes, disk allocation and pending tasks.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::Elasticsearch;
+use elasticsearch::cat::{CatAllocationParts, CatShardsParts};
+use elasticsearch::cluster::{ClusterAllocationExplainParts, ClusterHealthParts, ClusterPendingTasksParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_aux::prelude::*;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Maximum number of allocation explain requests for a single tool call. Unassigned shards are
+/// explained once per index and shard type, as they usually share the same cause.
+const MAX_EXPLAINED_GROUPS: usize = 20;
+
+/// Maximum number of shards and explanations listed for each unassigned reason
+const MAX_LISTED_PER_REASON: usize = 10;
+
+/// Default disk watermarks, in percent of disk used
+const DISK_LOW_WATERMARK: f64 = 85.0;
+const DISK_HIGH_WATERMARK: f64 = 90.0;
+const DISK_FLOOD_STAGE: f64 = 95.0;
+
+/// Heap and CPU usage above which a node is reported as overloaded, in percent
+const HEAP_WARNING: f64 = 85.0;
+const CPU_WARNING: f64 = 90.0;
+
+/// Pending tasks are reported if there are this many, or if the oldest one waited more than this
+const PENDING_TASKS_WARNING: usize = 50;
+const PENDING_TASK_AGE_WARNING_MS: u64 = 30_000;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ExplainAllocationParams {
+    /// Optional index name. If not provided, all unassigned shards are explained, grouped by reason.
+    index: Option<String>,
+    /// Shard number (default: 0, requires 'index')
+    shard: Option<u32>,
+    /// Explain the primary shard rather than a replica (default: true, requires 'index')
+    primary: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct DiagnoseClusterParams {}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct PendingTasksParams {}
+
+#[tool_router(router = diagnostic_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: explain shard allocation
+    ///
+    /// With no arguments, finds the unassigned shards with `_cat/shards`, explains one shard per
+    /// index and shard type, and groups the results by reason.
+    #[tool(
+        description = "Explain why shards are unassigned or can't be moved. With no arguments, explains all unassigned shards grouped by reason (disk watermark, allocation filtering, max retries, no valid copy, etc.) with the allocation decider explanations. Can also explain a specific shard.",
+        annotations(title = "Explain shard allocation", read_only_hint = true)
+    )]
+    async fn explain_allocation(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ExplainAllocationParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        if let Some(index) = params.index {
+            let shard = params.shard.unwrap_or(0);
+            let primary = params.primary.unwrap_or(true);
+            let explanation = explain_shard(&es_client, &index, shard, primary).await?;
+            let classification = classify_explanation(&explanation);
+
+            let message = format!(
+                "Shard {} of index '{}' ({}): {}. {}",
+                shard,
+                index,
+                if primary { "primary" } else { "replica" },
+                explanation.current_state,
+                explanation.explanation().unwrap_or_default()
+            );
+            let data = pack_json_value(
+                &json!({
+                    "reason": classification.issue,
+                    "suggestion": classification.issue.suggestion(),
+                    "unassigned_info": explanation.unassigned_info,
+                    "nodes": classification.nodes,
+                    "deciders": classification.explanations,
+                }),
+                max_response_chars(),
+            )?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "data": data }))?
+            ]));
+        }
+
+        let shards = fetch_shard_states(&es_client).await?;
+        let summary = explain_unassigned_shards(&es_client, &shards).await;
+        let message = if summary.unassigned_count == 0 {
+            "No unassigned shards.".to_string()
+        } else {
+            let mut message = format!(
+                "{} unassigned shard(s): {}.",
+                summary.unassigned_count,
+                summary
+                    .reasons
+                    .iter()
+                    .map(|r| format!("{} {}", r.shard_count, r.reason.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if summary.unexplained_count > 0 {
+                message.push_str(&format!(
+                    " {} shard(s) not explained, use explain_allocation(index=...) to explain them.",
+                    summary.unexplained_count
+                ));
+            }
+            message
+        };
+
+        let data = pack_json_value(&summary.reasons, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get pending cluster tasks
+    #[tool(
+        description = "Get the cluster-level changes (index creation, mapping updates, shard allocation, etc.) waiting to be executed by the master node, oldest first. A long queue means the master is overloaded.",
+        annotations(title = "Get pending cluster tasks", read_only_hint = true)
+    )]
+    async fn get_pending_tasks(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(_params): Parameters<PendingTasksParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let tasks = fetch_pending_tasks(&es_client).await?;
+        let message = match tasks.first() {
+            Some(oldest) => format!(
+                "{} pending task(s), the oldest waiting for {}s.",
+                tasks.len(),
+                oldest.time_in_queue_millis / 1000
+            ),
+            None => "No pending tasks.".to_string(),
+        };
+
+        let data = pack_json_value(&tasks, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: diagnose cluster
+    ///
+    /// Runs the root-cause analysis chain server-side (health, shards, nodes, allocation explain,
+    /// pending tasks and disk allocation) and returns the findings, most severe first. Only the
+    /// cluster health is required: other sources that fail are listed in `errors`.
+    #[tool(
+        description = "Diagnose cluster problems in a single call: checks cluster health, shards, nodes, disk allocation, pending tasks and explains unassigned shards, then returns a ranked list of findings (e.g. red index caused by unassigned primaries caused by disk watermark on a node) with evidence and suggested next steps.",
+        annotations(title = "Diagnose cluster", read_only_hint = true)
+    )]
+    async fn diagnose_cluster(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(_params): Parameters<DiagnoseClusterParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client.cluster().health(ClusterHealthParts::None).send().await;
+        let health: ClusterHealth = read_json(response).await?;
+
+        let mut errors = Vec::new();
+        let mut log_error = |source: &str, e: rmcp::Error| {
+            errors.push(format!("{source}: {}", e.message));
+        };
+
+        let shards = fetch_shard_states(&es_client).await.unwrap_or_else(|e| {
+            log_error("_cat/shards", e);
+            Vec::new()
+        });
+        let nodes = fetch_node_loads(&es_client).await.unwrap_or_else(|e| {
+            log_error("_cat/nodes", e);
+            Vec::new()
+        });
+        let allocation = fetch_disk_allocation(&es_client).await.unwrap_or_else(|e| {
+            log_error("_cat/allocation", e);
+            Vec::new()
+        });
+        let pending_tasks = fetch_pending_tasks(&es_client).await.unwrap_or_else(|e| {
+            log_error("_cluster/pending_tasks", e);
+            Vec::new()
+        });
+        let unassigned = explain_unassigned_shards(&es_client, &shards).await;
+
+        let snapshot = ClusterSnapshot {
+            health,
+            shards,
+            nodes,
+            allocation,
+            pending_tasks,
+            unassigned,
+        };
+        let findings = analyze_cluster(&snapshot);
+
+        let critical = findings.iter().filter(|f| f.severity == Severity::Critical).count();
+        let warning = findings.iter().filter(|f| f.severity == Severity::Warning).count();
+        let mut message = format!(
+            "Cluster status is {}: {} critical and {} warning finding(s).",
+            snapshot.health.status, critical, warning
+        );
+        if let Some(top) = findings.first()
+            && top.severity != Severity::Info
+        {
+            message.push_str(&format!(" Most severe: {}", top.title));
+        }
+
+        let data = pack_json_value(
+            &json!({
+                "findings": findings,
+                "errors": errors,
+            }),
+            max_response_chars(),
+        )?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Allocation explain
+
+async fn explain_shard(
+    es_client: &Elasticsearch,
+    index: &str,
+    shard: u32,
+    primary: bool,
+) -> Result<AllocationExplanation, rmcp::Error> {
+    let response = es_client
+        .cluster()
+        .allocation_explain(ClusterAllocationExplainParts::None)
+        .body(json!({ "index": index, "shard": shard, "primary": primary }))
+        .send()
+        .await;
+    read_json(response).await
+}
+
+/// Unassigned shards, grouped by reason
+pub struct UnassignedSummary {
+    pub unassigned_count: usize,
+    /// Unassigned shards that weren't explained because of the request limit
+    pub unexplained_count: usize,
+    pub reasons: Vec<UnassignedReason>,
+}
+
+#[derive(Serialize)]
+pub struct UnassignedReason {
+    pub reason: AllocationIssue,
+    pub shard_count: usize,
+    pub primary_count: usize,
+    pub suggestion: &'static str,
+    /// Shards, as `index[shard]` followed by `p` or `r`
+    pub shards: Vec<String>,
+    /// Nodes that refused the shards for this reason
+    pub nodes: Vec<String>,
+    /// Distinct decider explanations
+    pub explanations: Vec<String>,
+}
+
+/// Get the state of all shards
+pub async fn fetch_shard_states(es_client: &Elasticsearch) -> Result<Vec<ShardState>, rmcp::Error> {
+    let response = es_client
+        .cat()
+        .shards(CatShardsParts::None)
+        .format("json")
+        .h(&["index", "shard", "prirep", "state"])
+        .send()
+        .await;
+    read_json(response).await
+}
+
+/// Explain the unassigned shards and group them by reason.
+pub async fn explain_unassigned_shards(es_client: &Elasticsearch, shards: &[ShardState]) -> UnassignedSummary {
+    // Group by index and shard type
+    let mut groups = BTreeMap::<(&str, bool), Vec<&ShardState>>::new();
+    for shard in shards.iter().filter(|s| s.state == "UNASSIGNED") {
+        groups
+            .entry((shard.index.as_str(), shard.prirep == "p"))
+            .or_default()
+            .push(shard);
+    }
+    let unassigned_count = groups.values().map(|g| g.len()).sum();
+
+    // Primaries first, as they make indices red
+    let mut groups = groups.into_iter().collect::<Vec<_>>();
+    groups.sort_by_key(|((_, primary), _)| !*primary);
+
+    let mut reasons = BTreeMap::<AllocationIssue, UnassignedReason>::new();
+    let mut unexplained_count = 0;
+    for (i, ((index, primary), shards)) in groups.into_iter().enumerate() {
+        if i >= MAX_EXPLAINED_GROUPS {
+            unexplained_count += shards.len();
+            continue;
+        }
+
+        let classification = match explain_shard(es_client, index, shards[0].shard, primary).await {
+            Ok(explanation) => classify_explanation(&explanation),
+            Err(e) => {
+                tracing::warn!("Allocation explain failed for {index}: {}", e.message);
+                Classification {
+                    issue: AllocationIssue::Other,
+                    explanations: vec![e.message.to_string()],
+                    nodes: Vec::new(),
+                }
+            }
+        };
+        let reason = classification.issue;
+
+        let entry = reasons.entry(reason).or_insert_with(|| UnassignedReason {
+            reason,
+            shard_count: 0,
+            primary_count: 0,
+            suggestion: reason.suggestion(),
+            shards: Vec::new(),
+            nodes: Vec::new(),
+            explanations: Vec::new(),
+        });
+        entry.shard_count += shards.len();
+        if primary {
+            entry.primary_count += shards.len();
+        }
+        for shard in &shards {
+            if entry.shards.len() < MAX_LISTED_PER_REASON {
+                entry.shards.push(format!("{}[{}]{}", shard.index, shard.shard, shard.prirep));
+            }
+        }
+        for node in classification.nodes {
+            if entry.nodes.len() < MAX_LISTED_PER_REASON && !entry.nodes.contains(&node) {
+                entry.nodes.push(node);
+            }
+        }
+        for explanation in classification.explanations {
+            if entry.explanations.len() < MAX_LISTED_PER_REASON && !entry.explanations.contains(&explanation) {
+                entry.explanations.push(explanation);
+            }
+        }
+    }
+
+    let mut reasons = reasons.into_values().collect::<Vec<_>>();
+    reasons.sort_by(|a, b| b.primary_count.cmp(&a.primary_count).then(b.shard_count.cmp(&a.shard_count)));
+
+    UnassignedSummary {
+        unassigned_count,
+        unexplained_count,
+        reasons,
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Root-cause analysis
+
+async fn fetch_node_loads(es_client: &Elasticsearch) -> Result<Vec<NodeLoad>, rmcp::Error> {
+    let response = es_client
+        .cat()
+        .nodes()
+        .h(&["name", "heap.percent", "cpu", "load_1m", "node.role"])
+        .format("json")
+        .send()
+        .await;
+    read_json(response).await
+}
+
+async fn fetch_disk_allocation(es_client: &Elasticsearch) -> Result<Vec<DiskAllocation>, rmcp::Error> {
+    let response = es_client
+        .cat()
+        .allocation(CatAllocationParts::None)
+        .h(&["node", "shards", "disk.percent", "disk.avail"])
+        .format("json")
+        .send()
+        .await;
+    read_json(response).await
+}
+
+/// Get the cluster-level changes that have not yet been executed, oldest first
+pub async fn fetch_pending_tasks(es_client: &Elasticsearch) -> Result<Vec<PendingTask>, rmcp::Error> {
+    let response = es_client
+        .cluster()
+        .pending_tasks(ClusterPendingTasksParts::None)
+        .send()
+        .await;
+    let response: PendingTasksResponse = read_json(response).await?;
+    let mut tasks = response.tasks;
+    tasks.sort_by(|a, b| b.time_in_queue_millis.cmp(&a.time_in_queue_millis));
+    Ok(tasks)
+}
+
+/// Everything needed to diagnose a cluster
+pub struct ClusterSnapshot {
+    pub health: ClusterHealth,
+    pub shards: Vec<ShardState>,
+    pub nodes: Vec<NodeLoad>,
+    pub allocation: Vec<DiskAllocation>,
+    pub pending_tasks: Vec<PendingTask>,
+    pub unassigned: UnassignedSummary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Critical,
+    Warning,
+    Info,
+}
+
+#[derive(Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub title: String,
+    pub evidence: Vec<String>,
+    pub next_steps: Vec<String>,
+}
+
+impl Finding {
+    fn new(severity: Severity, title: String) -> Self {
+        Finding {
+            severity,
+            title,
+            evidence: Vec::new(),
+            next_steps: Vec::new(),
+        }
+    }
+}
+
+/// Apply the diagnosis rules, and return the findings, most severe first. Findings of the same
+/// severity keep the order of the chain: index health, its causes, then nodes and the master.
+pub fn analyze_cluster(snapshot: &ClusterSnapshot) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    // Index health: unassigned primaries make an index red, unassigned replicas make it yellow
+    let mut red = BTreeMap::<&str, usize>::new();
+    let mut yellow = BTreeMap::<&str, usize>::new();
+    for shard in snapshot.shards.iter().filter(|s| s.state == "UNASSIGNED") {
+        let target = if shard.prirep == "p" { &mut red } else { &mut yellow };
+        *target.entry(shard.index.as_str()).or_default() += 1;
+    }
+    yellow.retain(|index, _| !red.contains_key(index));
+
+    if !red.is_empty() {
+        let mut finding = Finding::new(
+            Severity::Critical,
+            format!(
+                "{} red index(es): primary shards are unassigned, part of their data can't be searched or indexed.",
+                red.len()
+            ),
+        );
+        finding.evidence = red
+            .iter()
+            .take(MAX_LISTED_PER_REASON)
+            .map(|(index, count)| format!("{index}: {count} unassigned primary shard(s)"))
+            .collect();
+        finding.next_steps.push("See the unassigned shard findings below for the cause.".to_string());
+        findings.push(finding);
+    }
+    if !yellow.is_empty() {
+        let mut finding = Finding::new(
+            Severity::Warning,
+            format!("{} yellow index(es): replica shards are unassigned, data has no redundancy.", yellow.len()),
+        );
+        finding.evidence = yellow
+            .iter()
+            .take(MAX_LISTED_PER_REASON)
+            .map(|(index, count)| format!("{index}: {count} unassigned replica shard(s)"))
+            .collect();
+        findings.push(finding);
+    }
+
+    // Causes of unassigned shards
+    for reason in &snapshot.unassigned.reasons {
+        let severity = if reason.primary_count > 0 { Severity::Critical } else { Severity::Warning };
+        let mut title = format!(
+            "{} shard(s) unassigned because of {}",
+            reason.shard_count,
+            reason.reason.as_str().replace('_', " ")
+        );
+        if !reason.nodes.is_empty() {
+            title.push_str(&format!(" on node(s) {}", reason.nodes.join(", ")));
+        }
+        title.push('.');
+        let mut finding = Finding::new(severity, title);
+        finding.evidence.push(format!("Shards: {}", reason.shards.join(", ")));
+        finding.evidence.extend(reason.explanations.iter().cloned());
+        finding.next_steps.push(reason.suggestion.to_string());
+        if let Some(shard) = reason.shards.first()
+            && let Some((index, _)) = shard.split_once('[')
+        {
+            finding.next_steps.push(format!("Run explain_allocation(index=\"{index}\") for the full explanation."));
+        }
+        findings.push(finding);
+    }
+    if snapshot.unassigned.unexplained_count > 0 {
+        let mut finding = Finding::new(
+            Severity::Info,
+            format!("{} unassigned shard(s) were not explained.", snapshot.unassigned.unexplained_count),
+        );
+        finding.next_steps.push("Run explain_allocation() or explain_allocation(index=...) to explain them.".to_string());
+        findings.push(finding);
+    }
+
+    // Disk usage
+    for node in snapshot.allocation.iter().filter(|n| n.node != "UNASSIGNED") {
+        let Some(percent) = node.disk_percent else {
+            continue;
+        };
+        let (severity, consequence) = if percent >= DISK_FLOOD_STAGE {
+            (Severity::Critical, "above the flood stage watermark: indices with a shard on this node are read-only")
+        } else if percent >= DISK_HIGH_WATERMARK {
+            (Severity::Warning, "above the high watermark: shards are moved away from this node")
+        } else if percent >= DISK_LOW_WATERMARK {
+            (Severity::Info, "above the low watermark: no new replicas are allocated to this node")
+        } else {
+            continue;
+        };
+        let mut finding = Finding::new(severity, format!("Node {} disk is {percent}% used, {consequence}.", node.node));
+        finding.evidence.push(format!(
+            "{} shard(s), {} available (default watermarks: {DISK_LOW_WATERMARK}% low, {DISK_HIGH_WATERMARK}% high, {DISK_FLOOD_STAGE}% flood stage)",
+            node.shards.unwrap_or(0),
+            node.disk_avail.as_deref().unwrap_or("unknown")
+        ));
+        finding.next_steps.push("Delete or shrink old indices, or add disk space or nodes.".to_string());
+        if severity == Severity::Critical {
+            finding.next_steps.push("Once disk space is freed, the read-only block is released automatically (7.4+), otherwise reset index.blocks.read_only_allow_delete.".to_string());
+        }
+        findings.push(finding);
+    }
+
+    // Node load
+    for node in &snapshot.nodes {
+        if let Some(heap) = node.heap_percent
+            && heap >= HEAP_WARNING
+        {
+            let mut finding = Finding::new(Severity::Warning, format!("Node {} heap is {heap}% used.", node.name));
+            finding.evidence.push(format!("Roles: {}", node.roles.as_deref().unwrap_or("unknown")));
+            finding.next_steps.push("Check GC activity and circuit breakers, and look for expensive aggregations or large bulk requests.".to_string());
+            findings.push(finding);
+        }
+        if let Some(cpu) = node.cpu
+            && cpu >= CPU_WARNING
+        {
+            let mut finding = Finding::new(Severity::Warning, format!("Node {} CPU is at {cpu}%.", node.name));
+            if let Some(load) = node.load_1m {
+                finding.evidence.push(format!("Load average (1m): {load}"));
+            }
+            finding.next_steps.push("Look for hot threads and expensive queries or indexing bursts on this node.".to_string());
+            findings.push(finding);
+        }
+    }
+
+    // Master node
+    let pending = &snapshot.pending_tasks;
+    let oldest = pending.first().map(|t| t.time_in_queue_millis).unwrap_or(0);
+    if pending.len() >= PENDING_TASKS_WARNING || oldest >= PENDING_TASK_AGE_WARNING_MS {
+        let mut finding = Finding::new(
+            Severity::Warning,
+            format!(
+                "{} pending cluster task(s), the oldest waiting for {}s: the master node is falling behind.",
+                pending.len(),
+                oldest / 1000
+            ),
+        );
+        finding.evidence = pending
+            .iter()
+            .take(5)
+            .map(|t| format!("[{}] {} ({}s)", t.priority, t.source, t.time_in_queue_millis / 1000))
+            .collect();
+        finding.next_steps.push("Look for mapping updates from dynamic fields, too many shards, or frequent settings and template changes.".to_string());
+        findings.push(finding);
+    }
+
+    // Recoveries
+    let health = &snapshot.health;
+    if health.initializing_shards > 0 || health.relocating_shards > 0 || health.delayed_unassigned_shards > 0 {
+        let mut finding = Finding::new(
+            Severity::Info,
+            format!(
+                "Shards are moving: {} initializing, {} relocating, {} delayed.",
+                health.initializing_shards, health.relocating_shards, health.delayed_unassigned_shards
+            ),
+        );
+        finding.evidence.push(format!("{}% of shards are active", health.active_shards_percent_as_number));
+        finding.next_steps.push("Recovery is in progress: check again later.".to_string());
+        findings.push(finding);
+    }
+
+    if findings.is_empty() {
+        let mut finding = Finding::new(Severity::Info, "No issues found.".to_string());
+        finding.evidence.push(format!(
+            "Status {}, {} node(s), {} data node(s)",
+            health.status, health.number_of_nodes, health.number_of_data_nodes
+        ));
+        findings.push(finding);
+    }
+
+    findings.sort_by_key(|f| f.severity);
+    findings
+}
+
+/// Why a shard can't be allocated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationIssue {
+    /// No node has a copy of the primary's data
+    NoValidCopy,
+    /// Allocation failed too many times (`index.allocation.max_retries`)
+    MaxRetries,
+    /// Nodes are above a disk watermark
+    DiskWatermark,
+    /// Allocation filtering or routing rules (`index.routing.allocation.*`, `cluster.routing.allocation.*`)
+    AllocationFiltering,
+    /// Allocation awareness attributes
+    Awareness,
+    /// Not enough nodes to separate primaries and replicas
+    SameShard,
+    /// Allocation is disabled (`cluster.routing.allocation.enable`)
+    AllocationDisabled,
+    /// Maximum shards per node reached
+    ShardsPerNodeLimit,
+    /// Allocation is only delayed or throttled, and will proceed
+    Throttled,
+    Other,
+}
+
+impl AllocationIssue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllocationIssue::NoValidCopy => "no_valid_copy",
+            AllocationIssue::MaxRetries => "max_retries",
+            AllocationIssue::DiskWatermark => "disk_watermark",
+            AllocationIssue::AllocationFiltering => "allocation_filtering",
+            AllocationIssue::Awareness => "awareness",
+            AllocationIssue::SameShard => "same_shard",
+            AllocationIssue::AllocationDisabled => "allocation_disabled",
+            AllocationIssue::ShardsPerNodeLimit => "shards_per_node_limit",
+            AllocationIssue::Throttled => "throttled",
+            AllocationIssue::Other => "other",
+        }
+    }
+
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            AllocationIssue::NoValidCopy => "The nodes holding this shard's data left the cluster: bring them back, or restore the index from a snapshot. Allocating a stale or empty primary loses data.",
+            AllocationIssue::MaxRetries => "Fix the cause of the allocation failures listed in the explanations, then retry allocation with POST _cluster/reroute?retry_failed=true.",
+            AllocationIssue::DiskWatermark => "Free disk space on the nodes (delete old indices, add nodes or disk), or review the cluster.routing.allocation.disk.watermark settings.",
+            AllocationIssue::AllocationFiltering => "Check the index.routing.allocation.* index settings and cluster.routing.allocation.* cluster settings against the node attributes.",
+            AllocationIssue::Awareness => "Check the cluster.routing.allocation.awareness settings: there may not be enough nodes in each zone.",
+            AllocationIssue::SameShard => "There are not enough nodes to hold all replicas: add nodes or reduce index.number_of_replicas.",
+            AllocationIssue::AllocationDisabled => "Shard allocation is disabled: re-enable it with cluster.routing.allocation.enable=all.",
+            AllocationIssue::ShardsPerNodeLimit => "Nodes reached the maximum number of shards: review index.routing.allocation.total_shards_per_node and cluster.max_shards_per_node, or add nodes.",
+            AllocationIssue::Throttled => "Allocation is in progress or delayed (e.g. waiting for a node that left to come back): wait and check again.",
+            AllocationIssue::Other => "Review the decider explanations.",
+        }
+    }
+}
+
+/// The main reason a shard can't be allocated
+pub struct Classification {
+    pub issue: AllocationIssue,
+    /// Explanations of the deciders that said no
+    pub explanations: Vec<String>,
+    /// Nodes that refused the shard because of `issue`
+    pub nodes: Vec<String>,
+}
+
+/// Classify an allocation explanation
+pub fn classify_explanation(explanation: &AllocationExplanation) -> Classification {
+    let mut deciders = Vec::new();
+    let mut issues = Vec::new();
+
+    for node in &explanation.node_allocation_decisions {
+        for decider in &node.deciders {
+            if decider.decision == "YES" {
+                continue;
+            }
+            let issue = match decider.decider.as_str() {
+                "max_retry" => AllocationIssue::MaxRetries,
+                "disk_threshold" => AllocationIssue::DiskWatermark,
+                "filter" => AllocationIssue::AllocationFiltering,
+                "awareness" => AllocationIssue::Awareness,
+                "same_shard" => AllocationIssue::SameShard,
+                "enable" => AllocationIssue::AllocationDisabled,
+                "shards_limit" => AllocationIssue::ShardsPerNodeLimit,
+                "throttling" => AllocationIssue::Throttled,
+                _ => AllocationIssue::Other,
+            };
+            issues.push((issue, node.node_name.as_str()));
+            let text = format!("[{}] {}", decider.decider, decider.explanation);
+            if !deciders.contains(&text) {
+                deciders.push(text);
+            }
+        }
+    }
+
+    let issue = match explanation.can_allocate.as_deref() {
+        Some("no_valid_shard_copy") => AllocationIssue::NoValidCopy,
+        Some("throttled") | Some("allocation_delayed") => AllocationIssue::Throttled,
+        // The most specific decider that applies to most nodes. The issue order puts the ones that
+        // need action first, as a single blocking decider per node is enough to prevent allocation.
+        _ => {
+            let mut counts = BTreeMap::<AllocationIssue, usize>::new();
+            for (issue, _) in &issues {
+                *counts.entry(*issue).or_default() += 1;
+            }
+            counts
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+                .map(|(issue, _)| issue)
+                .unwrap_or(AllocationIssue::Other)
+        }
+    };
+
+    if deciders.is_empty()
+        && let Some(explanation) = explanation.explanation()
+    {
+        deciders.push(explanation.to_string());
+    }
+
+    let mut nodes = issues
+        .into_iter()
+        .filter(|(i, _)| *i == issue)
+        .map(|(_, node)| node.to_string())
+        .collect::<Vec<_>>();
+    nodes.dedup();
+
+    Classification {
+        issue,
+        explanations: deciders,
+        nodes,
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+pub struct ClusterHealth {
+    pub status: String,
+    #[serde(default)]
+    pub number_of_nodes: u64,
+    #[serde(default)]
+    pub number_of_data_nodes: u64,
+    #[serde(default)]
+    pub initializing_shards: u64,
+    #[serde(default)]
+    pub relocating_shards: u64,
+    #[serde(default)]
+    pub delayed_unassigned_shards: u64,
+    #[serde(default)]
+    pub active_shards_percent_as_number: f64,
+}
+
+#[derive(Deserialize)]
+pub struct NodeLoad {
+    pub name: String,
+    #[serde(rename = "heap.percent", default, deserialize_with = "deserialize_option_number_from_string")]
+    pub heap_percent: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub cpu: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub load_1m: Option<f64>,
+    #[serde(rename = "node.role")]
+    pub roles: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DiskAllocation {
+    pub node: String,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub shards: Option<u64>,
+    #[serde(rename = "disk.percent", default, deserialize_with = "deserialize_option_number_from_string")]
+    pub disk_percent: Option<f64>,
+    #[serde(rename = "disk.avail")]
+    pub disk_avail: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PendingTasksResponse {
+    #[serde(default)]
+    tasks: Vec<PendingTask>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PendingTask {
+    pub insert_order: u64,
+    pub priority: String,
+    pub source: String,
+    pub time_in_queue_millis: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ShardState {
+    pub index: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub shard: u32,
+    pub prirep: String,
+    pub state: String,
+}
+
+#[derive(Deserialize)]
+pub struct AllocationExplanation {
+    pub current_state: String,
+    pub can_allocate: Option<String>,
+    pub allocate_explanation: Option<String>,
+    pub unassigned_info: Option<Value>,
+    /// For assigned shards
+    pub move_explanation: Option<String>,
+    #[serde(default)]
+    pub node_allocation_decisions: Vec<NodeAllocationDecision>,
+}
+
+impl AllocationExplanation {
+    pub fn explanation(&self) -> Option<&str> {
+        self.allocate_explanation
+            .as_deref()
+            .or(self.move_explanation.as_deref())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NodeAllocationDecision {
+    pub node_name: String,
+    #[serde(default)]
+    pub deciders: Vec<Decider>,
+}
+
+#[derive(Deserialize)]
+pub struct Decider {
+    pub decider: String,
+    pub decision: String,
+    pub explanation: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explanation(value: Value) -> AllocationExplanation {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn disk_watermark() {
+        let explanation = explanation(json!({
+            "current_state": "unassigned",
+            "can_allocate": "no",
+            "allocate_explanation": "cannot allocate because allocation is not permitted to any of the nodes",
+            "node_allocation_decisions": [
+                { "node_name": "node-1", "deciders": [
+                    { "decider": "disk_threshold", "decision": "NO", "explanation": "the node is above the high watermark" },
+                ]},
+                { "node_name": "node-2", "deciders": [
+                    { "decider": "same_shard", "decision": "NO", "explanation": "a copy of this shard is already allocated to this node" },
+                    { "decider": "disk_threshold", "decision": "NO", "explanation": "the node is above the high watermark" },
+                ]},
+            ]
+        }));
+        let classification = classify_explanation(&explanation);
+        assert_eq!(classification.issue, AllocationIssue::DiskWatermark);
+        assert_eq!(classification.nodes, vec!["node-1", "node-2"]);
+        assert_eq!(classification.explanations.len(), 2);
+    }
+
+    #[test]
+    fn no_valid_copy() {
+        let explanation = explanation(json!({
+            "current_state": "unassigned",
+            "can_allocate": "no_valid_shard_copy",
+            "allocate_explanation": "cannot allocate because a previous copy of the primary shard existed but can no longer be found",
+        }));
+        let classification = classify_explanation(&explanation);
+        assert_eq!(classification.issue, AllocationIssue::NoValidCopy);
+        assert_eq!(
+            classification.explanations,
+            vec!["cannot allocate because a previous copy of the primary shard existed but can no longer be found"]
+        );
+    }
+
+    fn shard(index: &str, prirep: &str, state: &str) -> ShardState {
+        ShardState {
+            index: index.to_string(),
+            shard: 0,
+            prirep: prirep.to_string(),
+            state: state.to_string(),
+        }
+    }
+
+    #[test]
+    fn root_cause_chain() {
+        let snapshot = ClusterSnapshot {
+            health: serde_json::from_value(json!({ "status": "red", "number_of_nodes": 3, "number_of_data_nodes": 3 })).unwrap(),
+            shards: vec![
+                shard("logs-1", "p", "UNASSIGNED"),
+                shard("logs-1", "r", "UNASSIGNED"),
+                shard("metrics-1", "p", "STARTED"),
+                shard("metrics-1", "r", "UNASSIGNED"),
+            ],
+            nodes: vec![NodeLoad {
+                name: "node-1".to_string(),
+                heap_percent: Some(91.0),
+                cpu: Some(12.0),
+                load_1m: None,
+                roles: Some("dim".to_string()),
+            }],
+            allocation: vec![DiskAllocation {
+                node: "node-3".to_string(),
+                shards: Some(120),
+                disk_percent: Some(96.0),
+                disk_avail: Some("10gb".to_string()),
+            }],
+            pending_tasks: Vec::new(),
+            unassigned: UnassignedSummary {
+                unassigned_count: 3,
+                unexplained_count: 0,
+                reasons: vec![UnassignedReason {
+                    reason: AllocationIssue::DiskWatermark,
+                    shard_count: 3,
+                    primary_count: 1,
+                    suggestion: AllocationIssue::DiskWatermark.suggestion(),
+                    shards: vec!["logs-1[0]p".to_string()],
+                    nodes: vec!["node-3".to_string()],
+                    explanations: Vec::new(),
+                }],
+            },
+        };
+
+        let findings = analyze_cluster(&snapshot);
+        let summary = findings
+            .iter()
+            .map(|f| (f.severity, f.title.split(' ').take(3).collect::<Vec<_>>().join(" ")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (Severity::Critical, "1 red index(es):".to_string()),
+                (Severity::Critical, "3 shard(s) unassigned".to_string()),
+                (Severity::Critical, "Node node-3 disk".to_string()),
+                (Severity::Warning, "1 yellow index(es):".to_string()),
+                (Severity::Warning, "Node node-1 heap".to_string()),
+            ]
+        );
+        assert!(findings[1].title.ends_with("disk watermark on node(s) node-3."));
+    }
+
+    #[test]
+    fn healthy_cluster() {
+        let snapshot = ClusterSnapshot {
+            health: serde_json::from_value(json!({ "status": "green", "number_of_nodes": 1 })).unwrap(),
+            shards: vec![shard("logs-1", "p", "STARTED")],
+            nodes: Vec::new(),
+            allocation: Vec::new(),
+            pending_tasks: Vec::new(),
+            unassigned: UnassignedSummary {
+                unassigned_count: 0,
+                unexplained_count: 0,
+                reasons: Vec::new(),
+            },
+        };
+        let findings = analyze_cluster(&snapshot);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].title, "No issues found.");
+    }
+
+    #[test]
+    fn max_retries_wins_ties() {
+        let explanation = explanation(json!({
+            "current_state": "unassigned",
+            "can_allocate": "no",
+            "node_allocation_decisions": [
+                { "node_name": "node-1", "deciders": [
+                    { "decider": "max_retry", "decision": "NO", "explanation": "shard has exceeded the maximum number of retries [5]" },
+                    { "decider": "filter", "decision": "NO", "explanation": "node does not match index setting [index.routing.allocation.require]" },
+                ]},
+            ]
+        }));
+        assert_eq!(classify_explanation(&explanation).issue, AllocationIssue::MaxRetries);
+    }
+}
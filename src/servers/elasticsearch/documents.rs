@@ -0,0 +1,616 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Documents: get documents by id, and index, update or delete single documents.
+//!
+//! Write tools follow the two-step confirmation protocol. The confirmation token is bound to the
+//! version of the document shown in the preview, and the write is conditional on that version so
+//! that concurrent changes are never overwritten. Target indices are checked against the
+//! `allow_write_patterns` and `deny_write_patterns` configuration.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_response_chars, max_search_size, pack_json_value};
+use crate::servers::elasticsearch::safety::{check_confirmation_token, confirmation_token};
+use crate::servers::elasticsearch::template_changes::json_diff;
+use crate::servers::elasticsearch::templates::merge_json;
+use crate::servers::elasticsearch::{internal_error, read_json};
+use elasticsearch::http::StatusCode;
+use elasticsearch::params::{OpType, Refresh};
+use elasticsearch::{DeleteParts, Elasticsearch, GetParts, IndexParts, MgetParts, UpdateParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetDocumentParams {
+    /// Index or alias containing the document
+    index: String,
+    /// Document id (`_id`)
+    id: String,
+    /// Fields of `_source` to return (supports * wildcard). The whole document is returned if not provided.
+    source_includes: Option<Vec<String>>,
+    /// Fields of `_source` to leave out (supports * wildcard)
+    source_excludes: Option<Vec<String>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct MgetDocumentsParams {
+    /// Default index or alias of the documents
+    index: Option<String>,
+    /// Documents to fetch
+    docs: Vec<DocumentRef>,
+    /// Fields of `_source` to return (supports * wildcard). Whole documents are returned if not provided.
+    source_includes: Option<Vec<String>>,
+    /// Fields of `_source` to leave out (supports * wildcard)
+    source_excludes: Option<Vec<String>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct DocumentRef {
+    /// Document id (`_id`)
+    id: String,
+    /// Index or alias of the document, if different from the default index
+    index: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct IndexDocumentParams {
+    /// Index or alias to write to
+    index: String,
+    /// Document id. An existing document with this id is replaced, and an id is generated if not provided.
+    id: Option<String>,
+    /// The document
+    document: Map<String, Value>,
+    /// Wait until the change is visible to searches (default: false)
+    refresh: Option<bool>,
+    /// Token returned by a first call without it, after reviewing the change
+    confirmation_token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct UpdateDocumentParams {
+    /// Index or alias containing the document
+    index: String,
+    /// Document id (`_id`)
+    id: String,
+    /// Partial document, merged into the existing document (objects are merged, other values are replaced)
+    doc: Map<String, Value>,
+    /// Create the document from `doc` if it doesn't exist (default: false)
+    upsert: Option<bool>,
+    /// Wait until the change is visible to searches (default: false)
+    refresh: Option<bool>,
+    /// Token returned by a first call without it, after reviewing the change
+    confirmation_token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct DeleteDocumentParams {
+    /// Index or alias containing the document
+    index: String,
+    /// Document id (`_id`)
+    id: String,
+    /// Wait until the change is visible to searches (default: false)
+    refresh: Option<bool>,
+    /// Token returned by a first call without it, after reviewing the document
+    confirmation_token: Option<String>,
+}
+
+#[tool_router(router = document_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get a document
+    #[tool(
+        description = "Get a document by id, optionally with only some fields of its _source",
+        annotations(title = "Get document", read_only_hint = true)
+    )]
+    async fn get_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<GetDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let source_filter = SourceFilter::new(params.source_includes, params.source_excludes);
+        let document = fetch_document(&es_client, &params.index, &params.id, &source_filter).await?;
+
+        let Some(document) = document else {
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": format!("Document {} not found in {}.", params.id, params.index) }))?
+            ]));
+        };
+
+        let message = format!("Document {} found in index {}.", document.id, document.index);
+        let data = pack_json_value(&document, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get multiple documents
+    #[tool(
+        description = "Get multiple documents by id, from one or several indices, optionally with only some fields of their _source",
+        annotations(title = "Get documents", read_only_hint = true)
+    )]
+    async fn mget_documents(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<MgetDocumentsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        if params.docs.is_empty() {
+            return Err(rmcp::Error::invalid_params("No document requested.", None));
+        }
+        let max_docs = max_search_size() as usize;
+        if params.docs.len() > max_docs {
+            return Err(rmcp::Error::invalid_params(
+                format!("At most {max_docs} documents can be requested at once."),
+                None,
+            ));
+        }
+        if params.index.is_none() && params.docs.iter().any(|d| d.index.is_none()) {
+            return Err(rmcp::Error::invalid_params(
+                "Each document needs an index if no default index is provided.",
+                None,
+            ));
+        }
+
+        let source_filter = SourceFilter::new(params.source_includes, params.source_excludes);
+        let docs = params
+            .docs
+            .iter()
+            .map(|d| {
+                let mut doc = json!({ "_id": d.id });
+                if let Some(index) = &d.index {
+                    doc["_index"] = json!(index);
+                }
+                if let Some(source) = source_filter.to_json() {
+                    doc["_source"] = source;
+                }
+                doc
+            })
+            .collect::<Vec<_>>();
+
+        let parts = match &params.index {
+            Some(index) => MgetParts::Index(index),
+            None => MgetParts::None,
+        };
+        let response = es_client.mget(parts).body(json!({ "docs": docs })).send().await;
+        let response: MgetResponse = read_json(response).await?;
+
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        for doc in response.docs {
+            match doc {
+                MgetDoc::Found(doc) if doc.found => found.push(doc),
+                MgetDoc::Found(doc) => missing.push(json!({ "index": doc.index, "id": doc.id })),
+                MgetDoc::Error(doc) => missing.push(json!({
+                    "index": doc.index,
+                    "id": doc.id,
+                    "error": doc.error.get("reason").unwrap_or(&doc.error),
+                })),
+            }
+        }
+
+        let message = format!("{} document(s) found, {} missing.", found.len(), missing.len());
+        let data = pack_json_value(&json!({ "documents": found, "missing": missing }), max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: index a document
+    #[tool(
+        description = "Create or replace a document. A first call without confirmation_token shows the changes to an existing document and returns a token: review them with the user, then call again with the token.",
+        annotations(title = "Index document", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn index_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<IndexDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        // Also covers the concrete index of an existing document, or the write index of an alias
+        self.check_write_targets(&es_client, &params.index).await?;
+        let current = match &params.id {
+            Some(id) => fetch_current_document(&es_client, &params.index, id).await?,
+            None => None,
+        };
+
+        let document = Value::Object(params.document);
+        let token = confirmation_token(&[
+            "index_document",
+            &params.index,
+            params.id.as_deref().unwrap_or_default(),
+            &document.to_string(),
+            &version_state(current.as_ref()),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let (message, data) = match &current {
+                Some(current) => (
+                    format!(
+                        "Document {} in index {} will be replaced. Review the changes with the user, then call index_document again with the confirmation_token.",
+                        current.id, current.index
+                    ),
+                    json!({ "changes": document_changes(current.source.as_ref(), &document) }),
+                ),
+                None => (
+                    format!(
+                        "A new document will be created in {}. Review it with the user, then call index_document again with the confirmation_token.",
+                        params.index
+                    ),
+                    json!({ "document": document }),
+                ),
+            };
+            let data = pack_json_value(&data, max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "index_document without confirmation_token")?;
+
+        let parts = match &params.id {
+            Some(id) => IndexParts::IndexId(&params.index, id),
+            None => IndexParts::Index(&params.index),
+        };
+        let mut request = es_client.index(parts).body(document).refresh(refresh(params.refresh));
+        request = match &current {
+            Some(current) => request.if_seq_no(current.seq_no).if_primary_term(current.primary_term),
+            // The document didn't exist at preview time: fail if it was created since then
+            None if params.id.is_some() => request.op_type(OpType::Create),
+            None => request,
+        };
+        let response: WriteResponse = read_json(request.send().await).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": response.message() }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: update a document
+    #[tool(
+        description = "Update some fields of a document with a partial document, optionally creating it if missing. A first call without confirmation_token shows the changes and returns a token: review them with the user, then call again with the token.",
+        annotations(title = "Update document", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn update_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<UpdateDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        self.check_write_targets(&es_client, &params.index).await?;
+        let upsert = params.upsert.unwrap_or(false);
+        let current = fetch_current_document(&es_client, &params.index, &params.id).await?;
+        if current.is_none() && !upsert {
+            return Err(rmcp::Error::invalid_params(
+                format!("Document {} not found in {}. Set upsert to create it.", params.id, params.index),
+                None,
+            ));
+        }
+
+        let doc = Value::Object(params.doc);
+        let mut updated = current.as_ref().and_then(|c| c.source.clone()).unwrap_or_else(|| json!({}));
+        merge_json(&mut updated, &doc);
+        let changes = document_changes(current.as_ref().and_then(|c| c.source.as_ref()), &updated);
+        if changes.is_empty() {
+            return Err(rmcp::Error::invalid_params(
+                "The document already has the requested values, there is nothing to update.",
+                None,
+            ));
+        }
+
+        let token = confirmation_token(&[
+            "update_document",
+            &params.index,
+            &params.id,
+            &doc.to_string(),
+            &upsert.to_string(),
+            &version_state(current.as_ref()),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "Document {} will be {} with {} change(s). Review them with the user, then call update_document again with the confirmation_token.",
+                params.id,
+                if current.is_some() { "updated" } else { "created" },
+                changes.len()
+            );
+            let data = pack_json_value(&json!({ "changes": changes }), max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "update_document without confirmation_token")?;
+
+        let response = match &current {
+            Some(current) => {
+                es_client
+                    .update(UpdateParts::IndexId(&current.index, &params.id))
+                    .body(json!({ "doc": doc }))
+                    .if_seq_no(current.seq_no)
+                    .if_primary_term(current.primary_term)
+                    .refresh(refresh(params.refresh))
+                    .send()
+                    .await
+            }
+            // Upsert of a document that didn't exist at preview time: fail if it was created since then
+            None => {
+                es_client
+                    .index(IndexParts::IndexId(&params.index, &params.id))
+                    .body(doc)
+                    .op_type(OpType::Create)
+                    .refresh(refresh(params.refresh))
+                    .send()
+                    .await
+            }
+        };
+        let response: WriteResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": response.message() }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: delete a document
+    #[tool(
+        description = "Delete a document by id. A first call without confirmation_token shows the document and returns a token: review it with the user, then call again with the token.",
+        annotations(title = "Delete document", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn delete_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<DeleteDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        self.check_write_targets(&es_client, &params.index).await?;
+        let Some(current) = fetch_current_document(&es_client, &params.index, &params.id).await? else {
+            return Err(rmcp::Error::invalid_params(
+                format!("Document {} not found in {}.", params.id, params.index),
+                None,
+            ));
+        };
+
+        let token = confirmation_token(&[
+            "delete_document",
+            &params.index,
+            &params.id,
+            &version_state(Some(&current)),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "Document {} will be deleted from index {}. Review it with the user, then call delete_document again with the confirmation_token.",
+                current.id, current.index
+            );
+            let data = pack_json_value(&current, max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "delete_document without confirmation_token")?;
+
+        let response = es_client
+            .delete(DeleteParts::IndexId(&current.index, &params.id))
+            .if_seq_no(current.seq_no)
+            .if_primary_term(current.primary_term)
+            .refresh(refresh(params.refresh))
+            .send()
+            .await;
+        let response: WriteResponse = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": response.message() }))?
+        ]))
+    }
+}
+
+/// `_source` filtering of get requests
+struct SourceFilter {
+    includes: Vec<String>,
+    excludes: Vec<String>,
+}
+
+impl SourceFilter {
+    fn new(includes: Option<Vec<String>>, excludes: Option<Vec<String>>) -> Self {
+        SourceFilter {
+            includes: includes.unwrap_or_default(),
+            excludes: excludes.unwrap_or_default(),
+        }
+    }
+
+    /// The `_source` parameter of a document in a mget request, if filtering is requested
+    fn to_json(&self) -> Option<Value> {
+        if self.includes.is_empty() && self.excludes.is_empty() {
+            None
+        } else {
+            Some(json!({ "includes": self.includes, "excludes": self.excludes }))
+        }
+    }
+}
+
+/// Get a document, or `None` if it doesn't exist. A missing index is an error.
+async fn fetch_document(
+    es_client: &Elasticsearch,
+    index: &str,
+    id: &str,
+    source_filter: &SourceFilter,
+) -> Result<Option<Document>, rmcp::Error> {
+    let includes = source_filter.includes.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let excludes = source_filter.excludes.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let mut request = es_client.get(GetParts::IndexId(index, id));
+    if !includes.is_empty() {
+        request = request._source_includes(&includes);
+    }
+    if !excludes.is_empty() {
+        request = request._source_excludes(&excludes);
+    }
+
+    // A missing document is a 404 with a regular response, a missing index is a 404 with an error
+    let response = match request.send().await {
+        Ok(response) if response.status_code() == StatusCode::NOT_FOUND => {
+            let body: Value = response.json().await.map_err(internal_error)?;
+            return match body.get("found") {
+                Some(_) => Ok(None),
+                None => Err(rmcp::Error::invalid_params(format!("Index {index} not found."), None)),
+            };
+        }
+        response => response,
+    };
+    let document: Document = read_json(response).await?;
+    Ok(document.found.then_some(document))
+}
+
+/// The whole current version of a document, before modifying it
+async fn fetch_current_document(
+    es_client: &Elasticsearch,
+    index: &str,
+    id: &str,
+) -> Result<Option<Document>, rmcp::Error> {
+    fetch_document(es_client, index, id, &SourceFilter::new(None, None)).await
+}
+
+/// Version of a document the confirmation token is bound to
+fn version_state(document: Option<&Document>) -> String {
+    match document {
+        Some(doc) => format!("{}/{}:{}:{}", doc.index, doc.id, doc.seq_no, doc.primary_term),
+        None => "missing".to_string(),
+    }
+}
+
+/// Changed fields of a document, as `{path, current, proposed}` objects
+fn document_changes(current: Option<&Value>, proposed: &Value) -> Vec<Value> {
+    let mut changes = Vec::new();
+    json_diff(current.unwrap_or(&json!({})), proposed, "", &mut changes);
+    changes
+}
+
+fn refresh(refresh: Option<bool>) -> Refresh {
+    if refresh.unwrap_or(false) {
+        Refresh::WaitFor
+    } else {
+        Refresh::False
+    }
+}
+
+//----- Responses
+
+#[derive(Serialize, Deserialize)]
+struct Document {
+    #[serde(rename = "_index")]
+    index: String,
+    #[serde(rename = "_id")]
+    id: String,
+    #[serde(default)]
+    found: bool,
+    #[serde(rename = "_version", skip_serializing_if = "Option::is_none")]
+    version: Option<u64>,
+    #[serde(rename = "_seq_no", default, skip_serializing)]
+    seq_no: i64,
+    #[serde(rename = "_primary_term", default, skip_serializing)]
+    primary_term: i64,
+    #[serde(rename = "_routing", skip_serializing_if = "Option::is_none")]
+    routing: Option<String>,
+    #[serde(rename = "_source", skip_serializing_if = "Option::is_none")]
+    source: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct MgetResponse {
+    docs: Vec<MgetDoc>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MgetDoc {
+    Error(MgetError),
+    Found(Document),
+}
+
+#[derive(Deserialize)]
+struct MgetError {
+    #[serde(rename = "_index")]
+    index: String,
+    #[serde(rename = "_id")]
+    id: String,
+    error: Value,
+}
+
+#[derive(Deserialize)]
+struct WriteResponse {
+    #[serde(rename = "_index")]
+    index: String,
+    #[serde(rename = "_id")]
+    id: String,
+    /// `created`, `updated`, `deleted` or `noop`
+    result: String,
+}
+
+impl WriteResponse {
+    fn message(&self) -> String {
+        format!("Document {} in index {}: {}.", self.id, self.index, self.result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_of_partial_update() {
+        let current = json!({ "name": "Jane", "address": { "city": "Paris", "zip": "75001" }, "tags": ["a", "b"] });
+        let mut updated = current.clone();
+        merge_json(&mut updated, &json!({ "address": { "city": "Lyon" }, "tags": ["c"] }));
+
+        assert_eq!(
+            document_changes(Some(&current), &updated),
+            vec![
+                json!({ "path": "address.city", "current": "Paris", "proposed": "Lyon" }),
+                json!({ "path": "tags", "current": ["a", "b"], "proposed": ["c"] }),
+            ]
+        );
+        // A new document only has additions
+        assert_eq!(
+            document_changes(None, &json!({ "name": "Jane" })),
+            vec![json!({ "path": "name", "current": null, "proposed": "Jane" })]
+        );
+    }
+
+    #[test]
+    fn mget_docs() {
+        let response: MgetResponse = serde_json::from_value(json!({ "docs": [
+            { "_index": "customers", "_id": "1", "_version": 3, "_seq_no": 12, "_primary_term": 1, "found": true, "_source": { "name": "Jane" } },
+            { "_index": "customers", "_id": "2", "found": false },
+            { "_index": "missing", "_id": "3", "error": { "type": "index_not_found_exception", "reason": "no such index [missing]" } },
+        ]}))
+        .unwrap();
+
+        assert!(matches!(&response.docs[0], MgetDoc::Found(doc) if doc.found && doc.seq_no == 12));
+        assert!(matches!(&response.docs[1], MgetDoc::Found(doc) if !doc.found));
+        assert!(matches!(&response.docs[2], MgetDoc::Error(doc) if doc.index == "missing"));
+    }
+}
@@ -0,0 +1,482 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Field lists: flattened fields of large mappings, and fields merged across several indices
+//! with detection of fields whose type differs between indices.
+
+use crate::servers::elasticsearch::base_tools::{
+    EsBaseTools, MappingProperty, MappingResponse, max_response_chars, pack_json_value,
+};
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::indices::IndicesGetMappingParts;
+use elasticsearch::{Elasticsearch, FieldCapsParts};
+use regex::Regex;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Maximum number of index names listed for each type of a conflicting field
+const MAX_CONFLICT_INDICES: usize = 10;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListFieldsParams {
+    /// Name of the Elasticsearch index, alias, data stream or index pattern
+    index: String,
+    /// Optional field name filter: a pattern with * wildcards (e.g. 'host.*'), or a regular
+    /// expression between slashes (e.g. '/^(source|destination)\.ip$/')
+    name_pattern: Option<String>,
+    /// Optional comma-separated field types to keep (e.g. 'keyword,ip')
+    types: Option<String>,
+    /// Include object and nested fields, that only hold sub-fields (default: false)
+    include_objects: Option<bool>,
+}
+
+#[tool_router(router = field_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list fields
+    #[tool(
+        description = "List the fields of an index as a compact list of dotted paths with their type, including multi-fields (e.g. message.keyword), and whether they are indexed and have doc values. Can filter by name (wildcard pattern or /regex/) and type. Use it instead of get_mappings for large mappings.",
+        annotations(title = "List fields", read_only_hint = true)
+    )]
+    async fn list_fields(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ListFieldsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let filter = FieldFilter::new(
+            params.name_pattern.as_deref(),
+            params.types.as_deref(),
+            params.include_objects.unwrap_or(false),
+        )?;
+
+        let response = es_client
+            .indices()
+            .get_mapping(IndicesGetMappingParts::Index(&[&params.index]))
+            .send()
+            .await;
+        let mappings: MappingResponse = read_json(response).await?;
+
+        // Indices matching a pattern usually share most of their fields
+        let all_fields = mappings
+            .values()
+            .flat_map(|mapping| flatten_fields(&mapping.mappings.properties))
+            .collect::<BTreeSet<_>>();
+        let total = all_fields.len();
+        let mut fields = all_fields.into_iter().filter(|f| filter.matches(f)).collect::<Vec<_>>();
+
+        let paths = fields.iter().map(|f| f.field.as_str()).collect::<BTreeSet<_>>();
+        let mut message = format!(
+            "{} of {total} fields of {} index(es) matching {} are listed.",
+            fields.len(),
+            mappings.len(),
+            params.index
+        );
+        if paths.len() < fields.len() {
+            message.push_str(" Some fields are listed more than once as their definition differs across indices, use get_mappings with merge to find type conflicts.");
+        }
+        let omitted = truncate_fields(&mut fields, max_response_chars());
+        if omitted > 0 {
+            message.push_str(&format!(
+                " {omitted} of them were omitted to fit the response size limit: use name_pattern or types to narrow the list."
+            ));
+        }
+
+        let data = pack_json_value(&fields, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+/// A field of a mapping
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct FieldInfo {
+    pub field: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    /// Not searchable (`index: false`)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub not_indexed: bool,
+    /// Not aggregatable nor sortable (`doc_values: false`)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub no_doc_values: bool,
+    /// Parent field of a multi-field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multi_field_of: Option<String>,
+}
+
+/// List all fields of a mapping, including sub-fields of objects and multi-fields, sorted by path
+pub fn flatten_fields(properties: &HashMap<String, MappingProperty>) -> Vec<FieldInfo> {
+    fn sub_properties(property: &MappingProperty, key: &str) -> Option<HashMap<String, MappingProperty>> {
+        HashMap::<String, MappingProperty>::deserialize(property.settings.get(key)?).ok()
+    }
+
+    fn field_info(path: String, property: &MappingProperty, multi_field_of: Option<&str>) -> FieldInfo {
+        let flag = |key: &str| property.settings.get(key).and_then(|v| v.as_bool());
+        FieldInfo {
+            field: path,
+            field_type: property.field_type().to_string(),
+            not_indexed: flag("index") == Some(false),
+            no_doc_values: flag("doc_values") == Some(false),
+            multi_field_of: multi_field_of.map(|p| p.to_string()),
+        }
+    }
+
+    fn collect(properties: &HashMap<String, MappingProperty>, prefix: &str, result: &mut Vec<FieldInfo>) {
+        for (name, property) in properties {
+            let path = if prefix.is_empty() { name.clone() } else { format!("{prefix}.{name}") };
+            if let Some(multi_fields) = sub_properties(property, "fields") {
+                for (sub_name, sub_property) in &multi_fields {
+                    result.push(field_info(format!("{path}.{sub_name}"), sub_property, Some(&path)));
+                }
+            }
+            if let Some(sub_properties) = sub_properties(property, "properties") {
+                collect(&sub_properties, &path, result);
+            }
+            result.push(field_info(path, property, None));
+        }
+    }
+
+    let mut result = Vec::new();
+    collect(properties, "", &mut result);
+    result.sort();
+    result
+}
+
+/// Keep the first fields whose serialized list fits in `max_chars`, and return the number of
+/// fields removed
+fn truncate_fields(fields: &mut Vec<FieldInfo>, max_chars: usize) -> usize {
+    // Brackets of the array, and a comma between fields
+    let mut length = 2;
+    let kept = fields
+        .iter()
+        .take_while(|field| {
+            length += serde_json::to_string(field).map_or(0, |s| s.len()) + 1;
+            length <= max_chars + 1
+        })
+        .count();
+    let omitted = fields.len() - kept;
+    fields.truncate(kept);
+    omitted
+}
+
+/// Field name and type filter of `list_fields`
+struct FieldFilter {
+    name: Option<Regex>,
+    types: Option<Vec<String>>,
+    include_objects: bool,
+}
+
+impl FieldFilter {
+    fn new(name_pattern: Option<&str>, types: Option<&str>, include_objects: bool) -> Result<Self, rmcp::Error> {
+        let name = match name_pattern {
+            Some(pattern) if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') => {
+                let regex = Regex::new(&pattern[1..pattern.len() - 1]).map_err(|e| {
+                    rmcp::Error::invalid_params(format!("Invalid regular expression in name_pattern: {e}"), None)
+                })?;
+                Some(regex)
+            }
+            Some(pattern) => {
+                let regex = format!("^{}$", regex::escape(pattern).replace(r"\*", ".*"));
+                Some(Regex::new(&regex).map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?)
+            }
+            None => None,
+        };
+        let types = types.map(|t| t.split(',').map(|t| t.trim().to_string()).collect());
+        Ok(FieldFilter {
+            name,
+            types,
+            include_objects,
+        })
+    }
+
+    fn matches(&self, field: &FieldInfo) -> bool {
+        let is_object = matches!(field.field_type.as_str(), "object" | "nested");
+        let type_matches = match &self.types {
+            Some(types) => types.contains(&field.field_type),
+            None => self.include_objects || !is_object,
+        };
+        type_matches && self.name.as_ref().is_none_or(|re| re.is_match(&field.field))
+    }
+}
+
+/// Source of merged field lists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldsBackend {
+    /// Full mappings of each index
+    #[default]
+    Mappings,
+    /// Field capabilities API, cheaper on large clusters as it doesn't return full mappings
+    FieldCaps,
+}
+
+/// A field of several indices, with its type in each of them
+#[derive(Debug, Serialize)]
+pub struct MergedField {
+    pub field: String,
+    /// Types of this field, sorted
+    pub types: Vec<String>,
+    /// Indices having each type, only if there are several types
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indices_by_type: Option<BTreeMap<String, Vec<String>>>,
+}
+
+impl MergedField {
+    pub fn is_conflict(&self) -> bool {
+        self.types.len() > 1
+    }
+}
+
+/// The fields of all indices matching an index expression, sorted by name
+pub async fn fetch_merged_fields(
+    es_client: &Elasticsearch,
+    index: &str,
+    backend: FieldsBackend,
+) -> Result<MergedFields, rmcp::Error> {
+    match backend {
+        FieldsBackend::Mappings => {
+            let response = es_client
+                .indices()
+                .get_mapping(IndicesGetMappingParts::Index(&[index]))
+                .send()
+                .await;
+            let mappings: MappingResponse = read_json(response).await?;
+            Ok(merge_mappings(&mappings))
+        }
+        FieldsBackend::FieldCaps => {
+            let response = es_client
+                .field_caps(FieldCapsParts::Index(&[index]))
+                .fields(&["*"])
+                .send()
+                .await;
+            let response: FieldCapsResponse = read_json(response).await?;
+            Ok(merge_field_caps(response))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MergedFields {
+    pub index_count: usize,
+    pub fields: Vec<MergedField>,
+}
+
+impl MergedFields {
+    pub fn conflicts(&self) -> impl Iterator<Item = &MergedField> {
+        self.fields.iter().filter(|f| f.is_conflict())
+    }
+}
+
+/// Merge the mappings of several indices
+fn merge_mappings(mappings: &MappingResponse) -> MergedFields {
+    // field -> type -> indices
+    let mut fields = BTreeMap::<String, BTreeMap<String, Vec<String>>>::new();
+    for (index, mapping) in mappings {
+        for field in flatten_fields(&mapping.mappings.properties) {
+            fields
+                .entry(field.field)
+                .or_default()
+                .entry(field.field_type)
+                .or_default()
+                .push(index.clone());
+        }
+    }
+
+    MergedFields {
+        index_count: mappings.len(),
+        fields: fields
+            .into_iter()
+            .map(|(field, indices_by_type)| merged_field(field, indices_by_type))
+            .collect(),
+    }
+}
+
+fn merged_field(field: String, mut indices_by_type: BTreeMap<String, Vec<String>>) -> MergedField {
+    let types = indices_by_type.keys().cloned().collect::<Vec<_>>();
+    let indices_by_type = (types.len() > 1).then(|| {
+        for indices in indices_by_type.values_mut() {
+            indices.sort();
+            indices.truncate(MAX_CONFLICT_INDICES);
+        }
+        indices_by_type
+    });
+    MergedField {
+        field,
+        types,
+        indices_by_type,
+    }
+}
+
+/// Merge field capabilities. Indices are only listed by the API for fields with several types.
+fn merge_field_caps(response: FieldCapsResponse) -> MergedFields {
+    let fields = response
+        .fields
+        .into_iter()
+        // Metadata fields (_id, _index, etc.)
+        .filter(|(field, _)| !field.starts_with('_'))
+        .map(|(field, caps)| {
+            let indices_by_type = caps
+                .into_iter()
+                .map(|(field_type, caps)| (field_type, caps.indices.unwrap_or_default()))
+                .collect();
+            merged_field(field, indices_by_type)
+        })
+        .collect();
+
+    MergedFields {
+        index_count: response.indices.len(),
+        fields,
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct FieldCapsResponse {
+    #[serde(default)]
+    indices: Vec<String>,
+    fields: BTreeMap<String, HashMap<String, FieldCaps>>,
+}
+
+#[derive(Deserialize)]
+struct FieldCaps {
+    indices: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_conflicting_mappings() {
+        let mappings: MappingResponse = serde_json::from_value(json!({
+            "logs-1": { "mappings": { "properties": {
+                "status": { "type": "keyword" },
+                "host": { "properties": { "name": { "type": "keyword" } } },
+                "user": { "type": "text", "fields": { "keyword": { "type": "keyword" } } }
+            }}},
+            "logs-2": { "mappings": { "properties": {
+                "status": { "type": "long" },
+                "host": { "properties": { "name": { "type": "keyword" } } },
+                "user": { "type": "text", "fields": { "keyword": { "type": "wildcard" } } }
+            }}}
+        }))
+        .unwrap();
+
+        let merged = merge_mappings(&mappings);
+        assert_eq!(merged.index_count, 2);
+        let fields = merged.fields.iter().map(|f| f.field.as_str()).collect::<Vec<_>>();
+        assert_eq!(fields, vec!["host", "host.name", "status", "user", "user.keyword"]);
+
+        let conflicts = merged.conflicts().collect::<Vec<_>>();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].types, vec!["keyword", "long"]);
+        assert_eq!(conflicts[1].field, "user.keyword");
+        assert_eq!(
+            conflicts[0].indices_by_type.as_ref().unwrap()["long"],
+            vec!["logs-2".to_string()]
+        );
+        assert!(merged.fields[1].indices_by_type.is_none());
+    }
+
+    #[test]
+    fn merge_field_capabilities() {
+        let response: FieldCapsResponse = serde_json::from_value(json!({
+            "indices": ["logs-1", "logs-2"],
+            "fields": {
+                "_id": { "_id": { "type": "_id", "searchable": true, "aggregatable": false } },
+                "status": {
+                    "keyword": { "type": "keyword", "searchable": true, "aggregatable": true, "indices": ["logs-1"] },
+                    "long": { "type": "long", "searchable": true, "aggregatable": true, "indices": ["logs-2"] }
+                },
+                "message": { "text": { "type": "text", "searchable": true, "aggregatable": false } }
+            }
+        }))
+        .unwrap();
+
+        let merged = merge_field_caps(response);
+        assert_eq!(merged.index_count, 2);
+        assert_eq!(merged.fields.len(), 2);
+        assert_eq!(merged.conflicts().map(|f| f.field.as_str()).collect::<Vec<_>>(), vec!["status"]);
+    }
+
+    #[test]
+    fn flatten_and_filter_fields() {
+        let mappings: MappingResponse = serde_json::from_value(json!({
+            "logs": { "mappings": { "properties": {
+                "message": { "type": "text", "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } } },
+                "host": { "properties": { "ip": { "type": "ip" }, "name": { "type": "keyword", "doc_values": false } } },
+                "raw": { "type": "keyword", "index": false }
+            }}}
+        }))
+        .unwrap();
+        let fields = flatten_fields(&mappings["logs"].mappings.properties);
+
+        let paths = fields.iter().map(|f| f.field.as_str()).collect::<Vec<_>>();
+        assert_eq!(paths, vec!["host", "host.ip", "host.name", "message", "message.keyword", "raw"]);
+        assert_eq!(fields[4].multi_field_of.as_deref(), Some("message"));
+        assert!(fields[2].no_doc_values);
+        assert!(fields[5].not_indexed);
+        assert_eq!(
+            serde_json::to_value(&fields[4]).unwrap(),
+            json!({ "field": "message.keyword", "type": "keyword", "multi_field_of": "message" })
+        );
+
+        let names = |filter: FieldFilter| {
+            fields
+                .iter()
+                .filter(|f| filter.matches(f))
+                .map(|f| f.field.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(FieldFilter::new(Some("host.*"), None, false).unwrap()), vec!["host.ip", "host.name"]);
+        assert_eq!(names(FieldFilter::new(Some("/keyword$/"), None, false).unwrap()), vec!["message.keyword"]);
+        assert_eq!(names(FieldFilter::new(None, Some("keyword, ip"), false).unwrap()).len(), 4);
+        assert_eq!(names(FieldFilter::new(None, None, true).unwrap()).len(), 6);
+        assert!(FieldFilter::new(Some("/(/"), None, false).is_err());
+    }
+
+    #[test]
+    fn fields_truncated_to_budget() {
+        let field = |i: usize| FieldInfo {
+            field: format!("field_{i:03}"),
+            field_type: "keyword".to_string(),
+            not_indexed: false,
+            no_doc_values: false,
+            multi_field_of: None,
+        };
+        let mut fields = (0..100).map(field).collect::<Vec<_>>();
+        assert_eq!(truncate_fields(&mut fields, 100_000), 0);
+        assert_eq!(fields.len(), 100);
+
+        let omitted = truncate_fields(&mut fields, 1000);
+        assert!(omitted > 0);
+        assert_eq!(fields.len() + omitted, 100);
+        assert!(serde_json::to_string(&fields).unwrap().len() <= 1000);
+        fields.push(field(fields.len()));
+        assert!(serde_json::to_string(&fields).unwrap().len() > 1000);
+    }
+}
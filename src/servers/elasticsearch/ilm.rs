@@ -0,0 +1,653 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Index lifecycle management (ILM): lifecycle state of indices, with detection of indices in
+//! error or stuck in a step, policies, and retry of failed steps.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_index_list, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::safety::{check_confirmation_token, confirmation_token};
+use elasticsearch::Elasticsearch;
+use elasticsearch::cat::CatIndicesParts;
+use elasticsearch::ilm::{IlmExplainLifecycleParts, IlmGetLifecycleParts, IlmRetryParts};
+use elasticsearch::params::Bytes;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default time in a step after which an index is considered stuck
+const DEFAULT_STUCK_AFTER_HOURS: u64 = 24;
+
+/// Step reached when all actions of a phase are done, where indices wait for the next phase
+const COMPLETE_STEP: &str = "complete";
+
+/// Step where indices wait for the rollover conditions of their policy to be met
+const ROLLOVER_STEP: &str = "check-rollover-ready";
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct IlmExplainParams {
+    /// Index pattern of the indices to explain (supports * wildcard, data streams and aliases)
+    index_pattern: String,
+    /// Hours spent in the same step after which an index is reported as stuck (default: 24). Indices waiting
+    /// for rollover are only reported once the rollover conditions of their policy are exceeded.
+    stuck_after_hours: Option<u64>,
+    /// Only list indices in error or stuck (default: false)
+    only_problems: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetIlmPolicyParams {
+    /// Optional policy name. All policies are returned if not provided.
+    name: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct IlmRetryParams {
+    /// Index pattern of the indices to retry (supports * wildcard). Only indices in the ERROR step are retried.
+    index_pattern: String,
+    /// Token returned by a first call without it, after reviewing the indices to retry
+    confirmation_token: Option<String>,
+}
+
+#[tool_router(router = ilm_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: explain index lifecycle
+    #[tool(
+        description = "Explain the index lifecycle (ILM) state of indices as a table: policy, phase, action, step and time spent in the step. Highlights indices in the ERROR step or stuck in a step for too long (e.g. an index that hasn't rolled over although it exceeds the rollover conditions of its policy), with the reason.",
+        annotations(title = "Explain index lifecycle", read_only_hint = true)
+    )]
+    async fn ilm_explain(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<IlmExplainParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let indices = fetch_lifecycle_states(&es_client, &params.index_pattern).await?;
+        let stuck_after_ms = params.stuck_after_hours.unwrap_or(DEFAULT_STUCK_AFTER_HOURS).saturating_mul(3_600_000);
+        let now = now_millis();
+
+        // Sizes and document counts are only needed to check the rollover conditions of indices waiting for it
+        let awaiting_rollover = indices
+            .values()
+            .any(|i| i.managed && i.step.as_deref() == Some(ROLLOVER_STEP));
+        let usage = if awaiting_rollover {
+            fetch_index_usage(&es_client, &params.index_pattern).await?
+        } else {
+            BTreeMap::new()
+        };
+
+        let unmanaged = indices.values().filter(|i| !i.managed).count();
+        let mut rows = indices
+            .values()
+            .filter(|i| i.managed)
+            .map(|i| lifecycle_row(i, now, stuck_after_ms, usage.get(&i.index)))
+            .collect::<Vec<_>>();
+        let errors = rows.iter().filter(|r| r.status == LifecycleStatus::Error).count();
+        let stuck = rows.iter().filter(|r| r.status == LifecycleStatus::Stuck).count();
+        let managed = rows.len();
+
+        if params.only_problems.unwrap_or(false) {
+            rows.retain(|r| r.status != LifecycleStatus::Ok);
+        }
+        rows.sort_by(|a, b| a.status.cmp(&b.status).then(b.hours_in_step.cmp(&a.hours_in_step)));
+
+        let max_list = max_index_list();
+        let mut message = format!(
+            "{managed} managed index(es): {errors} in error, {stuck} stuck. {unmanaged} index(es) not managed by ILM."
+        );
+        if rows.len() > max_list {
+            message.push_str(&format!(" Showing the first {max_list}, use only_problems or a narrower index_pattern."));
+            rows.truncate(max_list);
+        }
+
+        let data = pack_json_value(&rows, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get ILM policies
+    #[tool(
+        description = "Get index lifecycle (ILM) policies, with their phases, the actions of each phase and where they're used.",
+        annotations(title = "Get ILM policies", read_only_hint = true)
+    )]
+    async fn get_ilm_policy(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetIlmPolicyParams { name }): Parameters<GetIlmPolicyParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let parts = match &name {
+            Some(name) => IlmGetLifecycleParts::Policy(name),
+            None => IlmGetLifecycleParts::None,
+        };
+        let response = es_client.ilm().get_lifecycle(parts).send().await;
+        let policies: BTreeMap<String, IlmPolicyEntry> = read_json(response).await?;
+
+        let policies = policies
+            .into_iter()
+            .map(|(name, entry)| {
+                let phases = entry.policy["phases"].clone();
+                let in_use_by = entry.in_use_by.map(|u| {
+                    json!({
+                        "indices": u["indices"].as_array().map(|a| a.len()),
+                        "data_streams": u["data_streams"],
+                        "composable_templates": u["composable_templates"],
+                    })
+                });
+                json!({
+                    "name": name,
+                    "version": entry.version,
+                    "modified_date": entry.modified_date,
+                    "phases": phases,
+                    "in_use_by": in_use_by,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let data = pack_json_value(&policies, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Found {} ILM policy(ies).", policies.len()), "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: retry failed ILM steps
+    ///
+    /// Two-step protocol: a first call lists the indices in error and returns a confirmation token,
+    /// that must be provided in a second call to actually retry them.
+    #[tool(
+        description = "Retry the failed lifecycle (ILM) step of indices in the ERROR step, once the cause has been fixed. A first call without confirmation_token lists the indices that will be retried and returns a token: review them with the user, then call again with the token.",
+        annotations(title = "Retry failed ILM steps", read_only_hint = false, destructive_hint = false)
+    )]
+    async fn ilm_retry(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<IlmRetryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let indices = fetch_lifecycle_states(&es_client, &params.index_pattern).await?;
+        let failed = indices
+            .values()
+            .filter(|i| i.managed && i.step.as_deref() == Some("ERROR"))
+            .collect::<Vec<_>>();
+        if failed.is_empty() {
+            return Err(rmcp::Error::invalid_params(
+                format!("No index matching '{}' is in the ERROR step.", params.index_pattern),
+                None,
+            ));
+        }
+
+        let failed_steps = failed
+            .iter()
+            .map(|i| format!("{}/{}", i.index, i.failed_step.as_deref().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join(",");
+        let token = confirmation_token(&["ilm_retry", &params.index_pattern, &failed_steps]);
+
+        let now = now_millis();
+        let Some(provided) = params.confirmation_token else {
+            let rows = failed.iter().map(|i| lifecycle_row(i, now, 0, None)).collect::<Vec<_>>();
+            let message = format!(
+                "{} index(es) in the ERROR step will be retried. Check that the cause of the failures is fixed, then call ilm_retry again with the confirmation_token.",
+                rows.len()
+            );
+            let data = pack_json_value(&rows, max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "ilm_retry without confirmation_token")?;
+
+        let names = failed.iter().map(|i| i.index.as_str()).collect::<Vec<_>>().join(",");
+        let response = es_client.ilm().retry(IlmRetryParts::Index(&names)).send().await;
+        let _: Value = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({
+                "message": format!("Retried the failed step of {} index(es). Use ilm_explain to follow their progress.", failed.len()),
+                "data": failed.iter().map(|i| &i.index).collect::<Vec<_>>(),
+            }))?
+        ]))
+    }
+}
+
+async fn fetch_lifecycle_states(
+    es_client: &Elasticsearch,
+    index_pattern: &str,
+) -> Result<BTreeMap<String, IndexLifecycle>, rmcp::Error> {
+    let response = es_client
+        .ilm()
+        .explain_lifecycle(IlmExplainLifecycleParts::Index(index_pattern))
+        .send()
+        .await;
+    let response: IlmExplainResponse = read_json(response).await?;
+    Ok(response.indices)
+}
+
+/// Primary shards, documents and primary store size of indices, by index name
+async fn fetch_index_usage(
+    es_client: &Elasticsearch,
+    index_pattern: &str,
+) -> Result<BTreeMap<String, IndexUsage>, rmcp::Error> {
+    let response = es_client
+        .cat()
+        .indices(CatIndicesParts::Index(&[index_pattern]))
+        .h(&["index", "pri", "docs.count", "pri.store.size"])
+        .bytes(Bytes::B)
+        .format("json")
+        .send()
+        .await;
+    let rows: Vec<CatIndexUsage> = read_json(response).await?;
+    let parse = |value: &Option<String>| value.as_deref().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let usage = IndexUsage {
+                primary_shards: parse(&row.pri).max(1),
+                docs: parse(&row.docs_count),
+                primary_bytes: parse(&row.pri_store_size),
+            };
+            (row.index, usage)
+        })
+        .collect())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Lifecycle status, ordered by urgency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum LifecycleStatus {
+    Error,
+    Stuck,
+    Ok,
+}
+
+#[derive(Serialize)]
+struct LifecycleRow {
+    index: String,
+    status: LifecycleStatus,
+    policy: Option<String>,
+    phase: Option<String>,
+    action: Option<String>,
+    step: Option<String>,
+    /// Age since the lifecycle date (creation or rollover)
+    age: Option<String>,
+    hours_in_step: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+fn lifecycle_row(index: &IndexLifecycle, now: u64, stuck_after_ms: u64, usage: Option<&IndexUsage>) -> LifecycleRow {
+    let time_in_step = index.step_time_millis.map(|t| now.saturating_sub(t));
+    let step = index.step.as_deref().unwrap_or_default();
+
+    let step_reason = index.step_info.as_ref().and_then(|info| {
+        info["reason"]
+            .as_str()
+            .or(info["message"].as_str())
+            .map(|r| r.to_string())
+    });
+
+    let (status, reason) = if step == "ERROR" {
+        let reason = format!(
+            "Failed step '{}'{}{}",
+            index.failed_step.as_deref().unwrap_or("unknown"),
+            step_reason.map(|r| format!(": {r}")).unwrap_or_default(),
+            match (index.is_auto_retryable_error, index.failed_step_retry_count) {
+                (Some(true), Some(count)) => format!(" (automatically retried {count} time(s))"),
+                _ => String::new(),
+            }
+        );
+        (LifecycleStatus::Error, Some(reason))
+    } else if step == ROLLOVER_STEP {
+        // Waiting for rollover is the normal state of write indices: it's only a problem once the
+        // rollover conditions are exceeded
+        let age = index.lifecycle_date_millis.map(|t| now.saturating_sub(t));
+        let exceeded = match index.rollover_conditions() {
+            Some(conditions) if stuck_after_ms > 0 => conditions.exceeded(age, usage, stuck_after_ms),
+            _ => Vec::new(),
+        };
+        if exceeded.is_empty() {
+            (LifecycleStatus::Ok, step_reason)
+        } else {
+            let reason = format!(
+                "The rollover conditions of the policy are exceeded ({}) but the index hasn't rolled over. {}",
+                exceeded.join(", "),
+                step_reason.unwrap_or_else(|| {
+                    "Check that the rollover alias exists and points to this index as its write index.".to_string()
+                })
+            );
+            (LifecycleStatus::Stuck, Some(reason))
+        }
+    } else if step != COMPLETE_STEP
+        && stuck_after_ms > 0
+        && time_in_step.is_some_and(|t| t >= stuck_after_ms)
+    {
+        let reason = match (step, step_reason) {
+            (_, Some(reason)) => reason,
+            ("wait-for-active-shards", None) | ("check-migration", None) | ("check-allocation", None) => {
+                "Waiting for shards to be allocated: check for unassigned shards and allocation rules.".to_string()
+            }
+            _ => "No progress in this step.".to_string(),
+        };
+        (LifecycleStatus::Stuck, Some(reason))
+    } else {
+        (LifecycleStatus::Ok, step_reason)
+    };
+
+    LifecycleRow {
+        index: index.index.clone(),
+        status,
+        policy: index.policy.clone(),
+        phase: index.phase.clone(),
+        action: index.action.clone(),
+        step: index.step.clone(),
+        age: index.age.clone(),
+        hours_in_step: time_in_step.map(|t| t / 3_600_000),
+        reason,
+    }
+}
+
+/// Parse a time value, e.g. `30d` or `12h`, to milliseconds
+fn parse_time_value(value: &str) -> Option<u64> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = value.split_at(split);
+    let millis = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(millis)
+}
+
+/// Parse a byte size value, e.g. `50gb`, to bytes
+fn parse_byte_size(value: &str) -> Option<u64> {
+    let value = value.to_ascii_lowercase();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (number, unit) = value.split_at(split);
+    let bytes = match unit {
+        "b" => 1u64,
+        "kb" => 1 << 10,
+        "mb" => 1 << 20,
+        "gb" => 1 << 30,
+        "tb" => 1 << 40,
+        "pb" => 1 << 50,
+        _ => return None,
+    };
+    Some((number.parse::<f64>().ok()? * bytes as f64) as u64)
+}
+
+/// Size and document counts of an index, that rollover conditions apply to
+struct IndexUsage {
+    primary_shards: u64,
+    docs: u64,
+    primary_bytes: u64,
+}
+
+/// Conditions of a rollover action. Shard conditions are compared to the average primary shard,
+/// that is a lower bound of the largest one.
+#[derive(Deserialize)]
+struct RolloverConditions {
+    max_age: Option<String>,
+    max_docs: Option<u64>,
+    max_size: Option<String>,
+    max_primary_shard_size: Option<String>,
+    max_primary_shard_docs: Option<u64>,
+    min_age: Option<String>,
+    min_docs: Option<u64>,
+    min_size: Option<String>,
+    min_primary_shard_size: Option<String>,
+    min_primary_shard_docs: Option<u64>,
+}
+
+impl RolloverConditions {
+    /// The max conditions exceeded by an index (`max_age` by at least `grace_ms`), if all its min
+    /// conditions are known to be met. Conditions that can't be evaluated are not exceeded.
+    fn exceeded(&self, age: Option<u64>, usage: Option<&IndexUsage>, grace_ms: u64) -> Vec<String> {
+        let docs = usage.map(|u| u.docs);
+        let bytes = usage.map(|u| u.primary_bytes);
+        let shard_docs = usage.map(|u| u.docs / u.primary_shards);
+        let shard_bytes = usage.map(|u| u.primary_bytes / u.primary_shards);
+        let time = |v: &Option<String>| v.as_deref().and_then(parse_time_value);
+        let size = |v: &Option<String>| v.as_deref().and_then(parse_byte_size);
+
+        let min_conditions = [
+            (time(&self.min_age), age),
+            (self.min_docs, docs),
+            (size(&self.min_size), bytes),
+            (size(&self.min_primary_shard_size), shard_bytes),
+            (self.min_primary_shard_docs, shard_docs),
+        ];
+        let min_met = min_conditions
+            .into_iter()
+            .all(|(min, actual)| min.is_none_or(|min| actual.is_some_and(|actual| actual >= min)));
+        if !min_met {
+            return Vec::new();
+        }
+
+        let mut exceeded = Vec::new();
+        if let (Some(max_age), Some(age)) = (time(&self.max_age), age)
+            && age >= max_age.saturating_add(grace_ms)
+        {
+            exceeded.push(format!(
+                "max_age {} (age {}h)",
+                self.max_age.as_deref().unwrap_or_default(),
+                age / 3_600_000
+            ));
+        }
+        let max_conditions = [
+            ("max_docs", self.max_docs, docs, self.max_docs.map(|v| v.to_string())),
+            ("max_size", size(&self.max_size), bytes, self.max_size.clone()),
+            (
+                "max_primary_shard_size",
+                size(&self.max_primary_shard_size),
+                shard_bytes,
+                self.max_primary_shard_size.clone(),
+            ),
+            (
+                "max_primary_shard_docs",
+                self.max_primary_shard_docs,
+                shard_docs,
+                self.max_primary_shard_docs.map(|v| v.to_string()),
+            ),
+        ];
+        for (name, max, actual, text) in max_conditions {
+            if let (Some(max), Some(actual)) = (max, actual)
+                && actual >= max
+            {
+                exceeded.push(format!("{name} {} ({actual})", text.unwrap_or_default()));
+            }
+        }
+        exceeded
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct IlmExplainResponse {
+    #[serde(default)]
+    indices: BTreeMap<String, IndexLifecycle>,
+}
+
+#[derive(Deserialize)]
+struct IndexLifecycle {
+    index: String,
+    managed: bool,
+    policy: Option<String>,
+    age: Option<String>,
+    phase: Option<String>,
+    action: Option<String>,
+    step: Option<String>,
+    step_time_millis: Option<u64>,
+    lifecycle_date_millis: Option<u64>,
+    failed_step: Option<String>,
+    is_auto_retryable_error: Option<bool>,
+    failed_step_retry_count: Option<u64>,
+    step_info: Option<Value>,
+    /// Definition of the current phase, as cached when the index entered it
+    phase_execution: Option<Value>,
+}
+
+impl IndexLifecycle {
+    fn rollover_conditions(&self) -> Option<RolloverConditions> {
+        let rollover = self.phase_execution.as_ref()?["phase_definition"]["actions"]["rollover"].clone();
+        serde_json::from_value(rollover).ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct CatIndexUsage {
+    index: String,
+    pri: Option<String>,
+    #[serde(rename = "docs.count")]
+    docs_count: Option<String>,
+    #[serde(rename = "pri.store.size")]
+    pri_store_size: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IlmPolicyEntry {
+    version: Option<u64>,
+    modified_date: Option<String>,
+    policy: Value,
+    in_use_by: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(step: &str, hours_in_step: u64) -> IndexLifecycle {
+        IndexLifecycle {
+            index: "logs-000001".to_string(),
+            managed: true,
+            policy: Some("logs".to_string()),
+            age: Some("3d".to_string()),
+            phase: Some("hot".to_string()),
+            action: Some("rollover".to_string()),
+            step: Some(step.to_string()),
+            step_time_millis: Some(100 * 3_600_000 - hours_in_step * 3_600_000),
+            lifecycle_date_millis: Some(100 * 3_600_000 - 72 * 3_600_000),
+            failed_step: None,
+            is_auto_retryable_error: None,
+            failed_step_retry_count: None,
+            step_info: None,
+            phase_execution: None,
+        }
+    }
+
+    fn awaiting_rollover(conditions: Value) -> IndexLifecycle {
+        let mut index = index(ROLLOVER_STEP, 48);
+        index.phase_execution = Some(json!({ "phase_definition": { "actions": { "rollover": conditions } } }));
+        index
+    }
+
+    #[test]
+    fn lifecycle_status() {
+        let now = 100 * 3_600_000;
+        let stuck_after = 24 * 3_600_000;
+
+        let row = lifecycle_row(&index("wait-for-active-shards", 2), now, stuck_after, None);
+        assert_eq!(row.status, LifecycleStatus::Ok);
+        assert_eq!(row.hours_in_step, Some(2));
+
+        let row = lifecycle_row(&index("wait-for-active-shards", 48), now, stuck_after, None);
+        assert_eq!(row.status, LifecycleStatus::Stuck);
+        assert!(row.reason.unwrap().contains("unassigned shards"));
+
+        assert_eq!(lifecycle_row(&index("complete", 480), now, stuck_after, None).status, LifecycleStatus::Ok);
+
+        let mut failed = index("ERROR", 1);
+        failed.failed_step = Some("check-rollover-ready".to_string());
+        failed.step_info = Some(json!({
+            "type": "illegal_argument_exception",
+            "reason": "index.lifecycle.rollover_alias [logs] does not point to index [logs-000001]"
+        }));
+        let row = lifecycle_row(&failed, now, stuck_after, None);
+        assert_eq!(row.status, LifecycleStatus::Error);
+        assert_eq!(
+            row.reason.unwrap(),
+            "Failed step 'check-rollover-ready': index.lifecycle.rollover_alias [logs] does not point to index [logs-000001]"
+        );
+    }
+
+    #[test]
+    fn rollover_stuck_only_when_conditions_exceeded() {
+        let now = 100 * 3_600_000;
+        let stuck_after = 24 * 3_600_000;
+        let usage = IndexUsage {
+            primary_shards: 2,
+            docs: 1_000_000,
+            primary_bytes: 120 << 30,
+        };
+
+        // A healthy write index waiting for a 30 day rollover
+        let row = lifecycle_row(&awaiting_rollover(json!({ "max_age": "30d" })), now, stuck_after, Some(&usage));
+        assert_eq!(row.status, LifecycleStatus::Ok);
+        // No policy information: not reported
+        assert_eq!(lifecycle_row(&index(ROLLOVER_STEP, 48), now, stuck_after, None).status, LifecycleStatus::Ok);
+        // 3 days old with max_age 1d, exceeded for longer than stuck_after
+        let row = lifecycle_row(&awaiting_rollover(json!({ "max_age": "1d" })), now, stuck_after, Some(&usage));
+        assert_eq!(row.status, LifecycleStatus::Stuck);
+        assert!(row.reason.unwrap().contains("max_age 1d (age 72h)"));
+        // Exceeded by less than stuck_after
+        let row = lifecycle_row(&awaiting_rollover(json!({ "max_age": "60h" })), now, stuck_after, Some(&usage));
+        assert_eq!(row.status, LifecycleStatus::Ok);
+
+        let conditions = json!({ "max_primary_shard_size": "50gb" });
+        let row = lifecycle_row(&awaiting_rollover(conditions), now, stuck_after, Some(&usage));
+        assert_eq!(row.status, LifecycleStatus::Stuck);
+        assert!(row.reason.unwrap().contains("max_primary_shard_size 50gb"));
+
+        // Min conditions not met
+        let conditions = json!({ "max_primary_shard_size": "50gb", "min_docs": 2_000_000 });
+        let row = lifecycle_row(&awaiting_rollover(conditions), now, stuck_after, Some(&usage));
+        assert_eq!(row.status, LifecycleStatus::Ok);
+    }
+
+    #[test]
+    fn time_and_size_values() {
+        assert_eq!(parse_time_value("30d"), Some(30 * 86_400_000));
+        assert_eq!(parse_time_value("500ms"), Some(500));
+        assert_eq!(parse_time_value("7w"), None);
+        assert_eq!(parse_byte_size("50gb"), Some(50 << 30));
+        assert_eq!(parse_byte_size("1.5KB"), Some(1536));
+        assert_eq!(parse_byte_size("10"), None);
+    }
+}
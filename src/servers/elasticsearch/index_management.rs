@@ -0,0 +1,625 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Index lifecycle write tools: create, delete, close and open indices, update their settings and
+//! roll over aliases and data streams.
+//!
+//! All of them follow the two-step confirmation protocol and refuse to modify system indices.
+//! Deletions are restricted by the `allow_delete_patterns` and `deny_delete_patterns`
+//! configuration, other changes by `allow_write_patterns` and `deny_write_patterns`.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_index_list, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::{internal_error, read_json};
+use crate::servers::elasticsearch::safety::{check_confirmation_token, check_not_system_index, confirmation_token};
+use crate::servers::elasticsearch::template_changes::{json_diff, normalize_settings};
+use crate::servers::elasticsearch::templates::merge_json;
+use elasticsearch::Elasticsearch;
+use elasticsearch::cat::CatIndicesParts;
+use elasticsearch::http::StatusCode;
+use elasticsearch::indices::{
+    IndicesCloseParts, IndicesCreateParts, IndicesDeleteParts, IndicesExistsParts, IndicesGetSettingsParts,
+    IndicesOpenParts, IndicesPutSettingsParts, IndicesRolloverParts,
+};
+use elasticsearch::params::ExpandWildcards;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+/// Maximum length of the comma-separated index names sent in a request path, well below the
+/// default 4KB `http.max_initial_line_length`. Longer lists are sent in several requests.
+const MAX_PATH_NAMES_CHARS: usize = 3000;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CreateIndexParams {
+    /// Name of the index to create
+    index: String,
+    /// Optional index settings (e.g. number_of_shards, number_of_replicas)
+    settings: Option<Map<String, Value>>,
+    /// Optional mappings, with 'properties'
+    mappings: Option<Map<String, Value>>,
+    /// Optional aliases, as accepted by the create index API
+    aliases: Option<Map<String, Value>>,
+    /// Token returned by a first call without it, after reviewing the index to create
+    confirmation_token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct IndexTargetParams {
+    /// Index name, or comma-separated names and patterns (supports * wildcard)
+    index: String,
+    /// Token returned by a first call without it, after reviewing the target indices
+    confirmation_token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct UpdateIndexSettingsParams {
+    /// Index name, or comma-separated names and patterns (supports * wildcard)
+    index: String,
+    /// Dynamic settings to update (e.g. {"number_of_replicas": 1, "refresh_interval": "30s"}). A null value resets a setting to its default.
+    settings: Map<String, Value>,
+    /// Token returned by a first call without it, after reviewing the changes
+    confirmation_token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct RolloverParams {
+    /// Alias or data stream to roll over
+    target: String,
+    /// Optional conditions, e.g. {"max_age": "7d", "max_primary_shard_size": "50gb"}. Rolls over unconditionally if not provided.
+    conditions: Option<Map<String, Value>>,
+    /// Optional name of the new index (aliases only, generated if not provided)
+    new_index: Option<String>,
+    /// Token returned by a first call without it, after reviewing the result of the dry run
+    confirmation_token: Option<String>,
+}
+
+/// Operation on existing indices, that share the same preview and confirmation steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexOperation {
+    Delete,
+    Close,
+    Open,
+}
+
+impl IndexOperation {
+    fn tool(&self) -> &'static str {
+        match self {
+            IndexOperation::Delete => "delete_index",
+            IndexOperation::Close => "close_index",
+            IndexOperation::Open => "open_index",
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            IndexOperation::Delete => "deleted",
+            IndexOperation::Close => "closed",
+            IndexOperation::Open => "opened",
+        }
+    }
+}
+
+#[tool_router(router = index_management_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: create an index
+    #[tool(
+        description = "Create an index with optional settings, mappings and aliases. A first call without confirmation_token checks the name and returns a token: review it with the user, then call again with the token.",
+        annotations(title = "Create index", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn create_index(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<CreateIndexParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        check_index_name(&params.index)?;
+        self.write_policy.check(&params.index)?;
+        let response = es_client
+            .indices()
+            .exists(IndicesExistsParts::Index(&[&params.index]))
+            .send()
+            .await;
+        let exists = match response {
+            Ok(response) => response.status_code() != StatusCode::NOT_FOUND,
+            Err(err) => return Err(internal_error(err)),
+        };
+        if exists {
+            return Err(rmcp::Error::invalid_params(
+                format!("Index, alias or data stream {} already exists.", params.index),
+                None,
+            ));
+        }
+
+        let mut body = json!({});
+        for (key, value) in [("settings", params.settings), ("mappings", params.mappings), ("aliases", params.aliases)] {
+            if let Some(value) = value {
+                body[key] = Value::Object(value);
+            }
+        }
+        let token = confirmation_token(&["create_index", &params.index, &body.to_string()]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "Index {} will be created, with settings from matching index templates completed by the ones provided. Review it with the user, then call create_index again with the confirmation_token.",
+                params.index
+            );
+            let data = pack_json_value(&body, max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "create_index without confirmation_token")?;
+
+        let response = es_client
+            .indices()
+            .create(IndicesCreateParts::Index(&params.index))
+            .body(body)
+            .send()
+            .await;
+        let _: Value = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Index {} created.", params.index) }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: delete indices
+    #[tool(
+        description = "Delete indices. System indices can't be deleted, and the server configuration can restrict which indices may be. A first call without confirmation_token lists the indices that would be deleted with their size and returns a token: review them with the user, then call again with the token.",
+        annotations(title = "Delete index", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn delete_index(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<IndexTargetParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        self.index_operation(&es_client, IndexOperation::Delete, params).await
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: close indices
+    #[tool(
+        description = "Close indices: they are no longer searchable nor writable, but keep their data on disk. System indices can't be closed, and the server configuration can restrict which indices may be. A first call without confirmation_token lists the indices that would be closed and returns a token: review them with the user, then call again with the token.",
+        annotations(title = "Close index", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn close_index(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<IndexTargetParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        self.index_operation(&es_client, IndexOperation::Close, params).await
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: open indices
+    #[tool(
+        description = "Open closed indices. A first call without confirmation_token lists the indices that would be opened and returns a token: review them with the user, then call again with the token.",
+        annotations(title = "Open index", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn open_index(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<IndexTargetParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+        self.index_operation(&es_client, IndexOperation::Open, params).await
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: update index settings
+    #[tool(
+        description = "Update dynamic settings of indices (e.g. number_of_replicas, refresh_interval). A first call without confirmation_token shows the current and new value of each setting and returns a token: review them with the user, then call again with the token.",
+        annotations(title = "Update index settings", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn update_index_settings(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<UpdateIndexSettingsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let indices = fetch_target_indices(&es_client, &params.index).await?;
+        let names = indices.iter().map(|i| i.index.as_str()).collect::<Vec<_>>();
+        for name in &names {
+            self.write_policy.check(name)?;
+        }
+
+        let mut current = BTreeMap::<String, IndexSettings>::new();
+        for batch in name_batches(&names) {
+            let response = es_client
+                .indices()
+                .get_settings(IndicesGetSettingsParts::Index(&batch))
+                .send()
+                .await;
+            let settings: BTreeMap<String, IndexSettings> = read_json(response).await?;
+            current.extend(settings);
+        }
+
+        let proposed = normalize_settings(&Value::Object(params.settings.clone()));
+        let changes = current
+            .iter()
+            .map(|(index, settings)| (index, settings_changes(&settings.settings, &proposed)))
+            .filter(|(_, changes)| !changes.is_empty())
+            .collect::<BTreeMap<_, _>>();
+        if changes.is_empty() {
+            return Err(rmcp::Error::invalid_params(
+                "The settings already have the requested values, there is nothing to update.",
+                None,
+            ));
+        }
+
+        let token = confirmation_token(&[
+            "update_index_settings",
+            &names.join(","),
+            &proposed.to_string(),
+            &json!(changes).to_string(),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "Settings of {} index(es) will be updated. Review the changes with the user, then call update_index_settings again with the confirmation_token.",
+                changes.len()
+            );
+            let data = pack_json_value(&changes, max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "update_index_settings without confirmation_token")?;
+
+        let targets = changes.keys().map(|i| i.as_str()).collect::<Vec<_>>();
+        let mut updated = 0;
+        for batch in name_batches(&targets) {
+            let response = es_client
+                .indices()
+                .put_settings(IndicesPutSettingsParts::Index(&batch))
+                .body(proposed.clone())
+                .send()
+                .await;
+            read_json::<Value>(response)
+                .await
+                .map_err(|e| partial_failure(e, updated, "updated"))?;
+            updated += batch.len();
+        }
+
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Settings of {} index(es) updated.", targets.len()) }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: roll over an alias or data stream
+    #[tool(
+        description = "Roll over an alias or data stream to a new write index, optionally only if conditions are met. A first call without confirmation_token performs a dry run showing the old and new index and the conditions, and returns a token if a rollover would happen: review it with the user, then call again with the token.",
+        annotations(title = "Rollover", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn rollover(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<RolloverParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        self.write_policy.check(&params.target)?;
+        if let Some(new_index) = &params.new_index {
+            check_index_name(new_index)?;
+        }
+        let body = json!({ "conditions": params.conditions.unwrap_or_default() });
+
+        let dry_run = send_rollover(&es_client, &params.target, params.new_index.as_deref(), &body, true).await?;
+        // The new index of a data stream is a backing index, allowed with the data stream
+        if !dry_run.new_index.starts_with(".ds-") {
+            self.write_policy.check(&dry_run.new_index)?;
+        }
+        let token = confirmation_token(&[
+            "rollover",
+            &params.target,
+            &dry_run.old_index,
+            &dry_run.new_index,
+            &body.to_string(),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let data = pack_json_value(&dry_run, max_response_chars())?;
+            let conditions_met = dry_run.conditions.is_empty() || dry_run.conditions.values().any(|met| *met);
+            if !conditions_met {
+                let message = format!(
+                    "No rollover condition is met, {} would not be rolled over from {}.",
+                    params.target, dry_run.old_index
+                );
+                return Ok(CallToolResult::success(vec![
+                    Content::json(json!({ "message": message, "data": data }))?
+                ]));
+            }
+            let message = format!(
+                "{} will be rolled over from {} to {}. Review it with the user, then call rollover again with the confirmation_token.",
+                params.target, dry_run.old_index, dry_run.new_index
+            );
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "rollover without confirmation_token")?;
+
+        let result = send_rollover(&es_client, &params.target, params.new_index.as_deref(), &body, false).await?;
+        let message = if result.rolled_over {
+            format!("{} rolled over from {} to {}.", params.target, result.old_index, result.new_index)
+        } else {
+            format!("{} was not rolled over, as no condition is met.", params.target)
+        };
+        let data = pack_json_value(&result, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+impl EsBaseTools {
+    /// Delete, close or open indices, after a preview and confirmation step
+    async fn index_operation(
+        &self,
+        es_client: &Elasticsearch,
+        operation: IndexOperation,
+        params: IndexTargetParams,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let mut indices = fetch_target_indices(es_client, &params.index).await?;
+        for index in &indices {
+            match operation {
+                IndexOperation::Delete => self.delete_policy.check(&index.index)?,
+                IndexOperation::Close | IndexOperation::Open => self.write_policy.check(&index.index)?,
+            }
+        }
+        // Indices already in the requested state are left aside
+        match operation {
+            IndexOperation::Close => indices.retain(|i| i.status != "close"),
+            IndexOperation::Open => indices.retain(|i| i.status == "close"),
+            IndexOperation::Delete => {}
+        }
+        if indices.is_empty() {
+            return Err(rmcp::Error::invalid_params(
+                format!("All indices matching {} are already {}.", params.index, operation.verb()),
+                None,
+            ));
+        }
+
+        let names = indices.iter().map(|i| i.index.as_str()).collect::<Vec<_>>();
+        // Document counts are shown but not part of the token, as they change on indices being written to
+        let state = indices
+            .iter()
+            .map(|i| format!("{}:{}", i.index, i.status))
+            .collect::<Vec<_>>();
+        let token = confirmation_token(&[operation.tool(), &state.join(",")]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "{} index(es) will be {}. Review them with the user, then call {} again with the confirmation_token.",
+                indices.len(),
+                operation.verb(),
+                operation.tool()
+            );
+            let mut listed = indices;
+            listed.truncate(max_index_list());
+            let data = pack_json_value(&listed, max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, &format!("{} without confirmation_token", operation.tool()))?;
+
+        // Concrete names are used, so that wildcards are never sent with destructive operations
+        let indices_api = es_client.indices();
+        let mut done = 0;
+        for batch in name_batches(&names) {
+            let response = match operation {
+                IndexOperation::Delete => indices_api.delete(IndicesDeleteParts::Index(&batch)).send().await,
+                IndexOperation::Close => indices_api.close(IndicesCloseParts::Index(&batch)).send().await,
+                IndexOperation::Open => indices_api.open(IndicesOpenParts::Index(&batch)).send().await,
+            };
+            read_json::<Value>(response)
+                .await
+                .map_err(|e| partial_failure(e, done, operation.verb()))?;
+            done += batch.len();
+        }
+
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({
+                "message": format!("{} index(es) {}.", names.len(), operation.verb()),
+                "data": names,
+            }))?
+        ]))
+    }
+}
+
+/// Indices matching an expression, open or closed, sorted by name
+async fn fetch_target_indices(es_client: &Elasticsearch, expression: &str) -> Result<Vec<TargetIndex>, rmcp::Error> {
+    let patterns = expression.split(',').map(|p| p.trim()).collect::<Vec<_>>();
+    let response = es_client
+        .cat()
+        .indices(CatIndicesParts::Index(&patterns))
+        .expand_wildcards(&[ExpandWildcards::Open, ExpandWildcards::Closed])
+        .h(&["index", "status", "health", "docs.count", "store.size", "creation.date.string"])
+        .s(&["index"])
+        .format("json")
+        .send()
+        .await;
+    let indices: Vec<TargetIndex> = read_json(response).await?;
+    if indices.is_empty() {
+        return Err(rmcp::Error::invalid_params(format!("No index matches {expression}."), None));
+    }
+    Ok(indices)
+}
+
+/// Split index names in batches whose comma-separated list fits in a request path
+fn name_batches<'a>(names: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut batches: Vec<Vec<&str>> = Vec::new();
+    let mut length = 0;
+    for name in names {
+        match batches.last_mut() {
+            Some(batch) if length + 1 + name.len() <= MAX_PATH_NAMES_CHARS => {
+                batch.push(name);
+                length += 1 + name.len();
+            }
+            _ => {
+                batches.push(vec![name]);
+                length = name.len();
+            }
+        }
+    }
+    batches
+}
+
+/// Error of a batched operation, telling how many indices were already processed
+fn partial_failure(error: rmcp::Error, done: usize, verb: &str) -> rmcp::Error {
+    if done == 0 {
+        return error;
+    }
+    rmcp::Error::new(
+        error.code,
+        format!("{done} index(es) were {verb} before an error occurred: {}", error.message),
+        error.data,
+    )
+}
+
+/// Check that a new index name is valid and isn't a system index name
+fn check_index_name(index: &str) -> Result<(), rmcp::Error> {
+    check_not_system_index(index)?;
+    let invalid = index.is_empty()
+        || index.starts_with(['-', '_', '+'])
+        || index.chars().any(|c| c.is_uppercase() || r#"\/*?"<>| ,#:"#.contains(c))
+        || index == "."
+        || index == "..";
+    if invalid {
+        return Err(rmcp::Error::invalid_params(
+            format!(
+                "Invalid index name '{index}': names must be lowercase, can't start with '-', '_' or '+', and can't contain spaces nor \\ / * ? \" < > | , # :"
+            ),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Current and new values of settings, as `{path, current, proposed}` objects
+fn settings_changes(current: &Value, proposed: &Value) -> Vec<Value> {
+    let mut updated = current.clone();
+    merge_json(&mut updated, proposed);
+    let mut changes = Vec::new();
+    json_diff(current, &updated, "", &mut changes);
+    changes
+}
+
+async fn send_rollover(
+    es_client: &Elasticsearch,
+    target: &str,
+    new_index: Option<&str>,
+    body: &Value,
+    dry_run: bool,
+) -> Result<RolloverResponse, rmcp::Error> {
+    let parts = match new_index {
+        Some(new_index) => IndicesRolloverParts::AliasNewIndex(target, new_index),
+        None => IndicesRolloverParts::Alias(target),
+    };
+    let response = es_client
+        .indices()
+        .rollover(parts)
+        .dry_run(dry_run)
+        .body(body.clone())
+        .send()
+        .await;
+    read_json(response).await
+}
+
+//----- Responses
+
+#[derive(Serialize, Deserialize)]
+struct TargetIndex {
+    index: String,
+    status: String,
+    health: Option<String>,
+    #[serde(rename = "docs.count")]
+    docs_count: Option<String>,
+    #[serde(rename = "store.size")]
+    store_size: Option<String>,
+    #[serde(rename = "creation.date.string")]
+    creation_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IndexSettings {
+    settings: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RolloverResponse {
+    old_index: String,
+    new_index: String,
+    #[serde(default)]
+    rolled_over: bool,
+    #[serde(default)]
+    dry_run: bool,
+    /// Conditions, e.g. `[max_age: 7d]`, and whether they are met
+    #[serde(default)]
+    conditions: BTreeMap<String, bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_names() {
+        assert!(check_index_name("logs-2024.01.01").is_ok());
+        assert!(check_index_name(".security").is_err());
+        assert!(check_index_name("_logs").is_err());
+        assert!(check_index_name("Logs").is_err());
+        assert!(check_index_name("logs 2024").is_err());
+        assert!(check_index_name("logs*").is_err());
+    }
+
+    #[test]
+    fn batches_of_names() {
+        let long_names = (0..100).map(|i| format!("logs-{i:04}-{}", "x".repeat(80))).collect::<Vec<_>>();
+        let names = long_names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+        let batches = name_batches(&names);
+
+        assert!(batches.len() > 1);
+        assert!(batches.iter().all(|b| b.join(",").len() <= MAX_PATH_NAMES_CHARS));
+        assert_eq!(batches.concat(), names);
+        assert_eq!(name_batches(&["a", "b"]), vec![vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn changed_settings() {
+        let current = json!({ "index": { "number_of_replicas": "1", "refresh_interval": "1s", "number_of_shards": "3" } });
+        let proposed = normalize_settings(&json!({ "number_of_replicas": 2, "index.refresh_interval": "1s" }));
+
+        assert_eq!(
+            settings_changes(&current, &proposed),
+            vec![json!({ "path": "index.number_of_replicas", "current": "1", "proposed": "2" })]
+        );
+    }
+}
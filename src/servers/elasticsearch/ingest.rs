@@ -0,0 +1,401 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Ingest pipelines: listing, definitions, and simulation on sample documents with the changes
+//! made by each processor.
+
+use crate::servers::elasticsearch::base_tools::{
+    EsBaseTools, SearchResult, check_query, max_index_list, max_response_chars, pack_json_value, prepare_search_body,
+};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::template_changes::json_diff;
+use elasticsearch::http::StatusCode;
+use elasticsearch::ingest::{IngestGetPipelineParts, IngestSimulateParts};
+use elasticsearch::{Elasticsearch, SearchParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+/// Number of documents sampled from an index by default
+const DEFAULT_SAMPLE_SIZE: u64 = 3;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListIngestPipelinesParams {
+    /// Pipeline ID pattern (supports * wildcard, default: *)
+    id_pattern: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetIngestPipelineParams {
+    /// Pipeline ID
+    id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct SimulatePipelineParams {
+    /// ID of an existing pipeline to simulate. Either pipeline_id or pipeline must be provided.
+    pipeline_id: Option<String>,
+    /// Inline pipeline definition to simulate, with 'processors' and optional 'on_failure'
+    pipeline: Option<Map<String, Value>>,
+    /// Sample documents (their source)
+    docs: Option<Vec<Map<String, Value>>>,
+    /// Index to sample documents from, if docs aren't provided
+    sample_index: Option<String>,
+    /// Query DSL 'query' to select sampled documents (default: match_all)
+    sample_query: Option<Map<String, Value>>,
+    /// Number of documents to sample (default: 3)
+    sample_size: Option<u64>,
+}
+
+#[tool_router(router = ingest_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list ingest pipelines
+    #[tool(
+        description = "List ingest pipelines with their description and the types of their processors.",
+        annotations(title = "List ingest pipelines", read_only_hint = true)
+    )]
+    async fn list_ingest_pipelines(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ListIngestPipelinesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let pattern = params.id_pattern.as_deref().unwrap_or("*");
+        let pipelines = fetch_pipelines(&es_client, pattern).await?;
+
+        let mut summaries = pipelines
+            .iter()
+            .map(|(id, pipeline)| {
+                json!({
+                    "id": id,
+                    "description": pipeline.description,
+                    "version": pipeline.version,
+                    "processors": pipeline.processors.iter().map(processor_name).collect::<Vec<_>>(),
+                    "on_failure": !pipeline.on_failure.is_empty(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let total = summaries.len();
+        let max_list = max_index_list();
+        let message = if total > max_list {
+            summaries.truncate(max_list);
+            format!("Found {total} ingest pipelines (showing first {max_list}, use id_pattern to filter).")
+        } else {
+            format!("Found {total} ingest pipelines.")
+        };
+
+        let data = pack_json_value(&summaries, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get an ingest pipeline
+    #[tool(
+        description = "Get the definition of an ingest pipeline, with its processors.",
+        annotations(title = "Get ingest pipeline", read_only_hint = true)
+    )]
+    async fn get_ingest_pipeline(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetIngestPipelineParams { id }): Parameters<GetIngestPipelineParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .ingest()
+            .get_pipeline(IngestGetPipelineParts::Id(&id))
+            .send()
+            .await;
+        let mut pipelines: BTreeMap<String, Value> = read_json(response).await?;
+        let pipeline = pipelines
+            .remove(&id)
+            .ok_or_else(|| rmcp::Error::invalid_params(format!("Ingest pipeline {id} not found."), None))?;
+
+        let data = pack_json_value(&pipeline, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Ingest pipeline {id}."), "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: simulate an ingest pipeline
+    #[tool(
+        description = "Simulate an ingest pipeline (existing or inline) on sample documents, provided or sampled from an index. Returns, for each document and processor, its status, the fields it set or removed, and its error.",
+        annotations(title = "Simulate ingest pipeline", read_only_hint = true)
+    )]
+    async fn simulate_pipeline(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<SimulatePipelineParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let docs = match (params.docs, &params.sample_index) {
+            (Some(docs), _) => docs.into_iter().map(Value::Object).collect::<Vec<_>>(),
+            (None, Some(index)) => {
+                let size = params.sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE);
+                sample_documents(&es_client, index, params.sample_query, size).await?
+            }
+            (None, None) => {
+                return Err(rmcp::Error::invalid_params("Either docs or sample_index must be provided.", None));
+            }
+        };
+        if docs.is_empty() {
+            return Err(rmcp::Error::invalid_params("No sample document to simulate the pipeline on.", None));
+        }
+
+        let mut body = json!({ "docs": docs.iter().map(|doc| json!({ "_source": doc })).collect::<Vec<_>>() });
+        let response = match (&params.pipeline_id, params.pipeline) {
+            (None, Some(pipeline)) => {
+                body["pipeline"] = Value::Object(pipeline);
+                es_client.ingest().simulate(IngestSimulateParts::None)
+            }
+            (Some(id), None) => es_client.ingest().simulate(IngestSimulateParts::Id(id)),
+            _ => {
+                return Err(rmcp::Error::invalid_params(
+                    "Exactly one of pipeline_id or pipeline must be provided.",
+                    None,
+                ));
+            }
+        }
+        .verbose(true)
+        .body(body)
+        .send()
+        .await;
+        let response: SimulateResponse = read_json(response).await?;
+
+        let results = docs
+            .iter()
+            .zip(&response.docs)
+            .map(|(doc, result)| simulation_steps(doc, result))
+            .collect::<Vec<_>>();
+        let failed = results
+            .iter()
+            .filter(|steps| steps.iter().any(|s| s["status"] == "error"))
+            .count();
+        let message = format!(
+            "Simulated the pipeline on {} document(s), {failed} failed.",
+            results.len()
+        );
+
+        let data = pack_json_value(&results, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+async fn fetch_pipelines(es_client: &Elasticsearch, pattern: &str) -> Result<BTreeMap<String, Pipeline>, rmcp::Error> {
+    let response = es_client
+        .ingest()
+        .get_pipeline(IngestGetPipelineParts::Id(pattern))
+        .send()
+        .await;
+    // No pipeline matching is a 404 with an empty object
+    if let Ok(response) = &response
+        && response.status_code() == StatusCode::NOT_FOUND
+    {
+        return Ok(BTreeMap::new());
+    }
+    read_json(response).await
+}
+
+/// Sample the source of documents from an index
+async fn sample_documents(
+    es_client: &Elasticsearch,
+    index: &str,
+    query: Option<Map<String, Value>>,
+    size: u64,
+) -> Result<Vec<Value>, rmcp::Error> {
+    let query = query.map(Value::Object).unwrap_or_else(|| json!({ "match_all": {} }));
+    check_query(&query)?;
+    let mut body = Map::from_iter([("size".to_string(), json!(size)), ("query".to_string(), query)]);
+    prepare_search_body(&mut body, None);
+
+    let response = es_client
+        .search(SearchParts::Index(&[index]))
+        .body(body)
+        .send()
+        .await;
+    let response: SearchResult = read_json(response).await?;
+    Ok(response.hits.hits.into_iter().map(|hit| hit.source).collect())
+}
+
+/// Processor type, and its tag if any (e.g. `grok:parse_message`)
+fn processor_name(processor: &Value) -> String {
+    let Some((processor_type, config)) = processor.as_object().and_then(|p| p.iter().next()) else {
+        return "unknown".to_string();
+    };
+    match config["tag"].as_str() {
+        Some(tag) => format!("{processor_type}:{tag}"),
+        None => processor_type.clone(),
+    }
+}
+
+/// Status of each processor on a document, with the fields it changed compared to the previous processor
+fn simulation_steps(doc: &Value, result: &SimulatedDocument) -> Vec<Value> {
+    // Pipeline-level error, e.g. unknown pipeline or invalid processor
+    if let Some(error) = &result.error {
+        return vec![json!({ "processor": "pipeline", "status": "error", "error": error_reason(error) })];
+    }
+
+    let mut previous = doc.clone();
+    let mut steps = Vec::new();
+    for processor in &result.processor_results {
+        let name = match &processor.tag {
+            Some(tag) => format!("{}:{tag}", processor.processor_type),
+            None => processor.processor_type.clone(),
+        };
+        let mut step = json!({ "processor": name, "status": processor.status });
+
+        if let Some(source) = processor.doc.as_ref().and_then(|d| d.get("_source")) {
+            let mut diff = Vec::new();
+            json_diff(&previous, source, "", &mut diff);
+            let mut set = Map::new();
+            let mut removed = Vec::new();
+            for change in diff {
+                let path = change["path"].as_str().unwrap_or_default().to_string();
+                if change["removed"] == true {
+                    removed.push(path);
+                } else {
+                    set.insert(path, change["proposed"].clone());
+                }
+            }
+            if !set.is_empty() {
+                step["set"] = Value::Object(set);
+            }
+            if !removed.is_empty() {
+                step["removed"] = json!(removed);
+            }
+            previous = source.clone();
+        }
+        if let Some(error) = processor.error.as_ref().or(processor.ignored_error.as_ref()) {
+            step["error"] = json!(error_reason(error));
+        }
+        steps.push(step);
+    }
+    steps
+}
+
+fn error_reason(error: &Value) -> String {
+    // The root cause is more specific than the wrapping error
+    let error = error
+        .pointer("/root_cause/0")
+        .or_else(|| error.get("error"))
+        .unwrap_or(error);
+    match (error["type"].as_str(), error["reason"].as_str()) {
+        (Some(kind), Some(reason)) => format!("{kind}: {reason}"),
+        (None, Some(reason)) => reason.to_string(),
+        _ => error.to_string(),
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct Pipeline {
+    description: Option<String>,
+    version: Option<u64>,
+    #[serde(default)]
+    processors: Vec<Value>,
+    #[serde(default)]
+    on_failure: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct SimulateResponse {
+    #[serde(default)]
+    docs: Vec<SimulatedDocument>,
+}
+
+#[derive(Deserialize)]
+struct SimulatedDocument {
+    #[serde(default)]
+    processor_results: Vec<ProcessorResult>,
+    error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct ProcessorResult {
+    processor_type: String,
+    tag: Option<String>,
+    status: Option<String>,
+    doc: Option<Value>,
+    error: Option<Value>,
+    ignored_error: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processor_names() {
+        assert_eq!(processor_name(&json!({ "grok": { "field": "message", "tag": "parse" } })), "grok:parse");
+        assert_eq!(processor_name(&json!({ "date": { "field": "ts" } })), "date");
+    }
+
+    #[test]
+    fn simulation_diff() {
+        let doc = json!({ "message": "GET /index 200", "tmp": 1 });
+        let result: SimulatedDocument = serde_json::from_value(json!({
+            "processor_results": [
+                {
+                    "processor_type": "grok", "status": "success",
+                    "doc": { "_index": "_index", "_source": { "message": "GET /index 200", "tmp": 1, "http": { "method": "GET", "status": 200 } } }
+                },
+                {
+                    "processor_type": "remove", "tag": "cleanup", "status": "success",
+                    "doc": { "_index": "_index", "_source": { "message": "GET /index 200", "http": { "method": "GET", "status": 200 } } }
+                },
+                {
+                    "processor_type": "set", "status": "success",
+                    "doc": { "_index": "_index", "_source": { "message": "GET /index 200", "http": { "method": "GET", "status": null } } }
+                },
+                {
+                    "processor_type": "date", "status": "error",
+                    "error": {
+                        "root_cause": [{ "type": "illegal_argument_exception", "reason": "field [ts] not present as part of path [ts]" }],
+                        "type": "illegal_argument_exception", "reason": "field [ts] not present as part of path [ts]"
+                    }
+                }
+            ]
+        }))
+        .unwrap();
+
+        let steps = simulation_steps(&doc, &result);
+        assert_eq!(
+            steps,
+            vec![
+                json!({ "processor": "grok", "status": "success", "set": { "http": { "method": "GET", "status": 200 } } }),
+                json!({ "processor": "remove:cleanup", "status": "success", "removed": ["tmp"] }),
+                json!({ "processor": "set", "status": "success", "set": { "http.status": null } }),
+                json!({ "processor": "date", "status": "error", "error": "illegal_argument_exception: field [ts] not present as part of path [ts]" }),
+            ]
+        );
+    }
+}
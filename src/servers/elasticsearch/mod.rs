@@ -15,9 +15,31 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod aliases;
+mod analysis;
 mod base_tools;
+mod bulk;
+mod by_query;
+mod compat;
+mod data_streams;
+mod diagnostics;
+mod documents;
+mod fields;
+mod ilm;
+mod index_management;
+mod ingest;
+mod nodes;
+mod queries;
+mod reindex;
+mod safety;
+mod snapshots;
+mod tasks;
+mod template_changes;
+mod templates;
+mod vitality;
 
 use crate::servers::IncludeExclude;
+use crate::servers::elasticsearch::compat::OpenSearchQueryLanguage;
 use crate::utils::none_if_empty_string;
 use elasticsearch::Elasticsearch;
 use elasticsearch::auth::Credentials;
@@ -36,6 +58,7 @@ use serde::{Deserialize, Serialize};
 use serde_aux::field_attributes::deserialize_bool_from_anything;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -59,6 +82,10 @@ pub struct ElasticsearchMcpConfig {
     #[serde(default, deserialize_with = "deserialize_bool_from_anything")]
     pub ssl_skip_verify: bool,
 
+    /// Read-only mode: tools that modify the cluster are not exposed (default: true)
+    #[serde(default = "default_true", deserialize_with = "deserialize_bool_from_anything")]
+    pub read_only: bool,
+
     /// Search templates to expose as tools or resources
     #[serde(default)]
     pub tools: Tools,
@@ -66,6 +93,30 @@ pub struct ElasticsearchMcpConfig {
     /// Prompts
     #[serde(default)]
     pub prompts: Vec<String>,
+
+    /// Query language (`sql` or `ppl`) the `esql` tool uses when connected to OpenSearch,
+    /// which doesn't support ES|QL. The tool is hidden on OpenSearch if not set.
+    #[serde(default)]
+    pub opensearch_query_language: Option<OpenSearchQueryLanguage>,
+
+    /// Index patterns `delete_index` may delete (supports * wildcard). All indices but system
+    /// indices can be deleted if empty.
+    #[serde(default)]
+    pub allow_delete_patterns: Vec<String>,
+
+    /// Index patterns `delete_index` must never delete, even if they match `allow_delete_patterns`
+    #[serde(default)]
+    pub deny_delete_patterns: Vec<String>,
+
+    /// Index patterns whose documents the document write tools may create, update and delete, and
+    /// that index management tools may change or restore_snapshot may restore into (supports * wildcard).
+    /// All indices but system indices if empty.
+    #[serde(default)]
+    pub allow_write_patterns: Vec<String>,
+
+    /// Index patterns whose documents must never be modified, even if they match `allow_write_patterns`
+    #[serde(default)]
+    pub deny_write_patterns: Vec<String>,
     // TODO: search as resources?
 }
 
@@ -79,32 +130,58 @@ impl EsClientProvider {
         EsClientProvider(client)
     }
 
+    /// Key of the client used for the default client, i.e. requests without credentials.
+    pub const DEFAULT_CLIENT_KEY: u64 = 0;
+
     /// If the incoming request is a http request and has an `Authorization` header, use it
     /// to authenticate to the remote ES instance.
     pub fn get(&self, context: RequestContext<RoleServer>) -> Cow<'_, Elasticsearch> {
         let client = &self.0;
 
-        let Some(mut auth) = context
-            .extensions
-            .get::<Parts>()
-            .and_then(|p| p.headers.get(header::AUTHORIZATION))
-            .and_then(|h| h.to_str().ok())
-        else {
+        let Some(auth) = request_authorization(&context) else {
             // No auth
             return Cow::Borrowed(client);
         };
 
-        // MCP inspector insists on sending a bearer token and prepends "Bearer" to the value provided
-        if auth.starts_with("Bearer ApiKey ") || auth.starts_with("Bearer Basic ") {
-            auth = auth.trim_start_matches("Bearer ");
-        }
-
         let transport = client
             .transport()
             .clone_with_auth(Some(Credentials::AuthorizationHeader(auth.to_string())));
 
         Cow::Owned(Elasticsearch::new(transport))
     }
+
+    /// The client configured at startup, i.e. without per-request credentials.
+    pub fn default_client(&self) -> &Elasticsearch {
+        &self.0
+    }
+
+    /// Identifies the client `get` returns for a request, from a hash of its credentials.
+    pub fn key(&self, context: &RequestContext<RoleServer>) -> u64 {
+        match request_authorization(context) {
+            Some(auth) => {
+                let mut hasher = DefaultHasher::new();
+                auth.hash(&mut hasher);
+                hasher.finish().max(1)
+            }
+            None => Self::DEFAULT_CLIENT_KEY,
+        }
+    }
+}
+
+/// The `Authorization` header of the incoming http request, if any
+fn request_authorization(context: &RequestContext<RoleServer>) -> Option<&str> {
+    let auth = context
+        .extensions
+        .get::<Parts>()
+        .and_then(|p| p.headers.get(header::AUTHORIZATION))
+        .and_then(|h| h.to_str().ok())?;
+
+    // MCP inspector insists on sending a bearer token and prepends "Bearer" to the value provided
+    if auth.starts_with("Bearer ApiKey ") || auth.starts_with("Bearer Basic ") {
+        Some(auth.trim_start_matches("Bearer "))
+    } else {
+        Some(auth)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -212,13 +289,17 @@ impl ElasticsearchMcp {
         let transport = transport.build()?;
         let es_client = Elasticsearch::new(transport);
 
-        Ok(base_tools::EsBaseTools::new(es_client))
+        Ok(base_tools::EsBaseTools::new(es_client, &config))
     }
 }
 
 //------------------------------------------------------------------------------------------------
 // Utilities
 
+fn default_true() -> bool {
+    true
+}
+
 /// Rewrite urls targeting `localhost` to a hostname that maps to the container host, if possible.
 ///
 /// The host name for the container host depends on the OCI runtime used. This is useful to accept
@@ -276,7 +357,6 @@ pub async fn read_json<T: DeserializeOwned>(
     response.json().await.map_err(internal_error)
 }
 
-#[allow(dead_code)]
 pub async fn read_text(result: Result<Response, elasticsearch::Error>) -> Result<String, rmcp::Error> {
     let response = handle_error(result)?;
     response.text().await.map_err(internal_error)
@@ -0,0 +1,538 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Node-level diagnostics: JVM, thread pools, circuit breakers, disk, indexing pressure and hot threads.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::compat::Version;
+use crate::servers::elasticsearch::{read_json, read_text};
+use elasticsearch::cat::CatThreadPoolParts;
+use elasticsearch::nodes::{NodesHotThreadsParts, NodesStatsParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_aux::prelude::*;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+/// Node stats indexing pressure was introduced in 7.9
+const INDEXING_PRESSURE_VERSION: Version = Version::new(7, 9, 0);
+
+/// Circuit breakers are only reported if they tripped or are above this usage, in percent of their limit
+const BREAKER_USAGE_REPORTED: f64 = 50.0;
+
+/// Default number of hot threads per node, and of stack frames per thread
+const DEFAULT_HOT_THREADS: u32 = 3;
+const DEFAULT_HOT_THREAD_FRAMES: usize = 10;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct NodesStatsParams {
+    /// Optional node filter: node ID, name, or node specification like 'data:true' (default: _all)
+    node_id: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ThreadPoolsParams {
+    /// Optional comma-separated thread pool names (supports * wildcard, e.g. 'search,write')
+    thread_pool: Option<String>,
+    /// Include idle thread pools, with no active, queued or rejected tasks (default: false)
+    include_idle: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct HotThreadsParams {
+    /// Optional node filter: node ID, name, or node specification like 'data:true' (default: _all)
+    node_id: Option<String>,
+    /// Number of hot threads per node (default: 3)
+    threads: Option<u32>,
+    /// Maximum number of stack frames per thread (default: 10)
+    max_frames: Option<usize>,
+}
+
+#[tool_router(router = nodes_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: Get nodes stats
+    ///
+    /// Reduces `_nodes/stats` to what's needed to triage slow clusters. Thread pools are only listed
+    /// if they have queued or rejected tasks, and circuit breakers if they tripped or are half full.
+    #[tool(
+        description = "Get per-node runtime statistics: JVM heap and GC, thread pool rejections, circuit breakers, disk usage and indexing pressure. Use it to triage slow clusters.",
+        annotations(title = "Get nodes stats", read_only_hint = true)
+    )]
+    async fn get_nodes_stats(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<NodesStatsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let cluster = self.cached_cluster_info(&req_ctx);
+        let es_client = self.es_client.get(req_ctx);
+
+        let mut metrics = vec!["jvm", "thread_pool", "breaker", "fs"];
+        if cluster.is_some_and(|info| !info.is_opensearch() && info.version >= INDEXING_PRESSURE_VERSION) {
+            metrics.push("indexing_pressure");
+        }
+
+        let node_ids = [params.node_id.as_deref().unwrap_or("_all")];
+        let response = es_client
+            .nodes()
+            .stats(NodesStatsParts::NodeIdMetric(&node_ids, &metrics))
+            .human(true)
+            .send()
+            .await;
+        let response: NodesStatsResponse = read_json(response).await?;
+
+        let mut nodes = response
+            .nodes
+            .into_iter()
+            .map(|(id, node)| compact_node_stats(&id, &node))
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+        let data = pack_json_value(&nodes, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Stats for {} node(s).", nodes.len()), "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: Get thread pools
+    #[tool(
+        description = "Get thread pool activity per node (active, queued and rejected tasks). Idle thread pools are hidden by default. Rejections mean the cluster can't keep up with the search or indexing load.",
+        annotations(title = "Get thread pools", read_only_hint = true)
+    )]
+    async fn get_thread_pools(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ThreadPoolsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let patterns: Vec<&str> = params.thread_pool.as_deref().map(|p| p.split(',').collect()).unwrap_or_default();
+        let parts = if patterns.is_empty() {
+            CatThreadPoolParts::None
+        } else {
+            CatThreadPoolParts::ThreadPoolPatterns(&patterns)
+        };
+        let response = es_client
+            .cat()
+            .thread_pool(parts)
+            .h(&["node_name", "name", "active", "queue", "rejected", "size", "queue_size"])
+            .format("json")
+            .send()
+            .await;
+        let mut pools: Vec<CatThreadPool> = read_json(response).await?;
+        let total = pools.len();
+
+        if !params.include_idle.unwrap_or(false) {
+            pools.retain(|p| p.active > 0 || p.queue > 0 || p.rejected > 0);
+        }
+        pools.sort_by(|a, b| (b.rejected, b.queue, b.active).cmp(&(a.rejected, a.queue, a.active)));
+
+        let rejected = pools.iter().filter(|p| p.rejected > 0).count();
+        let message = format!(
+            "{} of {} thread pool(s) shown, {} with rejections.",
+            pools.len(),
+            total,
+            rejected
+        );
+        let data = pack_json_value(&pools, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: Get hot threads
+    ///
+    /// The text output of `_nodes/hot_threads` is parsed into the busiest threads of each node
+    /// and their top stack frames.
+    #[tool(
+        description = "Get the busiest threads of each node with their CPU usage and top stack frames, to find out what a busy cluster is actually doing.",
+        annotations(title = "Get hot threads", read_only_hint = true)
+    )]
+    async fn get_hot_threads(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<HotThreadsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let node_ids: [&str; 1];
+        let parts = match &params.node_id {
+            Some(node_id) => {
+                node_ids = [node_id];
+                NodesHotThreadsParts::NodeId(&node_ids)
+            }
+            None => NodesHotThreadsParts::None,
+        };
+        let response = es_client
+            .nodes()
+            .hot_threads(parts)
+            .threads(params.threads.unwrap_or(DEFAULT_HOT_THREADS) as i64)
+            .send()
+            .await;
+        let text = read_text(response).await?;
+
+        let mut nodes = parse_hot_threads(&text, params.max_frames.unwrap_or(DEFAULT_HOT_THREAD_FRAMES));
+        let mut message = match nodes
+            .iter()
+            .flat_map(|n| n.threads.iter().map(move |t| (n, t)))
+            .max_by(|a, b| a.1.cpu_percent.total_cmp(&b.1.cpu_percent))
+        {
+            Some((node, thread)) => format!(
+                "Hot threads of {} node(s). Busiest: '{}' on {} ({}% CPU).",
+                nodes.len(),
+                thread.thread,
+                node.node,
+                thread.cpu_percent
+            ),
+            None => format!("No hot threads on {} node(s).", nodes.len()),
+        };
+        if fit_hot_threads(&mut nodes, max_response_chars()) {
+            message.push_str(
+                " Stack frames and the least busy threads were trimmed to fit the response size limit: use node_id, threads or max_frames to narrow it.",
+            );
+        }
+
+        let data = pack_json_value(&nodes, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+/// Reduce the stats of a node to a compact shape
+fn compact_node_stats(id: &str, node: &Value) -> Value {
+    let mut result = Map::new();
+    result.insert("id".to_string(), json!(id));
+    result.insert("name".to_string(), node["name"].clone());
+    result.insert("roles".to_string(), node["roles"].clone());
+
+    if let Some(jvm) = node.get("jvm") {
+        let mem = &jvm["mem"];
+        let mut gc = Map::new();
+        if let Some(collectors) = jvm.pointer("/gc/collectors").and_then(|c| c.as_object()) {
+            for (name, collector) in collectors {
+                gc.insert(
+                    name.clone(),
+                    json!({
+                        "count": collector["collection_count"],
+                        "time_ms": collector["collection_time_in_millis"],
+                    }),
+                );
+            }
+        }
+        result.insert(
+            "jvm".to_string(),
+            json!({
+                "heap_used_percent": mem["heap_used_percent"],
+                "heap_used": mem["heap_used"],
+                "heap_max": mem["heap_max"],
+                "gc": gc,
+            }),
+        );
+    }
+
+    if let Some(pools) = node.get("thread_pool").and_then(|p| p.as_object()) {
+        let busy = pools
+            .iter()
+            .filter(|(_, pool)| pool["rejected"].as_u64().unwrap_or(0) > 0 || pool["queue"].as_u64().unwrap_or(0) > 0)
+            .map(|(name, pool)| {
+                (
+                    name.clone(),
+                    json!({
+                        "active": pool["active"],
+                        "queue": pool["queue"],
+                        "rejected": pool["rejected"],
+                    }),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        result.insert("thread_pools".to_string(), json!(busy));
+    }
+
+    if let Some(breakers) = node.get("breakers").and_then(|b| b.as_object()) {
+        let reported = breakers
+            .iter()
+            .filter_map(|(name, breaker)| {
+                let estimated = breaker["estimated_size_in_bytes"].as_f64().unwrap_or(0.0);
+                let limit = breaker["limit_size_in_bytes"].as_f64().unwrap_or(0.0);
+                let usage = if limit > 0.0 { (estimated * 100.0 / limit).round() } else { 0.0 };
+                let tripped = breaker["tripped"].as_u64().unwrap_or(0);
+                (tripped > 0 || usage >= BREAKER_USAGE_REPORTED).then(|| {
+                    (
+                        name.clone(),
+                        json!({
+                            "usage_percent": usage,
+                            "estimated": breaker["estimated_size"],
+                            "limit": breaker["limit_size"],
+                            "tripped": tripped,
+                        }),
+                    )
+                })
+            })
+            .collect::<BTreeMap<_, _>>();
+        result.insert("breakers".to_string(), json!(reported));
+    }
+
+    if let Some(total) = node.pointer("/fs/total") {
+        let total_bytes = total["total_in_bytes"].as_f64().unwrap_or(0.0);
+        let available_bytes = total["available_in_bytes"].as_f64().unwrap_or(0.0);
+        let used_percent = if total_bytes > 0.0 {
+            ((total_bytes - available_bytes) * 100.0 / total_bytes).round()
+        } else {
+            0.0
+        };
+        result.insert(
+            "disk".to_string(),
+            json!({
+                "total": total["total"],
+                "available": total["available"],
+                "used_percent": used_percent,
+            }),
+        );
+    }
+
+    if let Some(memory) = node.pointer("/indexing_pressure/memory") {
+        let total = &memory["total"];
+        let rejections = ["coordinating_rejections", "primary_rejections", "replica_rejections"]
+            .iter()
+            .map(|key| total[*key].as_u64().unwrap_or(0))
+            .sum::<u64>();
+        result.insert(
+            "indexing_pressure".to_string(),
+            json!({
+                "current": memory.pointer("/current/all"),
+                "limit": memory["limit"],
+                "rejections": rejections,
+            }),
+        );
+    }
+
+    Value::Object(result)
+}
+
+#[derive(Serialize)]
+struct NodeHotThreads {
+    node: String,
+    threads: Vec<HotThread>,
+}
+
+#[derive(Serialize)]
+struct HotThread {
+    cpu_percent: f64,
+    /// e.g. `76.6ms out of 500ms`
+    usage: String,
+    thread: String,
+    frames: Vec<String>,
+}
+
+/// Parse the text output of `_nodes/hot_threads`:
+///
+/// ```text
+/// ::: {node-1}{Ae8x...}{...}{127.0.0.1}{127.0.0.1:9300}{dim}
+///    Hot threads at 2024-01-01T00:00:00.000Z, interval=500ms, busiestThreads=3, ignoreIdleThreads=true:
+///
+///    15.3% (76.6ms out of 500ms) cpu usage by thread 'elasticsearch[node-1][search][T#3]'
+///      2/10 snapshots sharing following 30 elements
+///        app//org.apache.lucene.search.BooleanScorer.score(BooleanScorer.java:312)
+/// ```
+fn parse_hot_threads(text: &str, max_frames: usize) -> Vec<NodeHotThreads> {
+    let mut nodes: Vec<NodeHotThreads> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix(":::") {
+            let name = header
+                .trim()
+                .strip_prefix('{')
+                .and_then(|h| h.split('}').next())
+                .unwrap_or(header.trim());
+            nodes.push(NodeHotThreads {
+                node: name.to_string(),
+                threads: Vec::new(),
+            });
+        } else if let Some((usage, thread)) = line.split_once(" cpu usage by thread ") {
+            let Some(node) = nodes.last_mut() else {
+                continue;
+            };
+            let (percent, usage) = usage.split_once(' ').unwrap_or((usage, ""));
+            node.threads.push(HotThread {
+                cpu_percent: percent.trim_end_matches('%').parse().unwrap_or(0.0),
+                usage: usage.trim_matches(['(', ')']).to_string(),
+                thread: thread.trim_matches('\'').to_string(),
+                frames: Vec::new(),
+            });
+        } else if !line.is_empty()
+            && !line.starts_with("Hot threads at")
+            && !line.contains("snapshots sharing following")
+            && !line.starts_with("unique snapshot")
+            && let Some(thread) = nodes.last_mut().and_then(|n| n.threads.last_mut())
+            && thread.frames.len() < max_frames
+        {
+            thread.frames.push(line.to_string());
+        }
+    }
+
+    nodes
+}
+
+/// Drop stack frames, then the least busy threads, until the serialized nodes fit in `max_chars`.
+/// Returns whether anything was dropped.
+fn fit_hot_threads(nodes: &mut [NodeHotThreads], max_chars: usize) -> bool {
+    let fits = |nodes: &[NodeHotThreads]| serde_json::to_string(nodes).map_or(true, |s| s.len() <= max_chars);
+    let mut trimmed = false;
+
+    // Keep the top frames of all threads, as they tell what each thread is doing
+    let mut max_frames = nodes.iter().flat_map(|n| &n.threads).map(|t| t.frames.len()).max().unwrap_or(0);
+    while max_frames > 1 && !fits(nodes) {
+        max_frames -= 1;
+        for thread in nodes.iter_mut().flat_map(|n| &mut n.threads) {
+            thread.frames.truncate(max_frames);
+        }
+        trimmed = true;
+    }
+
+    while !fits(nodes) {
+        let least_busy = nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(n, node)| node.threads.iter().enumerate().map(move |(t, thread)| (n, t, thread.cpu_percent)))
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        let Some((n, t, _)) = least_busy else {
+            break;
+        };
+        nodes[n].threads.remove(t);
+        trimmed = true;
+    }
+    trimmed
+}
+
+//----- Responses
+
+#[derive(Serialize, Deserialize)]
+struct CatThreadPool {
+    node_name: String,
+    name: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    active: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    queue: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    rejected: u64,
+    size: Option<String>,
+    queue_size: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NodesStatsResponse {
+    #[serde(default)]
+    nodes: BTreeMap<String, Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_stats() {
+        let node = json!({
+            "name": "node-1",
+            "roles": ["data", "master"],
+            "jvm": {
+                "mem": { "heap_used_percent": 87, "heap_used": "3.4gb", "heap_max": "4gb" },
+                "gc": { "collectors": {
+                    "young": { "collection_count": 120, "collection_time_in_millis": 3000 },
+                    "old": { "collection_count": 2, "collection_time_in_millis": 800 },
+                }},
+            },
+            "thread_pool": {
+                "search": { "active": 13, "queue": 400, "rejected": 12 },
+                "write": { "active": 0, "queue": 0, "rejected": 0 },
+            },
+            "breakers": {
+                "parent": { "estimated_size_in_bytes": 3_800, "limit_size_in_bytes": 4_000, "estimated_size": "3.8kb", "limit_size": "4kb", "tripped": 0 },
+                "fielddata": { "estimated_size_in_bytes": 0, "limit_size_in_bytes": 4_000, "tripped": 0 },
+            },
+            "fs": { "total": { "total_in_bytes": 1000, "available_in_bytes": 100, "total": "1kb", "available": "100b" } },
+        });
+
+        let stats = compact_node_stats("abc", &node);
+        assert_eq!(stats["jvm"]["heap_used_percent"], json!(87));
+        assert_eq!(stats["jvm"]["gc"]["old"], json!({ "count": 2, "time_ms": 800 }));
+        assert_eq!(stats["thread_pools"], json!({ "search": { "active": 13, "queue": 400, "rejected": 12 } }));
+        assert_eq!(stats["breakers"].as_object().unwrap().keys().collect::<Vec<_>>(), vec!["parent"]);
+        assert_eq!(stats["breakers"]["parent"]["usage_percent"], json!(95.0));
+        assert_eq!(stats["disk"]["used_percent"], json!(90.0));
+        assert!(stats.get("indexing_pressure").is_none());
+    }
+
+    #[test]
+    fn hot_threads() {
+        let text = r"::: {node-1}{Ae8xQ}{aZ3}{127.0.0.1}{127.0.0.1:9300}{dim}
+   Hot threads at 2024-01-01T00:00:00.000Z, interval=500ms, busiestThreads=3, ignoreIdleThreads=true:
+
+   15.3% (76.6ms out of 500ms) cpu usage by thread 'elasticsearch[node-1][search][T#3]'
+     2/10 snapshots sharing following 30 elements
+       app//org.apache.lucene.search.BooleanScorer.score(BooleanScorer.java:312)
+       app//org.apache.lucene.search.Weight$DefaultBulkScorer.score(Weight.java:274)
+       app//org.elasticsearch.search.query.QueryPhase.execute(QueryPhase.java:210)
+
+::: {node-2}{Bx9}{cY1}{127.0.0.2}{127.0.0.2:9300}{dim}
+   Hot threads at 2024-01-01T00:00:00.000Z, interval=500ms, busiestThreads=3, ignoreIdleThreads=true:
+";
+        let nodes = parse_hot_threads(text, 2);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].node, "node-1");
+        let thread = &nodes[0].threads[0];
+        assert_eq!(thread.cpu_percent, 15.3);
+        assert_eq!(thread.usage, "76.6ms out of 500ms");
+        assert_eq!(thread.thread, "elasticsearch[node-1][search][T#3]");
+        assert_eq!(thread.frames.len(), 2);
+        assert!(nodes[1].threads.is_empty());
+    }
+
+    #[test]
+    fn hot_threads_fit_in_budget() {
+        let thread = |cpu: f64| HotThread {
+            cpu_percent: cpu,
+            usage: "10ms out of 500ms".to_string(),
+            thread: format!("elasticsearch[node-1][search][T#{cpu}]"),
+            frames: (0..20).map(|i| format!("app//org.elasticsearch.Frame{i}.run(Frame.java:{i})")).collect(),
+        };
+        let mut nodes = vec![NodeHotThreads {
+            node: "node-1".to_string(),
+            threads: vec![thread(80.0), thread(5.0), thread(40.0)],
+        }];
+        assert!(!fit_hot_threads(&mut nodes, 100_000));
+
+        assert!(fit_hot_threads(&mut nodes, 1000));
+        assert!(serde_json::to_string(&nodes).unwrap().len() <= 1000);
+        let threads = &nodes[0].threads;
+        assert_eq!(threads.len(), 3);
+        assert!(threads.iter().all(|t| t.frames.len() == threads[0].frames.len() && !t.frames.is_empty()));
+
+        assert!(fit_hot_threads(&mut nodes, 400));
+        assert!(serde_json::to_string(&nodes).unwrap().len() <= 400);
+        let cpu = nodes[0].threads.iter().map(|t| t.cpu_percent).collect::<Vec<_>>();
+        assert!(cpu.contains(&80.0) && !cpu.contains(&5.0));
+    }
+}
@@ -0,0 +1,458 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Query introspection and batching: count matching documents, validate queries and see how they
+//! are rewritten, explain why a document matches a query or not, and run several searches at once.
+
+use crate::servers::elasticsearch::base_tools::{
+    EsBaseTools, MsearchResponse, SearchResult, check_query, max_response_chars, pack_json_value, prepare_search_body,
+    search_payload,
+};
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::http::StatusCode;
+use elasticsearch::http::request::JsonBody;
+use elasticsearch::indices::IndicesValidateQueryParts;
+use elasticsearch::{CountParts, ExplainParts, MsearchParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+/// Depth of the score explanation tree returned by `explain_document`
+const MAX_EXPLANATION_DEPTH: usize = 6;
+
+/// Number of lines of the score explanation returned by `explain_document`
+const MAX_EXPLANATION_LINES: usize = 60;
+
+/// Number of searches `msearch` can run at once
+const MAX_MSEARCH_SEARCHES: usize = 10;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CountParams {
+    /// Index, alias, data stream or index pattern
+    index: String,
+    /// Optional query DSL, e.g. {"term": {"status": "error"}}
+    query: Option<Map<String, Value>>,
+    /// Optional query in Lucene query string syntax, e.g. "status:error AND service:api", instead of `query`
+    q: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ValidateQueryParams {
+    /// Index, alias, data stream or index pattern
+    index: String,
+    /// Query DSL to validate, e.g. {"match": {"title": "foo"}}
+    query: Map<String, Value>,
+    /// Return the query as rewritten against the index data (e.g. expanded wildcards and fuzzy terms), which is more detailed (default: false)
+    rewrite: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ExplainDocumentParams {
+    /// Index containing the document
+    index: String,
+    /// Document id (`_id`)
+    id: String,
+    /// Query DSL to explain, e.g. {"match": {"title": "foo"}}
+    query: Map<String, Value>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct MsearchParams {
+    /// Searches to run, at most 10
+    searches: Vec<MsearchEntry>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct MsearchEntry {
+    /// Name of the Elasticsearch index, alias, data stream or index pattern to search
+    index: String,
+    /// Name of the fields that need to be returned (optional)
+    fields: Option<Vec<String>>,
+    /// Complete Elasticsearch query DSL object that can include query, size, from, sort, etc.
+    query_body: Map<String, Value>,
+}
+
+#[tool_router(router = query_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: count documents
+    #[tool(
+        description = "Count the documents matching a query DSL or a Lucene query string, or all documents. Cheaper than a search with size 0.",
+        annotations(title = "Count documents", read_only_hint = true)
+    )]
+    async fn count(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<CountParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let mut request = es_client.count(CountParts::Index(&[&params.index]));
+        match (params.query, &params.q) {
+            (Some(_), Some(_)) => {
+                return Err(rmcp::Error::invalid_params("Provide either 'query' or 'q', not both.", None));
+            }
+            (Some(query), None) => {
+                let query = Value::Object(query);
+                check_query(&query)?;
+                request = request.body(json!({ "query": query }));
+            }
+            (None, Some(q)) => request = request.q(q),
+            (None, None) => {}
+        }
+        let response: CountResponse = read_json(request.send().await).await?;
+
+        let mut message = format!("{} document(s) match in {}.", response.count, params.index);
+        if response.shards.failed > 0 {
+            message.push_str(&format!(
+                " {} of {} shards failed: the count may be incomplete.",
+                response.shards.failed, response.shards.total
+            ));
+        }
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": { "count": response.count } }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: validate a query
+    #[tool(
+        description = "Validate a query DSL without running it: reports parsing errors (e.g. unknown query types or fields of the wrong type) and shows the Lucene query it translates to.",
+        annotations(title = "Validate query", read_only_hint = true)
+    )]
+    async fn validate_query(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ValidateQueryParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .indices()
+            .validate_query(IndicesValidateQueryParts::Index(&[&params.index]))
+            .explain(true)
+            .rewrite(params.rewrite.unwrap_or(false))
+            .body(json!({ "query": params.query }))
+            .send()
+            .await;
+        let response: ValidateQueryResponse = read_json(response).await?;
+
+        let explanations = group_explanations(response.explanations);
+        let message = if response.valid {
+            "The query is valid.".to_string()
+        } else {
+            let error = explanations
+                .iter()
+                .find_map(|e| e.error.as_deref())
+                .unwrap_or("unknown error");
+            format!("The query is invalid: {error}")
+        };
+        let data = pack_json_value(&explanations, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "valid": response.valid, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: explain a document's score
+    #[tool(
+        description = "Explain why a document matches a query or not, and how its score is computed.",
+        annotations(title = "Explain document", read_only_hint = true)
+    )]
+    async fn explain_document(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ExplainDocumentParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let query = Value::Object(params.query);
+        check_query(&query)?;
+        let response = es_client
+            .explain(ExplainParts::IndexId(&params.index, &params.id))
+            .body(json!({ "query": query }))
+            .send()
+            .await;
+        // A missing document is a 404 with a regular response
+        if let Ok(response) = &response
+            && response.status_code() == StatusCode::NOT_FOUND
+        {
+            return Err(rmcp::Error::invalid_params(
+                format!("Document {} not found in {}.", params.id, params.index),
+                None,
+            ));
+        }
+        let response: ExplainResponse = read_json(response).await?;
+
+        let mut lines = Vec::new();
+        if let Some(explanation) = &response.explanation {
+            explanation_lines(explanation, 0, &mut lines);
+        }
+        let message = if response.matched {
+            let score = response.explanation.as_ref().map(|e| e.value).unwrap_or_default();
+            format!("Document {} matches the query with score {score}.", params.id)
+        } else {
+            format!("Document {} doesn't match the query.", params.id)
+        };
+        let data = pack_json_value(&lines, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "matched": response.matched, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: multi-search
+    #[tool(
+        description = "Run several searches in a single call (e.g. to compare time windows or indices), each with an index and a query DSL like the search tool. Results are returned in the order of the searches, and a failed search doesn't fail the others.",
+        annotations(title = "Elasticsearch multi-search", read_only_hint = true)
+    )]
+    async fn msearch(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<MsearchParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let count = params.searches.len();
+        if count == 0 || count > MAX_MSEARCH_SEARCHES {
+            return Err(rmcp::Error::invalid_params(
+                format!("Provide between 1 and {MAX_MSEARCH_SEARCHES} searches."),
+                None,
+            ));
+        }
+
+        let mut indices = Vec::with_capacity(count);
+        let mut body: Vec<JsonBody<Value>> = Vec::with_capacity(count * 2);
+        for search in params.searches {
+            // Same as the search tool: size limit and requested fields, the query is forwarded as-is
+            let mut query_body = search.query_body;
+            prepare_search_body(&mut query_body, search.fields);
+            body.push(JsonBody::new(json!({ "index": search.index })));
+            body.push(JsonBody::new(Value::Object(query_body)));
+            indices.push(search.index);
+        }
+
+        let response = es_client.msearch(MsearchParts::None).body(body).send().await;
+        let response: MsearchResponse = read_json(response).await?;
+
+        // All searches share the response budget
+        let max_chars = max_response_chars() / count;
+        let mut failed = 0;
+        let mut results = Vec::with_capacity(count);
+        let mut responses = response.responses.into_iter();
+        for index in indices {
+            let mut result = match responses.next().map(search_result) {
+                Some(Ok(response)) => search_payload(&response, max_chars)?,
+                Some(Err(error)) => {
+                    failed += 1;
+                    Map::from_iter([("error".to_string(), Value::String(error))])
+                }
+                None => {
+                    failed += 1;
+                    Map::from_iter([("error".to_string(), json!("missing search response"))])
+                }
+            };
+            result.insert("index".to_string(), Value::String(index));
+            results.push(Value::Object(result));
+        }
+
+        let message = format!("{count} search(es) run, {failed} failed.");
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "results": results }))?
+        ]))
+    }
+}
+
+/// A search response of `_msearch`, or its error reason
+fn search_result(response: Value) -> Result<SearchResult, String> {
+    if let Some(error) = response.get("error") {
+        let reason = error
+            .get("reason")
+            .and_then(|r| r.as_str())
+            .unwrap_or("unknown error")
+            .to_string();
+        return Err(reason);
+    }
+    serde_json::from_value(response).map_err(|e| format!("invalid search response: {e}"))
+}
+
+/// Indices with the same validation result are grouped, as they usually share the same mappings
+fn group_explanations(explanations: Vec<IndexExplanation>) -> Vec<ExplanationGroup> {
+    let mut groups: Vec<ExplanationGroup> = Vec::new();
+    for e in explanations {
+        match groups
+            .iter_mut()
+            .find(|g| g.valid == e.valid && g.explanation == e.explanation && g.error == e.error)
+        {
+            Some(group) => group.indices.extend(e.index),
+            None => groups.push(ExplanationGroup {
+                indices: e.index.into_iter().collect(),
+                valid: e.valid,
+                explanation: e.explanation,
+                error: e.error,
+            }),
+        }
+    }
+    groups
+}
+
+/// Render a score explanation as indented `value: description` lines
+fn explanation_lines(explanation: &Explanation, depth: usize, lines: &mut Vec<String>) {
+    if lines.len() >= MAX_EXPLANATION_LINES {
+        return;
+    }
+    lines.push(format!(
+        "{}{}: {}",
+        "  ".repeat(depth),
+        explanation.value,
+        explanation.description
+    ));
+    if depth + 1 >= MAX_EXPLANATION_DEPTH {
+        if !explanation.details.is_empty() {
+            lines.push(format!("{}...", "  ".repeat(depth + 1)));
+        }
+        return;
+    }
+    for detail in &explanation.details {
+        explanation_lines(detail, depth + 1, lines);
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct CountResponse {
+    count: u64,
+    #[serde(rename = "_shards")]
+    shards: ShardStats,
+}
+
+#[derive(Deserialize)]
+struct ShardStats {
+    total: u64,
+    #[serde(default)]
+    failed: u64,
+}
+
+#[derive(Deserialize)]
+struct ValidateQueryResponse {
+    valid: bool,
+    #[serde(default)]
+    explanations: Vec<IndexExplanation>,
+}
+
+#[derive(Deserialize)]
+struct IndexExplanation {
+    index: Option<String>,
+    valid: bool,
+    explanation: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExplanationGroup {
+    indices: Vec<String>,
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExplainResponse {
+    matched: bool,
+    explanation: Option<Explanation>,
+}
+
+#[derive(Deserialize)]
+struct Explanation {
+    value: f64,
+    description: String,
+    #[serde(default)]
+    details: Vec<Explanation>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grouped_explanations() {
+        let response: ValidateQueryResponse = serde_json::from_value(json!({
+            "valid": false,
+            "explanations": [
+                { "index": "logs-1", "valid": true, "explanation": "+status:error" },
+                { "index": "logs-2", "valid": true, "explanation": "+status:error" },
+                { "index": "logs-3", "valid": false, "error": "failed to create query: For input string: \"error\"" },
+            ]
+        }))
+        .unwrap();
+
+        let groups = group_explanations(response.explanations);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].indices, vec!["logs-1", "logs-2"]);
+        assert_eq!(groups[1].indices, vec!["logs-3"]);
+        assert!(!groups[1].valid);
+    }
+
+    #[test]
+    fn msearch_results() {
+        let ok = search_result(json!({
+            "took": 3, "hits": { "total": { "value": 42, "relation": "eq" }, "hits": [{ "_index": "logs", "_id": "1", "_source": { "a": 1 } }] },
+            "status": 200
+        }))
+        .unwrap();
+        assert_eq!(ok.hits.total.map(|t| t.value), Some(42));
+
+        let error = search_result(json!({
+            "error": { "type": "index_not_found_exception", "reason": "no such index [missing]" },
+            "status": 404
+        }));
+        assert_eq!(error.err().as_deref(), Some("no such index [missing]"));
+    }
+
+    #[test]
+    fn explanation_tree() {
+        let explanation: Explanation = serde_json::from_value(json!({
+            "value": 1.5, "description": "sum of:",
+            "details": [
+                { "value": 1.0, "description": "weight(title:foo in 0)", "details": [
+                    { "value": 1.0, "description": "score(freq=1.0), computed as boost * idf * tf from:" }
+                ]},
+                { "value": 0.5, "description": "weight(body:foo in 0)" }
+            ]
+        }))
+        .unwrap();
+
+        let mut lines = Vec::new();
+        explanation_lines(&explanation, 0, &mut lines);
+        assert_eq!(
+            lines,
+            vec![
+                "1.5: sum of:",
+                "  1: weight(title:foo in 0)",
+                "    1: score(freq=1.0), computed as boost * idf * tf from:",
+                "  0.5: weight(body:foo in 0)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Reindex documents from one index to another.
+//!
+//! The reindex runs as a background task. If the client asked for progress notifications (by
+//! providing a progress token), the tool follows the task and reports its progress until it
+//! completes. Otherwise it returns the task id, to be followed with `get_task`.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, check_query, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::by_query::{TaskResponse, task_started};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::safety::{check_confirmation_token, confirmation_token};
+use crate::servers::elasticsearch::tasks::{TaskResult, fetch_task, task_message, task_progress};
+use elasticsearch::http::StatusCode;
+use elasticsearch::params::Slices;
+use elasticsearch::{CountParts, Elasticsearch, ReindexParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content, ProgressNotificationParam, ProgressToken};
+use rmcp::service::{Peer, RequestContext};
+use rmcp_macros::{tool, tool_router};
+use serde_json::{Map, Value, json};
+use std::time::{Duration, Instant};
+
+/// Interval between two progress checks of a reindex task
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Reindex tasks are followed at most this long, after which the task id is returned
+const MAX_FOLLOW_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// Number of document failures reported when a reindex completes
+const MAX_FAILURES: usize = 5;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ReindexParams {
+    /// Source index, alias, data stream or index pattern
+    source_index: String,
+    /// Destination index. If it doesn't exist, it is created from matching index templates or dynamic mappings.
+    dest_index: String,
+    /// Optional query DSL selecting the source documents to reindex (default: all documents)
+    query: Option<Map<String, Value>>,
+    /// Optional ingest pipeline documents are processed with
+    pipeline: Option<String>,
+    /// Maximum number of documents to reindex (default: all matching documents)
+    max_docs: Option<u64>,
+    /// Number of slices the reindex is split into to run in parallel, or "auto" (default: 1)
+    slices: Option<String>,
+    /// Throttling, in documents per second (default: unlimited)
+    requests_per_second: Option<i64>,
+    /// Token returned by a first call without it, after reviewing the source and destination
+    confirmation_token: Option<String>,
+}
+
+#[tool_router(router = reindex_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: reindex
+    #[tool(
+        description = "Copy documents from a source index to a destination index, optionally filtered by a query and processed by an ingest pipeline, e.g. to apply mapping changes. A first call without confirmation_token returns the number of documents to copy and the state of the destination with a token: review them with the user, then call again with the token. The reindex runs as a background task: its progress is notified if the client supports it, otherwise follow it with get_task.",
+        annotations(title = "Reindex", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn reindex(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ReindexParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let progress_token = req_ctx.meta.get_progress_token();
+        let peer = req_ctx.peer.clone();
+        let cancellation = req_ctx.ct.clone();
+        let es_client = self.es_client.get(req_ctx);
+
+        if params.source_index == params.dest_index {
+            return Err(rmcp::Error::invalid_params(
+                "The source and destination indices must be different.",
+                None,
+            ));
+        }
+        let query = params.query.map(Value::Object);
+        if let Some(query) = &query {
+            check_query(query)?;
+        }
+        let slices = params.slices.as_deref().map(parse_slices).transpose()?;
+        self.check_write_targets(&es_client, &params.dest_index).await?;
+
+        let token = confirmation_token(&[
+            "reindex",
+            &params.source_index,
+            &params.dest_index,
+            &json!(query).to_string(),
+            params.pipeline.as_deref().unwrap_or_default(),
+            &json!(params.max_docs).to_string(),
+            params.slices.as_deref().unwrap_or_default(),
+            &json!(params.requests_per_second).to_string(),
+        ]);
+
+        let source_count = count_documents(&es_client, &params.source_index, query.as_ref())
+            .await?
+            .ok_or_else(|| rmcp::Error::invalid_params(format!("Index {} not found.", params.source_index), None))?;
+        let copied = params.max_docs.map_or(source_count, |max| max.min(source_count));
+
+        let Some(provided) = params.confirmation_token else {
+            let dest_count = count_documents(&es_client, &params.dest_index, None).await?;
+            let dest_state = match dest_count {
+                Some(count) => format!("{} exists and has {count} document(s)", params.dest_index),
+                None => format!(
+                    "{} doesn't exist and will be created from matching index templates or dynamic mappings (use create_index first to control its mappings)",
+                    params.dest_index
+                ),
+            };
+            let message = format!(
+                "{copied} document(s) will be copied from {} to {}. {dest_state}. Review it with the user, then call reindex again with the confirmation_token.",
+                params.source_index, params.dest_index
+            );
+            let data = json!({ "source_count": source_count, "dest_count": dest_count });
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "reindex without confirmation_token")?;
+
+        let mut body = json!({
+            "source": { "index": params.source_index },
+            "dest": { "index": params.dest_index },
+        });
+        if let Some(query) = query {
+            body["source"]["query"] = query;
+        }
+        if let Some(pipeline) = &params.pipeline {
+            body["dest"]["pipeline"] = json!(pipeline);
+        }
+        if let Some(max_docs) = params.max_docs {
+            body["max_docs"] = json!(max_docs);
+        }
+
+        let mut request = es_client
+            .reindex(ReindexParts::None)
+            .wait_for_completion(false)
+            .body(body);
+        if let Some(slices) = slices {
+            request = request.slices(slices);
+        }
+        if let Some(requests_per_second) = params.requests_per_second {
+            request = request.requests_per_second(requests_per_second);
+        }
+        let response: TaskResponse = read_json(request.send().await).await?;
+
+        let Some(progress_token) = progress_token else {
+            return task_started("Reindex", &response.task, Some(copied));
+        };
+
+        // Follow the task until it completes, the client cancels the request, or it runs too long
+        let started = Instant::now();
+        loop {
+            tokio::select! {
+                _ = cancellation.cancelled() => return task_started("Reindex", &response.task, Some(copied)),
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+            // The reindex is running: return its id rather than an error if it can't be followed
+            let task = match fetch_task(&es_client, &response.task).await {
+                Ok(task) => task,
+                Err(e) => {
+                    tracing::warn!("Could not follow reindex task {}: {}", response.task, e.message);
+                    return task_started("Reindex", &response.task, Some(copied));
+                }
+            };
+            notify_progress(&peer, &progress_token, &response.task, &task).await;
+
+            if task.completed {
+                let message = task_message(&response.task, &task);
+                let data = pack_json_value(&reindex_summary(task.response), max_response_chars())?;
+                return Ok(CallToolResult::success(vec![
+                    Content::json(json!({ "message": message, "task_id": response.task, "data": data }))?
+                ]));
+            }
+            if started.elapsed() > MAX_FOLLOW_DURATION {
+                return task_started("Reindex", &response.task, Some(copied));
+            }
+        }
+    }
+}
+
+/// Number of documents of an index matching an optional query, or `None` if the index doesn't exist
+async fn count_documents(
+    es_client: &Elasticsearch,
+    index: &str,
+    query: Option<&Value>,
+) -> Result<Option<u64>, rmcp::Error> {
+    let mut request = es_client.count(CountParts::Index(&[index]));
+    if let Some(query) = query {
+        request = request.body(json!({ "query": query }));
+    }
+    let response = request.send().await;
+    if let Ok(response) = &response
+        && response.status_code() == StatusCode::NOT_FOUND
+    {
+        return Ok(None);
+    }
+    let response: Value = read_json(response).await?;
+    Ok(response["count"].as_u64())
+}
+
+fn parse_slices(slices: &str) -> Result<Slices, rmcp::Error> {
+    match slices {
+        "auto" => Ok(Slices::Auto),
+        _ => match slices.parse::<u32>() {
+            Ok(n) if n > 0 => Ok(Slices::Count(n)),
+            _ => Err(rmcp::Error::invalid_params(
+                format!("Invalid slices '{slices}': expected \"auto\" or a positive number."),
+                None,
+            )),
+        },
+    }
+}
+
+async fn notify_progress(peer: &Peer<RoleServer>, progress_token: &ProgressToken, task_id: &str, task: &TaskResult) {
+    let (done, total) = task_progress(task).unwrap_or_default();
+    let param = ProgressNotificationParam {
+        progress_token: progress_token.clone(),
+        progress: done.min(u32::MAX as u64) as u32,
+        total: (total > 0).then_some(total.min(u32::MAX as u64) as u32),
+        message: Some(task_message(task_id, task)),
+    };
+    if let Err(e) = peer.notify_progress(param).await {
+        tracing::warn!("Could not send reindex progress notification: {e}");
+    }
+}
+
+/// Counters of a completed reindex, with its first document failures
+fn reindex_summary(response: Option<Value>) -> Value {
+    let Some(Value::Object(mut response)) = response else {
+        return Value::Null;
+    };
+    if let Some(Value::Array(failures)) = response.get_mut("failures") {
+        failures.truncate(MAX_FAILURES);
+    }
+    let keys = [
+        "took", "timed_out", "total", "created", "updated", "deleted", "batches", "version_conflicts", "noops",
+        "failures",
+    ];
+    Value::Object(keys.iter().filter_map(|k| Some((k.to_string(), response.remove(*k)?))).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices() {
+        assert!(matches!(parse_slices("auto"), Ok(Slices::Auto)));
+        assert!(matches!(parse_slices("4"), Ok(Slices::Count(4))));
+        assert!(parse_slices("0").is_err());
+        assert!(parse_slices("many").is_err());
+    }
+
+    #[test]
+    fn summary_of_completed_reindex() {
+        let failures = (0..10).map(|i| json!({ "id": i.to_string(), "cause": {} })).collect::<Vec<_>>();
+        let response = json!({
+            "took": 1200, "timed_out": false, "total": 1000, "created": 990, "updated": 0, "deleted": 0,
+            "batches": 1, "version_conflicts": 0, "noops": 0, "retries": { "bulk": 0, "search": 0 },
+            "throttled_millis": 0, "failures": failures,
+        });
+
+        let summary = reindex_summary(Some(response));
+        assert_eq!(summary["created"], 990);
+        assert_eq!(summary["failures"].as_array().unwrap().len(), MAX_FAILURES);
+        assert!(summary.get("retries").is_none());
+        assert_eq!(reindex_summary(None), Value::Null);
+    }
+}
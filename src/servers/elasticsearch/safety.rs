@@ -0,0 +1,134 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Safety mechanisms for tools that modify the cluster.
+//!
+//! Write tools are only exposed when the server isn't in read-only mode, and the most dangerous
+//! ones follow a two-step protocol: a preview (dry-run) call describes the change and returns a
+//! confirmation token, which the actual call must provide.
+
+use crate::servers::elasticsearch::templates::matches_pattern;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::OnceLock;
+
+/// Compute the confirmation token for a change, identified by its parts (tool name, target, proposed
+/// change and current state of the target). A token is therefore only valid if neither the change nor
+/// the target have been modified since the preview.
+///
+/// Tokens are keyed with a per-process secret so that they can't be computed by clients, and are
+/// only valid for the lifetime of the server process.
+pub fn confirmation_token(parts: &[&str]) -> String {
+    static SECRET: OnceLock<RandomState> = OnceLock::new();
+    let hash = SECRET.get_or_init(RandomState::new).hash_one(parts);
+    format!("{hash:016x}")
+}
+
+/// Check a confirmation token provided by the client against the expected one.
+pub fn check_confirmation_token(provided: &str, expected: &str, preview_tool: &str) -> Result<(), rmcp::Error> {
+    if provided == expected {
+        Ok(())
+    } else {
+        Err(rmcp::Error::invalid_params(
+            format!(
+                "Invalid confirmation token: the target or the requested change differ from the preview. Call {preview_tool} again and review the result before retrying."
+            ),
+            None,
+        ))
+    }
+}
+
+/// Refuse changes to system indices, whose name starts with a dot (e.g. `.security`, `.kibana`)
+pub fn check_not_system_index(index: &str) -> Result<(), rmcp::Error> {
+    if index.starts_with('.') {
+        Err(rmcp::Error::invalid_params(
+            format!("Index {index} is a system index and can't be modified by this server."),
+            None,
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Indices an operation is allowed on, from the `allow_<operation>_patterns` and
+/// `deny_<operation>_patterns` configuration. Deny patterns take precedence, and if allow patterns
+/// are configured, only matching indices are allowed. System indices are never allowed.
+#[derive(Debug, Clone)]
+pub struct IndexPolicy {
+    operation: &'static str,
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl IndexPolicy {
+    /// Policy for `operation` (e.g. "delete", "write"), used in error messages to name the configuration entries.
+    pub fn new(operation: &'static str, allow: Vec<String>, deny: Vec<String>) -> Self {
+        IndexPolicy { operation, allow, deny }
+    }
+
+    pub fn check(&self, index: &str) -> Result<(), rmcp::Error> {
+        check_not_system_index(index)?;
+        let operation = self.operation;
+        let refused = |reason: String| {
+            Err(rmcp::Error::invalid_params(
+                format!("Operation '{operation}' on index {index} is not allowed: {reason}."),
+                None,
+            ))
+        };
+        if let Some(pattern) = self.deny.iter().find(|p| matches_pattern(index, p)) {
+            return refused(format!("it matches the deny_{operation}_patterns entry '{pattern}'"));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| matches_pattern(index, p)) {
+            return refused(format!("it doesn't match any allow_{operation}_patterns entry"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_depend_on_all_parts() {
+        let token = confirmation_token(&["upsert_template", "logs", "{}"]);
+        assert_eq!(token, confirmation_token(&["upsert_template", "logs", "{}"]));
+        assert_ne!(token, confirmation_token(&["upsert_template", "logs", "{\"a\":1}"]));
+        assert_ne!(token, confirmation_token(&["upsert_template", "metrics", "{}"]));
+
+        assert!(check_confirmation_token(&token, &token, "preview").is_ok());
+        assert!(check_confirmation_token("foo", &token, "preview").is_err());
+    }
+
+    #[test]
+    fn index_policy() {
+        let open = IndexPolicy::new("delete", vec![], vec![]);
+        assert!(open.check("logs-2024.01.01").is_ok());
+        assert!(open.check(".security-7").is_err());
+
+        let policy = IndexPolicy::new(
+            "delete",
+            vec!["logs-*".to_string(), "tmp-*".to_string()],
+            vec!["logs-audit-*".to_string()],
+        );
+        assert!(policy.check("logs-2024.01.01").is_ok());
+        assert!(policy.check("tmp-reindex").is_ok());
+        assert!(policy.check("metrics-2024.01.01").is_err());
+        // Deny patterns take precedence over allow patterns
+        assert!(policy.check("logs-audit-2024.01.01").is_err());
+        assert!(policy.check(".logs-internal").is_err());
+    }
+}
@@ -0,0 +1,719 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Snapshots: repositories, snapshots and their progress, snapshot lifecycle management (SLM)
+//! policies, and creation and restore of snapshots.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_index_list, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::safety::{check_confirmation_token, confirmation_token};
+use crate::servers::elasticsearch::templates::matches_pattern;
+use elasticsearch::Elasticsearch;
+use elasticsearch::cat::CatIndicesParts;
+use elasticsearch::params::ExpandWildcards;
+use elasticsearch::slm::SlmGetLifecycleParts;
+use elasticsearch::snapshot::{
+    SnapshotCreateParts, SnapshotGetParts, SnapshotGetRepositoryParts, SnapshotRestoreParts, SnapshotStatusParts,
+};
+use regex::Regex;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashSet};
+
+/// Number of snapshots listed by default, most recent first
+const DEFAULT_SNAPSHOT_COUNT: usize = 20;
+
+/// Maximum number of shard failures listed for each snapshot
+const MAX_SNAPSHOT_FAILURES: usize = 5;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListSnapshotsParams {
+    /// Snapshot repository name
+    repository: String,
+    /// Snapshot name pattern (supports * wildcard, default: *)
+    name_pattern: Option<String>,
+    /// Maximum number of snapshots to list, most recent first (default: 20)
+    limit: Option<usize>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetSnapshotStatusParams {
+    /// Optional snapshot repository name. All running snapshots are returned if not provided.
+    repository: Option<String>,
+    /// Optional snapshot name, requires repository
+    snapshot: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetSlmPoliciesParams {
+    /// Optional SLM policy ID. All policies are returned if not provided.
+    policy_id: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CreateSnapshotParams {
+    /// Snapshot repository name
+    repository: String,
+    /// Name of the new snapshot
+    snapshot: String,
+    /// Optional comma-separated indices and data streams to include (supports * wildcard, default: all)
+    indices: Option<String>,
+    /// Include the cluster state: templates, persistent settings, ingest pipelines, etc. (default: false)
+    include_global_state: Option<bool>,
+    /// Token returned by a first call without it, after reviewing the snapshot
+    confirmation_token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct RestoreSnapshotParams {
+    /// Snapshot repository name
+    repository: String,
+    /// Snapshot name
+    snapshot: String,
+    /// Optional comma-separated indices to restore (supports * wildcard, default: all indices of the snapshot)
+    indices: Option<String>,
+    /// Regular expression matching the names of restored indices (e.g. '(.+)')
+    rename_pattern: String,
+    /// Replacement for the names of restored indices, which can refer to groups of rename_pattern (e.g. 'restored-$1')
+    rename_replacement: String,
+    /// Also restore the aliases of the indices (default: false, as they could conflict with aliases of live indices)
+    include_aliases: Option<bool>,
+    /// Token returned by a first call without it, after reviewing the indices to restore
+    confirmation_token: Option<String>,
+}
+
+#[tool_router(router = snapshot_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list snapshot repositories
+    #[tool(
+        description = "List snapshot repositories with their type and location.",
+        annotations(title = "List snapshot repositories", read_only_hint = true)
+    )]
+    async fn list_snapshot_repositories(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let response = es_client
+            .snapshot()
+            .get_repository(SnapshotGetRepositoryParts::None)
+            .send()
+            .await;
+        let repositories: BTreeMap<String, Repository> = read_json(response).await?;
+
+        let repositories = repositories
+            .into_iter()
+            .map(|(name, repository)| {
+                // Location settings depend on the repository type
+                let location = ["location", "bucket", "container", "url", "path"]
+                    .iter()
+                    .filter_map(|key| repository.settings.get(*key).and_then(|v| v.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("/");
+                json!({
+                    "name": name,
+                    "type": repository.type_,
+                    "location": location,
+                    "readonly": repository.settings.get("readonly"),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let data = pack_json_value(&repositories, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Found {} snapshot repositories.", repositories.len()), "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list snapshots
+    #[tool(
+        description = "List the snapshots of a repository, most recent first, with their state, duration, number of indices, shard failures and their reasons.",
+        annotations(title = "List snapshots", read_only_hint = true)
+    )]
+    async fn list_snapshots(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ListSnapshotsParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let pattern = params.name_pattern.as_deref().unwrap_or("*");
+        let mut snapshots = fetch_snapshots(&es_client, &params.repository, pattern).await?;
+        snapshots.sort_by(|a, b| b.start_time_in_millis.cmp(&a.start_time_in_millis));
+
+        let total = snapshots.len();
+        let failed = snapshots.iter().filter(|s| s.state != "SUCCESS" && s.state != "IN_PROGRESS").count();
+        let limit = params.limit.unwrap_or(DEFAULT_SNAPSHOT_COUNT).min(max_index_list());
+        let summaries = snapshots.iter().take(limit).map(snapshot_summary).collect::<Vec<_>>();
+
+        let mut message = format!("Found {total} snapshots in repository {}", params.repository);
+        if let Some(latest) = snapshots.first() {
+            message.push_str(&format!(", the latest is {} ({})", latest.snapshot, latest.state));
+        }
+        message.push_str(&format!(". {failed} snapshot(s) are not successful."));
+        if total > limit {
+            message.push_str(&format!(" Showing the {limit} most recent."));
+        }
+
+        let data = pack_json_value(&summaries, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get the status of snapshots
+    #[tool(
+        description = "Get the progress of running snapshots (or of a given snapshot): shards done, failed and remaining, and data processed.",
+        annotations(title = "Get snapshot status", read_only_hint = true)
+    )]
+    async fn get_snapshot_status(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<GetSnapshotStatusParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let snapshot;
+        let parts = match (&params.repository, &params.snapshot) {
+            (Some(repository), Some(name)) => {
+                snapshot = [name.as_str()];
+                SnapshotStatusParts::RepositorySnapshot(repository, &snapshot)
+            }
+            (Some(repository), None) => SnapshotStatusParts::Repository(repository),
+            (None, None) => SnapshotStatusParts::None,
+            (None, Some(_)) => {
+                return Err(rmcp::Error::invalid_params("A snapshot name requires a repository.", None));
+            }
+        };
+        let response = es_client.snapshot().status(parts).human(true).send().await;
+        let response: SnapshotStatusResponse = read_json(response).await?;
+
+        let statuses = response.snapshots.iter().map(snapshot_status).collect::<Vec<_>>();
+        let message = if statuses.is_empty() {
+            "No snapshot is running.".to_string()
+        } else {
+            format!("Status of {} snapshot(s).", statuses.len())
+        };
+
+        let data = pack_json_value(&statuses, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get SLM policies
+    #[tool(
+        description = "Get snapshot lifecycle management (SLM) policies: schedule, repository, retention, last success and failure, and next execution. Flags policies whose last execution failed.",
+        annotations(title = "Get SLM policies", read_only_hint = true)
+    )]
+    async fn get_slm_policies(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetSlmPoliciesParams { policy_id }): Parameters<GetSlmPoliciesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let ids;
+        let parts = match &policy_id {
+            Some(id) => {
+                ids = [id.as_str()];
+                SlmGetLifecycleParts::PolicyId(&ids)
+            }
+            None => SlmGetLifecycleParts::None,
+        };
+        let response = es_client.slm().get_lifecycle(parts).human(true).send().await;
+        let policies: BTreeMap<String, SlmPolicy> = read_json(response).await?;
+
+        let summaries = policies
+            .iter()
+            .map(|(id, policy)| slm_policy_summary(id, policy))
+            .collect::<Vec<_>>();
+        let failing = summaries.iter().filter(|p| p.failing).map(|p| p.id.as_str()).collect::<Vec<_>>();
+
+        let mut message = format!("Found {} SLM policies.", summaries.len());
+        if !failing.is_empty() {
+            message.push_str(&format!(" The last execution of {} failed.", failing.join(", ")));
+        }
+
+        let data = pack_json_value(&summaries, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: create a snapshot
+    ///
+    /// Two-step protocol: a first call checks the repository and returns a confirmation token,
+    /// that must be provided in a second call to actually start the snapshot.
+    #[tool(
+        description = "Start a snapshot of indices and data streams in a repository. A first call without confirmation_token checks the repository and returns a token: review it with the user, then call again with the token. Use get_snapshot_status to follow progress.",
+        annotations(title = "Create snapshot", read_only_hint = false, destructive_hint = false)
+    )]
+    async fn create_snapshot(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<CreateSnapshotParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        // Fails if the repository doesn't exist
+        let existing = fetch_snapshots(&es_client, &params.repository, &params.snapshot).await?;
+        if !existing.is_empty() {
+            return Err(rmcp::Error::invalid_params(
+                format!("Snapshot {} already exists in repository {}.", params.snapshot, params.repository),
+                None,
+            ));
+        }
+
+        let indices = params.indices.as_deref().unwrap_or("*");
+        let include_global_state = params.include_global_state.unwrap_or(false);
+        let token = confirmation_token(&[
+            "create_snapshot",
+            &params.repository,
+            &params.snapshot,
+            indices,
+            &include_global_state.to_string(),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "Snapshot {} of '{indices}' will be created in repository {}. Review it with the user, then call create_snapshot again with the confirmation_token.",
+                params.snapshot, params.repository
+            );
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "create_snapshot without confirmation_token")?;
+
+        let response = es_client
+            .snapshot()
+            .create(SnapshotCreateParts::RepositorySnapshot(&params.repository, &params.snapshot))
+            .body(json!({ "indices": indices, "include_global_state": include_global_state }))
+            .send()
+            .await;
+        let _: Value = read_json(response).await?;
+
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({
+                "message": format!("Snapshot {} started. Use get_snapshot_status to follow its progress.", params.snapshot)
+            }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: restore a snapshot
+    ///
+    /// Restored indices must be renamed to names that don't exist, so that a restore never
+    /// overwrites live indices. Two-step protocol as for other write tools.
+    #[tool(
+        description = "Restore indices from a snapshot under new names: rename_pattern and rename_replacement are required, and restoring onto existing indices, system indices or indices outside the allowed write patterns is rejected. A first call without confirmation_token lists the indices and their new names and returns a token: review it with the user, then call again with the token.",
+        annotations(title = "Restore snapshot", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn restore_snapshot(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<RestoreSnapshotParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let snapshot = fetch_snapshots(&es_client, &params.repository, &params.snapshot)
+            .await?
+            .into_iter()
+            .find(|s| s.snapshot == params.snapshot)
+            .ok_or_else(|| {
+                rmcp::Error::invalid_params(
+                    format!("Snapshot {} not found in repository {}.", params.snapshot, params.repository),
+                    None,
+                )
+            })?;
+        if snapshot.state != "SUCCESS" && snapshot.state != "PARTIAL" {
+            return Err(rmcp::Error::invalid_params(
+                format!("Snapshot {} can't be restored, its state is {}.", params.snapshot, snapshot.state),
+                None,
+            ));
+        }
+
+        let patterns = params.indices.as_deref().unwrap_or("*");
+        let indices = select_indices(&snapshot.indices, patterns);
+        if indices.is_empty() {
+            return Err(rmcp::Error::invalid_params(
+                format!("No index of snapshot {} matches '{patterns}'.", params.snapshot),
+                None,
+            ));
+        }
+
+        let existing = fetch_index_names(&es_client).await?;
+        let renames = rename_indices(&indices, &params.rename_pattern, &params.rename_replacement, &existing)?;
+        // Restored indices are new indices: no system index, and only where writes are allowed
+        for rename in &renames {
+            self.write_policy.check(&rename.restored_as)?;
+        }
+
+        let include_aliases = params.include_aliases.unwrap_or(false);
+        let token = confirmation_token(&[
+            "restore_snapshot",
+            &params.repository,
+            &params.snapshot,
+            &json!(renames).to_string(),
+            &include_aliases.to_string(),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "{} index(es) will be restored from snapshot {} under new names. Review them with the user, then call restore_snapshot again with the confirmation_token.",
+                renames.len(),
+                params.snapshot
+            );
+            let data = pack_json_value(&renames, max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "restore_snapshot without confirmation_token")?;
+
+        let response = es_client
+            .snapshot()
+            .restore(SnapshotRestoreParts::RepositorySnapshot(&params.repository, &params.snapshot))
+            .body(json!({
+                "indices": indices.join(","),
+                "rename_pattern": params.rename_pattern,
+                "rename_replacement": params.rename_replacement,
+                "include_aliases": include_aliases,
+                "include_global_state": false,
+            }))
+            .send()
+            .await;
+        let _: Value = read_json(response).await?;
+
+        let message = format!(
+            "Restore of {} index(es) from snapshot {} started. Use get_shards or get_cluster_health to follow the recovery.",
+            renames.len(),
+            params.snapshot
+        );
+        let data = pack_json_value(&renames, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+async fn fetch_snapshots(es_client: &Elasticsearch, repository: &str, pattern: &str) -> Result<Vec<Snapshot>, rmcp::Error> {
+    let response = es_client
+        .snapshot()
+        .get(SnapshotGetParts::RepositorySnapshot(repository, &[pattern]))
+        .ignore_unavailable(true)
+        .send()
+        .await;
+    let response: SnapshotsResponse = read_json(response).await?;
+    Ok(response.snapshots)
+}
+
+/// Names of all indices of the cluster, including closed and hidden ones
+async fn fetch_index_names(es_client: &Elasticsearch) -> Result<HashSet<String>, rmcp::Error> {
+    let response = es_client
+        .cat()
+        .indices(CatIndicesParts::None)
+        .expand_wildcards(&[ExpandWildcards::All])
+        .h(&["index"])
+        .format("json")
+        .send()
+        .await;
+    let rows: Vec<Value> = read_json(response).await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| row["index"].as_str().map(|s| s.to_string()))
+        .collect())
+}
+
+/// Indices of a snapshot matching comma-separated patterns, sorted by name
+fn select_indices(indices: &[String], patterns: &str) -> Vec<String> {
+    let patterns = patterns.split(',').map(|p| p.trim()).collect::<Vec<_>>();
+    let mut selected = indices
+        .iter()
+        .filter(|index| patterns.iter().any(|p| matches_pattern(index, p)))
+        .cloned()
+        .collect::<Vec<_>>();
+    selected.sort();
+    selected
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Rename {
+    index: String,
+    restored_as: String,
+}
+
+/// Compute the names of restored indices, rejecting renames that would restore onto an existing index
+fn rename_indices(
+    indices: &[String],
+    rename_pattern: &str,
+    rename_replacement: &str,
+    existing: &HashSet<String>,
+) -> Result<Vec<Rename>, rmcp::Error> {
+    let pattern = Regex::new(rename_pattern)
+        .map_err(|e| rmcp::Error::invalid_params(format!("Invalid rename_pattern: {e}"), None))?;
+    // Elasticsearch uses Java replacements, where '$1' is always group 1, even if followed by letters
+    let replacement = Regex::new(r"\$(\d+)")
+        .map_err(|e| rmcp::Error::internal_error(e.to_string(), None))?
+        .replace_all(rename_replacement, "$${${1}}");
+
+    let mut renames = Vec::new();
+    let mut targets = HashSet::new();
+    for index in indices {
+        let restored_as = pattern.replace_all(index, replacement.as_ref()).to_string();
+        let problem = if restored_as == *index {
+            Some("isn't renamed by rename_pattern")
+        } else if existing.contains(&restored_as) {
+            Some("would be restored onto an existing index")
+        } else if !targets.insert(restored_as.clone()) {
+            Some("would be restored under the same name as another index")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            return Err(rmcp::Error::invalid_params(
+                format!("Index {index} {problem} ({restored_as}). Restoring never overwrites indices, adjust rename_pattern and rename_replacement."),
+                None,
+            ));
+        }
+        renames.push(Rename {
+            index: index.clone(),
+            restored_as,
+        });
+    }
+    Ok(renames)
+}
+
+fn snapshot_summary(snapshot: &Snapshot) -> Value {
+    let failures = snapshot
+        .failures
+        .iter()
+        .take(MAX_SNAPSHOT_FAILURES)
+        .map(|f| format!("{}[{}]: {}", f.index, f.shard_id, f.reason))
+        .collect::<Vec<_>>();
+    json!({
+        "snapshot": snapshot.snapshot,
+        "state": snapshot.state,
+        "start_time": snapshot.start_time,
+        "duration_seconds": snapshot.duration_in_millis.map(|d| d / 1000),
+        "indices": snapshot.indices.len(),
+        "data_streams": snapshot.data_streams.len(),
+        "shards": snapshot.shards,
+        "failures": failures,
+    })
+}
+
+fn snapshot_status(status: &SnapshotStatus) -> Value {
+    let shards = &status.shards_stats;
+    json!({
+        "snapshot": status.snapshot,
+        "repository": status.repository,
+        "state": status.state,
+        "shards_done": shards.done,
+        "shards_failed": shards.failed,
+        "shards_total": shards.total,
+        "progress_percent": (shards.done * 100).checked_div(shards.total),
+        "processed_size": status.stats.pointer("/processed/size"),
+        "total_size": status.stats.pointer("/total/size"),
+        "elapsed": status.stats.get("time"),
+    })
+}
+
+#[derive(Serialize)]
+struct SlmPolicySummary {
+    id: String,
+    schedule: Option<String>,
+    repository: Option<String>,
+    indices: Option<Value>,
+    retention: Option<Value>,
+    last_success: Option<String>,
+    last_failure: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_failure_details: Option<String>,
+    next_execution: Option<String>,
+    /// The last execution failed
+    failing: bool,
+}
+
+fn slm_policy_summary(id: &str, policy: &SlmPolicy) -> SlmPolicySummary {
+    let time = |execution: &Option<SlmExecution>| execution.as_ref().map(|e| e.time);
+    let failing = match (time(&policy.last_success), time(&policy.last_failure)) {
+        (Some(success), Some(failure)) => failure > success,
+        (None, Some(_)) => true,
+        _ => false,
+    };
+    let describe = |execution: &Option<SlmExecution>| {
+        execution
+            .as_ref()
+            .map(|e| format!("{} ({})", e.snapshot_name, e.time_string.as_deref().unwrap_or_default()))
+    };
+    SlmPolicySummary {
+        id: id.to_string(),
+        schedule: policy.policy["schedule"].as_str().map(|s| s.to_string()),
+        repository: policy.policy["repository"].as_str().map(|s| s.to_string()),
+        indices: policy.policy.pointer("/config/indices").cloned(),
+        retention: policy.policy.get("retention").cloned(),
+        last_success: describe(&policy.last_success),
+        last_failure: describe(&policy.last_failure),
+        last_failure_details: failing
+            .then(|| policy.last_failure.as_ref().and_then(|f| f.details.clone()))
+            .flatten(),
+        next_execution: policy.next_execution.clone(),
+        failing,
+    }
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct Repository {
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(default)]
+    settings: serde_json::Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct SnapshotsResponse {
+    #[serde(default)]
+    snapshots: Vec<Snapshot>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    snapshot: String,
+    state: String,
+    #[serde(default)]
+    indices: Vec<String>,
+    #[serde(default)]
+    data_streams: Vec<String>,
+    start_time: Option<String>,
+    #[serde(default)]
+    start_time_in_millis: u64,
+    duration_in_millis: Option<u64>,
+    #[serde(default)]
+    failures: Vec<SnapshotFailure>,
+    shards: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct SnapshotFailure {
+    index: String,
+    shard_id: u32,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+struct SnapshotStatusResponse {
+    #[serde(default)]
+    snapshots: Vec<SnapshotStatus>,
+}
+
+#[derive(Deserialize)]
+struct SnapshotStatus {
+    snapshot: String,
+    repository: String,
+    state: String,
+    shards_stats: ShardsStats,
+    #[serde(default)]
+    stats: Value,
+}
+
+#[derive(Deserialize)]
+struct ShardsStats {
+    done: u64,
+    failed: u64,
+    total: u64,
+}
+
+#[derive(Deserialize)]
+struct SlmPolicy {
+    policy: Value,
+    last_success: Option<SlmExecution>,
+    last_failure: Option<SlmExecution>,
+    next_execution: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SlmExecution {
+    snapshot_name: String,
+    time: u64,
+    time_string: Option<String>,
+    details: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_restored_indices() {
+        let indices = vec!["logs-1".to_string(), "logs-2".to_string()];
+        let existing = HashSet::from(["logs-1".to_string(), "restored-logs-2".to_string()]);
+
+        let renames = rename_indices(&indices, "(.+)", "restored_$1", &existing).unwrap();
+        assert_eq!(
+            renames,
+            vec![
+                Rename { index: "logs-1".to_string(), restored_as: "restored_logs-1".to_string() },
+                Rename { index: "logs-2".to_string(), restored_as: "restored_logs-2".to_string() },
+            ]
+        );
+
+        // Onto an existing index
+        assert!(rename_indices(&indices, "(.+)", "restored-$1", &existing).is_err());
+        // Not renamed
+        assert!(rename_indices(&indices, "metrics", "restored", &existing).is_err());
+        // Two indices with the same name
+        assert!(rename_indices(&indices, "logs-\\d", "restored", &existing).is_err());
+    }
+
+    #[test]
+    fn select_snapshot_indices() {
+        let indices = vec!["metrics".to_string(), "logs-2".to_string(), "logs-1".to_string()];
+        assert_eq!(select_indices(&indices, "logs-*"), vec!["logs-1", "logs-2"]);
+        assert_eq!(select_indices(&indices, "metrics, logs-2"), vec!["logs-2", "metrics"]);
+    }
+
+    #[test]
+    fn failing_slm_policy() {
+        let policy: SlmPolicy = serde_json::from_value(json!({
+            "policy": { "name": "<nightly-{now/d}>", "schedule": "0 30 1 * * ?", "repository": "backups" },
+            "last_success": { "snapshot_name": "nightly-2024.01.01", "time": 1000, "time_string": "2024-01-01T01:30:00Z" },
+            "last_failure": { "snapshot_name": "nightly-2024.01.02", "time": 2000, "details": "repository is readonly" },
+            "next_execution": "2024-01-03T01:30:00.000Z"
+        }))
+        .unwrap();
+
+        let summary = slm_policy_summary("nightly", &policy);
+        assert!(summary.failing);
+        assert_eq!(summary.repository.as_deref(), Some("backups"));
+        assert_eq!(summary.last_failure_details.as_deref(), Some("repository is readonly"));
+    }
+}
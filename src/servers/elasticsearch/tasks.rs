@@ -0,0 +1,441 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Task management: list, inspect and cancel long-running tasks (reindex, delete by query,
+//! expensive searches, etc.)
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_index_list, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::safety::{check_confirmation_token, confirmation_token};
+use elasticsearch::Elasticsearch;
+use elasticsearch::params::GroupBy;
+use elasticsearch::tasks::{TasksCancelParts, TasksGetParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Task descriptions (e.g. search requests) are truncated to this length in task lists
+const MAX_DESCRIPTION_CHARS: usize = 300;
+
+/// Action of the task listing tasks, which is excluded from the results
+const LIST_TASKS_ACTION: &str = "cluster:monitor/tasks/lists";
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct ListTasksParams {
+    /// Optional comma-separated actions to filter (supports * wildcard, e.g. '*search*', '*reindex', '*byquery')
+    actions: Option<String>,
+    /// Optional comma-separated node IDs or names to filter
+    nodes: Option<String>,
+    /// Only list tasks running for at least this number of seconds (default: 0)
+    min_running_seconds: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetTaskParams {
+    /// Task ID, as 'node_id:task_number'
+    task_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CancelTaskParams {
+    /// Task ID, as 'node_id:task_number'
+    task_id: String,
+    /// Token returned by a first call without it, after reviewing the task to cancel
+    confirmation_token: Option<String>,
+}
+
+#[tool_router(router = task_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: list tasks
+    ///
+    /// Tasks are grouped by parent: a reindex or a search fans out to a child task per shard,
+    /// which are summarized by action.
+    #[tool(
+        description = "List the tasks running in the cluster (searches, reindex, delete by query, etc.), longest running first, with child tasks grouped under their parent. Can filter by action, node and minimum running time.",
+        annotations(title = "List tasks", read_only_hint = true)
+    )]
+    async fn list_tasks(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<ListTasksParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let actions: Vec<&str> = params.actions.as_deref().map(|a| a.split(',').collect()).unwrap_or_default();
+        let nodes: Vec<&str> = params.nodes.as_deref().map(|n| n.split(',').collect()).unwrap_or_default();
+
+        let mut request = es_client.tasks().list().detailed(true).group_by(GroupBy::None);
+        if !actions.is_empty() {
+            request = request.actions(&actions);
+        }
+        if !nodes.is_empty() {
+            request = request.nodes(&nodes);
+        }
+        let response: TasksListResponse = read_json(request.send().await).await?;
+
+        let min_running_millis = params.min_running_seconds.unwrap_or(0).saturating_mul(1000);
+        let mut tasks = group_tasks(response.tasks, min_running_millis);
+
+        let total = tasks.len();
+        let max_list = max_index_list();
+        tasks.truncate(max_list);
+
+        let mut message = format!("Found {total} task(s).");
+        if total > max_list {
+            message.push_str(&format!(" Showing the {max_list} longest running, use filters to narrow down."));
+        }
+        if !response.node_failures.is_empty() {
+            message.push_str(&format!(" {} node(s) failed to report their tasks.", response.node_failures.len()));
+        }
+
+        let data = pack_json_value(&tasks, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: get task
+    #[tool(
+        description = "Get the details and progress of a task, or its result if it has completed (for tasks run with wait_for_completion=false).",
+        annotations(title = "Get task", read_only_hint = true)
+    )]
+    async fn get_task(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetTaskParams { task_id }): Parameters<GetTaskParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let task = fetch_task(&es_client, &task_id).await?;
+        let message = task_message(&task_id, &task);
+
+        let data = pack_json_value(&task, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: cancel task
+    ///
+    /// Two-step protocol: a first call describes the task and returns a confirmation token, that
+    /// must be provided in a second call to actually cancel it.
+    #[tool(
+        description = "Cancel a running task. A first call without confirmation_token returns the task details and a token: review them with the user, then call again with the token to cancel the task.",
+        annotations(title = "Cancel task", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn cancel_task(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<CancelTaskParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let task = fetch_task(&es_client, &params.task_id).await?;
+        if task.completed {
+            return Err(rmcp::Error::invalid_params(
+                format!("Task {} has already completed.", params.task_id),
+                None,
+            ));
+        }
+        if !task.task.cancellable {
+            return Err(rmcp::Error::invalid_params(
+                format!("Task {} ({}) is not cancellable.", params.task_id, task.task.action),
+                None,
+            ));
+        }
+
+        let token = confirmation_token(&[
+            "cancel_task",
+            &params.task_id,
+            &task.task.action,
+            &task.task.start_time_in_millis.to_string(),
+        ]);
+
+        let Some(provided) = params.confirmation_token else {
+            let message = format!(
+                "Task {} ({}) has been running for {}s. Review it with the user, then call cancel_task again with the confirmation_token to cancel it.",
+                params.task_id,
+                task.task.action,
+                task.task.running_time_in_nanos / 1_000_000_000
+            );
+            let data = pack_json_value(&task.task, max_response_chars())?;
+            return Ok(CallToolResult::success(vec![
+                Content::json(json!({ "message": message, "confirmation_token": token, "data": data }))?
+            ]));
+        };
+        check_confirmation_token(&provided, &token, "cancel_task without confirmation_token")?;
+
+        let response = es_client
+            .tasks()
+            .cancel(TasksCancelParts::TaskId(&params.task_id))
+            .send()
+            .await;
+        let response: TasksListResponse = read_json(response).await?;
+
+        if let Some(failure) = response.node_failures.first() {
+            return Err(rmcp::Error::internal_error(
+                format!("Could not cancel task {}: {failure}", params.task_id),
+                None,
+            ));
+        }
+
+        // Cancellation is asynchronous: tasks check for it at regular intervals
+        let message = format!(
+            "Cancellation of task {} ({}) requested. It may take a moment to stop, use get_task to check.",
+            params.task_id, task.task.action
+        );
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message }))?
+        ]))
+    }
+}
+
+/// Get a task, and its result if it has completed
+pub async fn fetch_task(es_client: &Elasticsearch, task_id: &str) -> Result<TaskResult, rmcp::Error> {
+    if !task_id.contains(':') {
+        return Err(rmcp::Error::invalid_params(
+            format!("Invalid task ID '{task_id}', expected 'node_id:task_number'."),
+            None,
+        ));
+    }
+    let response = es_client.tasks().get(TasksGetParts::TaskId(task_id)).send().await;
+    read_json(response).await
+}
+
+/// Documents processed and total documents to process, for reindex, update by query and delete
+/// by query tasks. Sliced tasks and retried conflicts can count more documents than the total, so
+/// the count is capped to the total.
+pub fn task_progress(task: &TaskResult) -> Option<(u64, u64)> {
+    let status = task.task.status.as_ref()?;
+    let total = status.get("total")?.as_u64().filter(|total| *total > 0)?;
+    let done = ["created", "updated", "deleted", "noops", "version_conflicts"]
+        .iter()
+        .map(|key| status[*key].as_u64().unwrap_or(0))
+        .sum::<u64>();
+    Some((done.min(total), total))
+}
+
+/// A one-line description of a task's state and progress
+pub fn task_message(task_id: &str, task: &TaskResult) -> String {
+    let progress = task_progress(task)
+        .map(|(done, total)| format!(", {done}/{total} documents processed ({}%)", (done * 100 / total).min(100)))
+        .unwrap_or_default();
+
+    if task.completed {
+        match &task.error {
+            Some(error) => format!(
+                "Task {task_id} ({}) failed: {}",
+                task.task.action,
+                error["reason"].as_str().unwrap_or("unknown error")
+            ),
+            None => format!("Task {task_id} ({}) has completed{progress}.", task.task.action),
+        }
+    } else {
+        format!(
+            "Task {task_id} ({}) is running for {}s{progress}.",
+            task.task.action,
+            task.task.running_time_in_nanos / 1_000_000_000
+        )
+    }
+}
+
+/// Group tasks under their parent task, and keep those running for at least `min_running_millis`,
+/// longest running first.
+fn group_tasks(tasks: Vec<TaskInfo>, min_running_millis: u64) -> Vec<TaskSummary> {
+    let ids = tasks.iter().map(|t| t.task_id()).collect::<HashSet<_>>();
+
+    let mut children = HashMap::<String, Vec<TaskInfo>>::new();
+    let mut parents = Vec::new();
+    for task in tasks {
+        if task.action.starts_with(LIST_TASKS_ACTION) {
+            continue;
+        }
+        match &task.parent_task_id {
+            Some(parent) if ids.contains(parent) => children.entry(parent.clone()).or_default().push(task),
+            _ => parents.push(task),
+        }
+    }
+
+    let mut result = parents
+        .into_iter()
+        .filter(|t| t.running_time_in_nanos / 1_000_000 >= min_running_millis)
+        .map(|task| {
+            let task_id = task.task_id();
+            let children = children.remove(&task_id).unwrap_or_default();
+            let mut child_actions = BTreeMap::<String, usize>::new();
+            for child in &children {
+                *child_actions.entry(child.action.clone()).or_default() += 1;
+            }
+            let mut description = task.description.unwrap_or_default();
+            if description.len() > MAX_DESCRIPTION_CHARS {
+                let mut end = MAX_DESCRIPTION_CHARS;
+                while !description.is_char_boundary(end) {
+                    end -= 1;
+                }
+                description.truncate(end);
+                description.push_str("...");
+            }
+            TaskSummary {
+                task_id,
+                action: task.action,
+                description,
+                running_time_ms: task.running_time_in_nanos / 1_000_000,
+                cancellable: task.cancellable,
+                cancelled: task.cancelled,
+                child_count: children.len(),
+                child_actions,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    result.sort_by(|a, b| b.running_time_ms.cmp(&a.running_time_ms));
+    result
+}
+
+//----- Responses
+
+#[derive(Deserialize)]
+struct TasksListResponse {
+    #[serde(default)]
+    tasks: Vec<TaskInfo>,
+    #[serde(default)]
+    node_failures: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub node: String,
+    pub id: u64,
+    pub action: String,
+    pub description: Option<String>,
+    pub start_time_in_millis: u64,
+    pub running_time_in_nanos: u64,
+    pub cancellable: bool,
+    #[serde(default)]
+    pub cancelled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_task_id: Option<String>,
+    /// Progress of reindex, update by query and delete by query tasks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Value>,
+}
+
+impl TaskInfo {
+    pub fn task_id(&self) -> String {
+        format!("{}:{}", self.node, self.id)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TaskResult {
+    pub completed: bool,
+    pub task: TaskInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct TaskSummary {
+    task_id: String,
+    action: String,
+    description: String,
+    running_time_ms: u64,
+    cancellable: bool,
+    cancelled: bool,
+    child_count: usize,
+    /// Number of child tasks per action
+    child_actions: BTreeMap<String, usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(node: &str, id: u64, action: &str, running_ms: u64, parent: Option<&str>) -> TaskInfo {
+        TaskInfo {
+            node: node.to_string(),
+            id,
+            action: action.to_string(),
+            description: None,
+            start_time_in_millis: 0,
+            running_time_in_nanos: running_ms * 1_000_000,
+            cancellable: true,
+            cancelled: false,
+            parent_task_id: parent.map(|p| p.to_string()),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn tasks_are_grouped_by_parent() {
+        let tasks = vec![
+            task("n1", 1, "indices:data/read/search", 120_000, None),
+            task("n1", 2, "indices:data/read/search[phase/query]", 119_000, Some("n1:1")),
+            task("n2", 3, "indices:data/read/search[phase/query]", 118_000, Some("n1:1")),
+            task("n2", 4, "indices:data/write/reindex", 5_000, None),
+            task("n1", 5, "cluster:monitor/tasks/lists", 1, None),
+            // Parent on a node that failed to report
+            task("n3", 6, "indices:data/read/search[phase/query]", 60_000, Some("n9:1")),
+        ];
+
+        let grouped = group_tasks(tasks, 10_000);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].task_id, "n1:1");
+        assert_eq!(grouped[0].child_count, 2);
+        assert_eq!(grouped[0].child_actions["indices:data/read/search[phase/query]"], 2);
+        assert_eq!(grouped[1].task_id, "n3:6");
+    }
+
+    #[test]
+    fn progress_message() {
+        let result: TaskResult = serde_json::from_value(json!({
+            "completed": false,
+            "task": {
+                "node": "n1", "id": 4, "action": "indices:data/write/reindex",
+                "start_time_in_millis": 0, "running_time_in_nanos": 65_000_000_000_u64, "cancellable": true,
+                "status": { "total": 1000, "created": 200, "updated": 50, "deleted": 0 }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            task_message("n1:4", &result),
+            "Task n1:4 (indices:data/write/reindex) is running for 65s, 250/1000 documents processed (25%)."
+        );
+
+        let result: TaskResult = serde_json::from_value(json!({
+            "completed": false,
+            "task": {
+                "node": "n1", "id": 5, "action": "indices:data/write/reindex",
+                "start_time_in_millis": 0, "running_time_in_nanos": 1_000_000_000_u64, "cancellable": true,
+                "status": { "total": 100, "created": 90, "version_conflicts": 30 }
+            }
+        }))
+        .unwrap();
+        assert_eq!(task_progress(&result), Some((100, 100)));
+        assert!(task_message("n1:5", &result).ends_with("100/100 documents processed (100%)."));
+    }
+}
@@ -0,0 +1,673 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Two-phase index template changes: `preview_template_change` diffs a proposed template against
+//! the current one and reports risks, and `upsert_template` applies it if the confirmation token matches
+//! and the safety limits pass.
+
+use crate::servers::elasticsearch::base_tools::{
+    EsBaseTools, max_response_chars, max_template_result_window, max_template_shards, min_template_replicas,
+    pack_json_value,
+};
+use crate::servers::elasticsearch::compat::{ClusterInfo, TemplateApi};
+use crate::servers::elasticsearch::read_json;
+use crate::servers::elasticsearch::safety::{check_confirmation_token, confirmation_token};
+use crate::servers::elasticsearch::templates::{
+    IndexTemplate, TemplateBody, TemplateDefinition, fetch_component_templates, fetch_composable_templates,
+    fetch_legacy_templates,
+};
+use elasticsearch::Elasticsearch;
+use elasticsearch::indices::{IndicesPutIndexTemplateParts, IndicesPutTemplateParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Elasticsearch's default `index.max_result_window`
+const DEFAULT_MAX_RESULT_WINDOW: u64 = 10_000;
+
+/// Maximum number of changes listed in a preview
+const MAX_PREVIEW_CHANGES: usize = 100;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct PreviewTemplateChangeParams {
+    /// Name of the index template to create or update
+    name: String,
+    /// Optional template type (legacy, composable). Defaults to the type of the existing template, or composable if the cluster supports it.
+    template_type: Option<TemplateApi>,
+    /// Complete template definition, as accepted by the `_index_template` (composable) or `_template` (legacy) API
+    template: Map<String, Value>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct UpsertTemplateParams {
+    /// Name of the index template to create or update
+    name: String,
+    /// Optional template type (legacy, composable). Defaults to the type of the existing template, or composable if the cluster supports it.
+    template_type: Option<TemplateApi>,
+    /// Complete template definition, identical to the one given to preview_template_change
+    template: Map<String, Value>,
+    /// Token returned by preview_template_change for this template
+    confirmation_token: String,
+    /// Apply the change even if it exceeds the safety limits (default: false)
+    force: Option<bool>,
+}
+
+#[tool_router(router = template_change_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: preview an index template change
+    ///
+    /// First phase of a template change: compares the proposed template with the current one and
+    /// reports risks. The returned token must be passed to `upsert_template` to apply the change.
+    #[tool(
+        description = "Preview the creation or update of an index template: lists the changes compared to the current template and the risks (mapping type changes, primary shard increase, no replicas, large max_result_window). Returns a confirmation_token required by upsert_template.",
+        annotations(title = "Preview index template change", read_only_hint = true)
+    )]
+    async fn preview_template_change(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<PreviewTemplateChangeParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let cluster = self.cached_cluster_info(&req_ctx);
+        let es_client = self.es_client.get(req_ctx);
+
+        let change = self
+            .prepare_template_change(&es_client, cluster, &params.name, params.template_type, &params.template)
+            .await?;
+
+        let blocking = change.risks.iter().filter(|r| r.blocking).count();
+        let mut message = format!(
+            "Template '{}' ({}) will be {}: {} change(s), {} risk(s) ({} blocking).",
+            params.name,
+            type_name(change.template_type),
+            if change.current.is_some() { "updated" } else { "created" },
+            change.changes.len(),
+            change.risks.len(),
+            blocking,
+        );
+        if blocking > 0 {
+            message.push_str(" Blocking risks exceed the safety limits: upsert_template will refuse the change unless 'force' is set.");
+        }
+        message.push_str(" Review the risks with the user, then call upsert_template with the confirmation_token.");
+
+        let mut changes = change.changes;
+        changes.truncate(MAX_PREVIEW_CHANGES);
+        let data = pack_json_value(
+            &json!({
+                "risks": change.risks,
+                "changes": changes,
+            }),
+            max_response_chars(),
+        )?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "confirmation_token": change.token, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: create or update an index template
+    ///
+    /// Second phase of a template change: only applies the template if it's identical to the
+    /// previewed one, the current template hasn't changed since the preview, and the safety limits
+    /// pass (unless `force` is set).
+    #[tool(
+        description = "Create or update an index template. Requires the confirmation_token returned by preview_template_change for the same template, and refuses changes exceeding the safety limits unless 'force' is set.",
+        annotations(title = "Create or update index template", read_only_hint = false, destructive_hint = true)
+    )]
+    async fn upsert_template(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<UpsertTemplateParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let cluster = self.cached_cluster_info(&req_ctx);
+        let es_client = self.es_client.get(req_ctx);
+
+        let change = self
+            .prepare_template_change(&es_client, cluster, &params.name, params.template_type, &params.template)
+            .await?;
+
+        check_confirmation_token(&params.confirmation_token, &change.token, "preview_template_change")?;
+
+        let blocking = change.risks.iter().filter(|r| r.blocking).collect::<Vec<_>>();
+        let force = params.force.unwrap_or(false);
+        if !blocking.is_empty() && !force {
+            return Err(rmcp::Error::invalid_params(
+                format!(
+                    "Template change refused by safety limits: {}",
+                    blocking.iter().map(|r| r.message.as_str()).collect::<Vec<_>>().join(" ")
+                ),
+                Some(json!({ "risks": blocking })),
+            ));
+        }
+
+        let response = match change.template_type {
+            TemplateApi::Legacy => {
+                es_client
+                    .indices()
+                    .put_template(IndicesPutTemplateParts::Name(&params.name))
+                    .body(params.template)
+                    .send()
+                    .await
+            }
+            TemplateApi::Composable => {
+                es_client
+                    .indices()
+                    .put_index_template(IndicesPutIndexTemplateParts::Name(&params.name))
+                    .body(params.template)
+                    .send()
+                    .await
+            }
+        };
+        let response: Value = read_json(response).await?;
+
+        let message = format!(
+            "Template '{}' ({}) {}.{}",
+            params.name,
+            type_name(change.template_type),
+            if change.current.is_some() { "updated" } else { "created" },
+            if blocking.is_empty() { "" } else { " Safety limits were overridden with 'force'." }
+        );
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": response }))?
+        ]))
+    }
+}
+
+impl EsBaseTools {
+    /// Compare a proposed template with the current one. This is the same for the preview and the
+    /// actual change, so that the confirmation token can be checked.
+    async fn prepare_template_change(
+        &self,
+        es_client: &Elasticsearch,
+        cluster: Option<ClusterInfo>,
+        name: &str,
+        template_type: Option<TemplateApi>,
+        template: &Map<String, Value>,
+    ) -> Result<TemplateChange, rmcp::Error> {
+        if name.is_empty() || name.contains(['*', ',']) {
+            return Err(rmcp::Error::invalid_params(
+                "Template name must be a single name, without wildcards.",
+                None,
+            ));
+        }
+
+        let composable_supported = cluster.is_some_and(|info| info.template_api() == TemplateApi::Composable);
+
+        // Current template of each type
+        let legacy = fetch_legacy_templates(es_client, name).await?.into_iter().next();
+        let composable = if composable_supported {
+            fetch_composable_templates(es_client, name).await?.into_iter().next()
+        } else {
+            None
+        };
+
+        let template_type = template_type.unwrap_or(if composable.is_some() {
+            TemplateApi::Composable
+        } else if legacy.is_some() {
+            TemplateApi::Legacy
+        } else if composable_supported {
+            TemplateApi::Composable
+        } else {
+            TemplateApi::Legacy
+        });
+
+        if template_type == TemplateApi::Composable && !composable_supported {
+            return Err(rmcp::Error::invalid_params(
+                "This cluster doesn't support composable templates, use template_type 'legacy'.",
+                None,
+            ));
+        }
+
+        let current = match template_type {
+            TemplateApi::Legacy => legacy,
+            TemplateApi::Composable => composable,
+        };
+        let mut proposed = parse_template(name, template_type, template)?;
+        proposed.template = normalize(proposed.template);
+
+        // Settings and mappings may come from component templates
+        let component_names = current
+            .iter()
+            .chain(std::iter::once(&proposed))
+            .flat_map(|t| t.composed_of.iter().flatten().map(|s| s.as_str()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let components = if component_names.is_empty() {
+            HashMap::new()
+        } else {
+            fetch_component_templates(es_client, &component_names).await?
+        };
+
+        let current_body = current.as_ref().map(|t| normalize(t.resolve(&components)));
+        let proposed_body = normalize(proposed.resolve(&components));
+        let risks = analyze_template_change(current_body.as_ref(), &proposed_body, &TemplateLimits::from_env());
+
+        let current_json = current.as_ref().map(normalized_json).unwrap_or(Value::Null);
+        let proposed_json = normalized_json(&proposed);
+        let mut changes = Vec::new();
+        json_diff(&current_json, &proposed_json, "", &mut changes);
+
+        let token = confirmation_token(&[
+            "upsert_template",
+            name,
+            type_name(template_type),
+            &proposed_json.to_string(),
+            &current_json.to_string(),
+        ]);
+
+        Ok(TemplateChange {
+            template_type,
+            current,
+            changes,
+            risks,
+            token,
+        })
+    }
+}
+
+fn type_name(template_type: TemplateApi) -> &'static str {
+    match template_type {
+        TemplateApi::Legacy => "legacy",
+        TemplateApi::Composable => "composable",
+    }
+}
+
+/// Parse a template definition as sent to the template APIs
+fn parse_template(
+    name: &str,
+    template_type: TemplateApi,
+    template: &Map<String, Value>,
+) -> Result<TemplateDefinition, rmcp::Error> {
+    let value = Value::Object(template.clone());
+    let result = match template_type {
+        TemplateApi::Composable => serde_json::from_value::<IndexTemplate>(value)
+            .map(|t| TemplateDefinition::from_composable(name.to_string(), t)),
+        TemplateApi::Legacy => serde_json::from_value::<TemplateDefinition>(value).map(|t| TemplateDefinition {
+            name: name.to_string(),
+            template_type: TemplateApi::Legacy,
+            ..t
+        }),
+    };
+    result.map_err(|e| {
+        rmcp::Error::invalid_params(format!("Invalid {} template: {e}", type_name(template_type)), None)
+    })
+}
+
+/// A template definition as JSON, with normalized settings, for comparison
+fn normalized_json(template: &TemplateDefinition) -> Value {
+    let mut value = serde_json::to_value(template).unwrap_or(Value::Null);
+    if let Some(settings) = value.get_mut("settings") {
+        *settings = normalize_settings(settings);
+    }
+    value
+}
+
+fn normalize(mut body: TemplateBody) -> TemplateBody {
+    body.settings = body.settings.as_ref().map(normalize_settings);
+    body
+}
+
+/// Normalize index settings to the nested form returned by Elasticsearch: dotted keys are expanded,
+/// settings are placed under `index`, and numbers and booleans are converted to strings.
+pub fn normalize_settings(settings: &Value) -> Value {
+    fn insert(target: &mut Map<String, Value>, path: &[&str], value: &Value) {
+        let (first, rest) = path.split_first().expect("non-empty path");
+        if rest.is_empty() {
+            match value {
+                Value::Object(obj) => {
+                    for (key, value) in obj {
+                        let sub_path = key.split('.').collect::<Vec<_>>();
+                        let entry = target.entry(first.to_string()).or_insert_with(|| json!({}));
+                        if let Value::Object(entry) = entry {
+                            insert(entry, &sub_path, value);
+                        }
+                    }
+                }
+                Value::Number(n) => {
+                    target.insert(first.to_string(), Value::String(n.to_string()));
+                }
+                Value::Bool(b) => {
+                    target.insert(first.to_string(), Value::String(b.to_string()));
+                }
+                other => {
+                    target.insert(first.to_string(), other.clone());
+                }
+            }
+        } else {
+            let entry = target.entry(first.to_string()).or_insert_with(|| json!({}));
+            if let Value::Object(entry) = entry {
+                insert(entry, rest, value);
+            }
+        }
+    }
+
+    let Value::Object(settings) = settings else {
+        return settings.clone();
+    };
+
+    let mut result = Map::new();
+    for (key, value) in settings {
+        let mut path = key.split('.').collect::<Vec<_>>();
+        if path[0] != "index" {
+            path.insert(0, "index");
+        }
+        insert(&mut result, &path, value);
+    }
+    Value::Object(result)
+}
+
+/// Compute leaf-level differences between two JSON values, as `{path, current, proposed}` objects.
+/// Keys missing from `proposed` have a `removed` flag, to tell them apart from keys set to `null`.
+pub fn json_diff(current: &Value, proposed: &Value, path: &str, result: &mut Vec<Value>) {
+    match (current, proposed) {
+        (Value::Object(c), Value::Object(p)) => {
+            let keys = c.keys().chain(p.keys()).collect::<BTreeSet<_>>();
+            for key in keys {
+                let sub_path = if path.is_empty() { key.clone() } else { format!("{path}.{key}") };
+                match (c.get(key), p.get(key)) {
+                    (Some(c), Some(p)) => json_diff(c, p, &sub_path, result),
+                    (Some(c), None) => {
+                        result.push(json!({ "path": sub_path, "current": c, "proposed": null, "removed": true }))
+                    }
+                    (None, Some(p)) => result.push(json!({ "path": sub_path, "current": null, "proposed": p })),
+                    (None, None) => {}
+                }
+            }
+        }
+        (c, p) if c != p => result.push(json!({ "path": path, "current": c, "proposed": p })),
+        _ => {}
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Risk analysis
+
+/// A proposed template change, compared to the current template
+struct TemplateChange {
+    template_type: TemplateApi,
+    current: Option<TemplateDefinition>,
+    changes: Vec<Value>,
+    risks: Vec<Risk>,
+    token: String,
+}
+
+#[derive(Serialize)]
+pub struct Risk {
+    /// Blocking risks exceed the safety limits and prevent the change unless `force` is set
+    pub blocking: bool,
+    pub kind: &'static str,
+    pub message: String,
+}
+
+pub struct TemplateLimits {
+    pub max_shards: u64,
+    pub min_replicas: u64,
+    pub max_result_window: u64,
+}
+
+impl TemplateLimits {
+    pub fn from_env() -> Self {
+        TemplateLimits {
+            max_shards: max_template_shards(),
+            min_replicas: min_template_replicas(),
+            max_result_window: max_template_result_window(),
+        }
+    }
+}
+
+/// Analyze the risks of replacing a template (resolved with its component templates, and with
+/// normalized settings) with a new one.
+pub fn analyze_template_change(
+    current: Option<&TemplateBody>,
+    proposed: &TemplateBody,
+    limits: &TemplateLimits,
+) -> Vec<Risk> {
+    let mut risks = Vec::new();
+    let current_settings = current.and_then(|c| c.settings.as_ref());
+    let proposed_settings = proposed.settings.as_ref();
+
+    // Mapping changes: existing indices keep the old mapping, so the same field will have different
+    // types across indices, breaking aggregations and sorting on index patterns
+    let current_fields = field_types(current.and_then(|c| c.mappings.as_ref()));
+    let proposed_fields = field_types(proposed.mappings.as_ref());
+    for (field, current_type) in &current_fields {
+        match proposed_fields.get(field) {
+            Some(proposed_type) if proposed_type != current_type => risks.push(Risk {
+                blocking: false,
+                kind: "mapping_type_change",
+                message: format!(
+                    "Field '{field}' changes from '{current_type}' to '{proposed_type}': new indices will conflict with existing ones, and documents may be rejected."
+                ),
+            }),
+            None => risks.push(Risk {
+                blocking: false,
+                kind: "mapping_field_removed",
+                message: format!(
+                    "Field '{field}' ({current_type}) is no longer mapped: new indices will map it dynamically, possibly with a different type."
+                ),
+            }),
+            _ => {}
+        }
+    }
+
+    // Primary shards
+    let current_shards = index_setting(current_settings, "number_of_shards");
+    if let Some(shards) = index_setting(proposed_settings, "number_of_shards") {
+        if shards > limits.max_shards {
+            risks.push(Risk {
+                blocking: true,
+                kind: "max_shards",
+                message: format!(
+                    "number_of_shards is {shards}, above the limit of {}: this puts memory pressure on nodes.",
+                    limits.max_shards
+                ),
+            });
+        } else if let Some(current_shards) = current_shards
+            && shards >= current_shards.max(1) * 2
+        {
+            risks.push(Risk {
+                blocking: false,
+                kind: "shard_increase",
+                message: format!("Primary shards jump from {current_shards} to {shards} for new indices."),
+            });
+        }
+    }
+
+    // Replicas
+    if let Some(replicas) = index_setting(proposed_settings, "number_of_replicas") {
+        if replicas < limits.min_replicas {
+            risks.push(Risk {
+                blocking: true,
+                kind: "min_replicas",
+                message: format!(
+                    "number_of_replicas is {replicas}, below the minimum of {}: a node failure may lose data.",
+                    limits.min_replicas
+                ),
+            });
+        } else if replicas == 0 {
+            risks.push(Risk {
+                blocking: false,
+                kind: "no_replicas",
+                message: "number_of_replicas is 0: a node failure may lose data.".to_string(),
+            });
+        }
+    }
+
+    // Deep pagination
+    if let Some(window) = index_setting(proposed_settings, "max_result_window") {
+        let current_window = index_setting(current_settings, "max_result_window").unwrap_or(DEFAULT_MAX_RESULT_WINDOW);
+        if window > limits.max_result_window {
+            risks.push(Risk {
+                blocking: true,
+                kind: "max_result_window",
+                message: format!(
+                    "max_result_window is {window}, above the limit of {}: deep pagination can exhaust the heap.",
+                    limits.max_result_window
+                ),
+            });
+        } else if window > DEFAULT_MAX_RESULT_WINDOW && window > current_window {
+            risks.push(Risk {
+                blocking: false,
+                kind: "large_result_window",
+                message: format!(
+                    "max_result_window increases to {window}: deep pagination increases heap pressure, consider search_after."
+                ),
+            });
+        }
+    }
+
+    risks
+}
+
+/// Get a numeric setting from normalized index settings
+fn index_setting(settings: Option<&Value>, name: &str) -> Option<u64> {
+    match settings?.get("index")?.get(name)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Map of dotted field paths to their mapping type (`object` for objects without a type)
+fn field_types(mappings: Option<&Value>) -> BTreeMap<String, String> {
+    fn collect(properties: &Value, prefix: &str, result: &mut BTreeMap<String, String>) {
+        let Some(properties) = properties.as_object() else {
+            return;
+        };
+        for (name, field) in properties {
+            let path = if prefix.is_empty() { name.clone() } else { format!("{prefix}.{name}") };
+            let field_type = field.get("type").and_then(|t| t.as_str()).unwrap_or("object");
+            result.insert(path.clone(), field_type.to_string());
+            if let Some(sub_properties) = field.get("properties") {
+                collect(sub_properties, &path, result);
+            }
+        }
+    }
+
+    let mut result = BTreeMap::new();
+    if let Some(properties) = mappings.and_then(|m| m.get("properties")) {
+        collect(properties, "", &mut result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> TemplateLimits {
+        TemplateLimits {
+            max_shards: 50,
+            min_replicas: 1,
+            max_result_window: 10_000_000,
+        }
+    }
+
+    fn body(settings: Value, mappings: Value) -> TemplateBody {
+        TemplateBody {
+            settings: Some(normalize_settings(&settings)),
+            mappings: Some(mappings),
+            aliases: None,
+        }
+    }
+
+    fn kinds(risks: &[Risk]) -> Vec<(&str, bool)> {
+        risks.iter().map(|r| (r.kind, r.blocking)).collect()
+    }
+
+    #[test]
+    fn settings_are_normalized() {
+        assert_eq!(
+            normalize_settings(&json!({ "index.number_of_shards": 3, "number_of_replicas": "1", "index": { "refresh_interval": "5s" } })),
+            json!({ "index": { "number_of_shards": "3", "number_of_replicas": "1", "refresh_interval": "5s" } })
+        );
+    }
+
+    #[test]
+    fn risky_changes() {
+        let current = body(
+            json!({ "index": { "number_of_shards": "5", "number_of_replicas": "1" } }),
+            json!({ "properties": { "swTimesA": { "type": "long" }, "host": { "properties": { "name": { "type": "keyword" } } } } }),
+        );
+        let proposed = body(
+            json!({ "number_of_shards": 60, "number_of_replicas": 0, "max_result_window": 50_000 }),
+            json!({ "properties": { "swTimesA": { "type": "keyword" } } }),
+        );
+
+        let risks = analyze_template_change(Some(&current), &proposed, &limits());
+        assert_eq!(
+            kinds(&risks),
+            vec![
+                ("mapping_field_removed", false),
+                ("mapping_field_removed", false),
+                ("mapping_type_change", false),
+                ("max_shards", true),
+                ("min_replicas", true),
+                ("large_result_window", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn safe_changes() {
+        let current = body(
+            json!({ "index": { "number_of_shards": "1", "number_of_replicas": "1" } }),
+            json!({ "properties": { "message": { "type": "text" } } }),
+        );
+        let proposed = body(
+            json!({ "index": { "number_of_shards": "1", "number_of_replicas": "2" } }),
+            json!({ "properties": { "message": { "type": "text" }, "level": { "type": "keyword" } } }),
+        );
+        assert!(analyze_template_change(Some(&current), &proposed, &limits()).is_empty());
+        assert!(analyze_template_change(None, &proposed, &limits()).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_leaf_changes() {
+        let mut changes = Vec::new();
+        json_diff(
+            &json!({ "priority": 100, "settings": { "index": { "number_of_shards": "1" } } }),
+            &json!({ "priority": 200, "settings": { "index": { "number_of_shards": "1", "codec": "best_compression" } } }),
+            "",
+            &mut changes,
+        );
+        assert_eq!(
+            changes,
+            vec![
+                json!({ "path": "priority", "current": 100, "proposed": 200 }),
+                json!({ "path": "settings.index.codec", "current": null, "proposed": "best_compression" }),
+            ]
+        );
+
+        let mut changes = Vec::new();
+        json_diff(&json!({ "a": 1, "b": 2 }), &json!({ "a": null, "c": null }), "", &mut changes);
+        assert_eq!(
+            changes,
+            vec![
+                json!({ "path": "a", "current": 1, "proposed": null }),
+                json!({ "path": "b", "current": 2, "proposed": null, "removed": true }),
+                json!({ "path": "c", "current": null, "proposed": null }),
+            ]
+        );
+    }
+}
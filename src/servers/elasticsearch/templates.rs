@@ -0,0 +1,742 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Index template tools: legacy (`_template`), composable (`_index_template`) and component
+//! (`_component_template`) templates, and resolution of the templates that apply to an index.
+
+use crate::servers::elasticsearch::base_tools::{EsBaseTools, max_response_chars, pack_json_value};
+use crate::servers::elasticsearch::compat::TemplateApi;
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::Elasticsearch;
+use elasticsearch::cluster::ClusterGetComponentTemplateParts;
+use elasticsearch::http::response::Response;
+use elasticsearch::http::StatusCode;
+use elasticsearch::indices::{IndicesGetIndexTemplateParts, IndicesGetTemplateParts, IndicesSimulateIndexTemplateParts};
+use regex::Regex;
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetTemplatesParams {
+    /// Optional template name filter (supports * wildcard)
+    name: Option<String>,
+    /// Optional index name to find which template(s) match it
+    matching_index: Option<String>,
+    /// Optional template type (legacy, composable). Both types are returned by default.
+    template_type: Option<TemplateApi>,
+    /// Include the settings, mappings and aliases of the component templates that composable templates are built from (default: false)
+    resolve_components: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct GetComponentTemplatesParams {
+    /// Optional component template name filter (supports * wildcard)
+    name: Option<String>,
+}
+
+#[tool_router(router = template_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: Get index templates
+    ///
+    /// Comprehensive template query tool that supports:
+    /// - Legacy (`_template`) and composable (`_index_template`) templates
+    /// - Filtering by template name (with wildcard support)
+    /// - Finding which template(s) apply to a specific index name
+    ///
+    /// # Arguments
+    /// * `name` - Optional template name filter (supports * wildcard)
+    /// * `matching_index` - Optional index name to find matching templates
+    /// * `template_type` - Optional template type (legacy or composable)
+    /// * `resolve_components` - Merge component templates into composable templates
+    ///
+    /// # Returns
+    /// Template definitions, or for `matching_index` the matching templates, the ones that apply
+    /// and the resulting settings, mappings and aliases.
+    #[tool(
+        description = "Get legacy and composable index templates with optional filtering by name or matching index. Supports wildcard patterns and can determine which template applies to a specific index, and the resulting settings and mappings.",
+        annotations(title = "Get index templates", read_only_hint = true)
+    )]
+    async fn get_templates(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<GetTemplatesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let cluster = self.cached_cluster_info(&req_ctx);
+        let es_client = self.es_client.get(req_ctx);
+
+        // Determine the template name pattern to query
+        let template_name = params.name.as_deref().unwrap_or("*");
+
+        let mut templates = Vec::new();
+        if params.template_type != Some(TemplateApi::Composable) {
+            templates.extend(fetch_legacy_templates(&es_client, template_name).await?);
+        }
+        if params.template_type != Some(TemplateApi::Legacy) {
+            match cluster.as_ref().map(|info| info.template_api()) {
+                Some(TemplateApi::Composable) => {
+                    templates.extend(fetch_composable_templates(&es_client, template_name).await?)
+                }
+                Some(TemplateApi::Legacy) => {}
+                // Unknown version: probe the composable template API, that older clusters don't have
+                None => match fetch_composable_templates(&es_client, template_name).await {
+                    Ok(composable) => templates.extend(composable),
+                    Err(e) => tracing::warn!("Composable templates are not available: {}", e.message),
+                },
+            }
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // If matching_index is specified, find the templates that apply to it. The simulate API is
+        // also tried if the version is unknown, with a fallback to local resolution.
+        if let Some(index_name) = params.matching_index {
+            let simulate = cluster.as_ref().is_none_or(|info| info.supports_simulate_index());
+            return templates_for_index(&es_client, &templates, &index_name, simulate).await;
+        }
+
+        let mut missing_components = Vec::new();
+        if params.resolve_components.unwrap_or(false) {
+            let names = templates
+                .iter()
+                .flat_map(|t| t.composed_of.iter().flatten().map(|s| s.as_str()))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            if !names.is_empty() {
+                let components = fetch_component_templates(&es_client, &names).await?;
+                for template in templates.iter_mut() {
+                    template.template = template.resolve(&components);
+                }
+                missing_components = missing_component_names(&names, &components);
+            }
+        }
+
+        // Return all templates (or filtered by name)
+        let composable_count = templates
+            .iter()
+            .filter(|t| t.template_type == TemplateApi::Composable)
+            .count();
+        let mut message = format!(
+            "Found {} template(s) ({} legacy, {} composable).",
+            templates.len(),
+            templates.len() - composable_count,
+            composable_count
+        );
+        if !missing_components.is_empty() {
+            message.push_str(&format!(
+                " Component template(s) not found, left out of the resolution: {}.",
+                missing_components.join(", ")
+            ));
+        }
+        let data = pack_json_value(&templates_by_name(&templates), max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+
+    //---------------------------------------------------------------------------------------------
+    /// Tool: Get component templates
+    #[tool(
+        description = "Get component templates, the building blocks of composable index templates.",
+        annotations(title = "Get component templates", read_only_hint = true)
+    )]
+    async fn get_component_templates(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(GetComponentTemplatesParams { name }): Parameters<GetComponentTemplatesParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let name = name.as_deref().unwrap_or("*");
+        let components = fetch_component_templates(&es_client, &[name]).await?;
+
+        let mut components = components.into_iter().collect::<Vec<_>>();
+        components.sort_by(|a, b| a.0.cmp(&b.0));
+        let components = components
+            .into_iter()
+            .map(|(name, template)| json!({ "name": name, "template": template }))
+            .collect::<Vec<_>>();
+
+        let data = pack_json_value(&components, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("Found {} component template(s).", components.len()), "data": data }))?
+        ]))
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Template resolution
+
+/// Find the templates that apply to an index and the resulting settings, mappings and aliases.
+/// The simulate index API is used if available, as it's the authoritative answer.
+async fn templates_for_index(
+    es_client: &Elasticsearch,
+    templates: &[TemplateDefinition],
+    index_name: &str,
+    simulate: bool,
+) -> Result<CallToolResult, rmcp::Error> {
+    let matching = TemplateMatch::new(templates, index_name);
+    if matching.is_empty() {
+        return Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": format!("No templates match index '{}'.", index_name), "data": {} }))?
+        ]));
+    }
+
+    let applied = matching.applied();
+    let message = match matching.composable.first() {
+        Some(winner) => format!(
+            "Composable template '{}' (priority {}) applies to index '{}'. {} other composable and {} legacy template(s) also match but are ignored.",
+            winner.name,
+            winner.precedence(),
+            index_name,
+            matching.composable.len() - 1,
+            matching.legacy.len()
+        ),
+        None => format!(
+            "{} legacy template(s) apply to index '{}', merged by increasing order (the last one wins on conflicts).",
+            matching.legacy.len(),
+            index_name
+        ),
+    };
+
+    let simulated = if simulate { simulate_index(es_client, index_name).await } else { None };
+    let mut missing_components = Vec::new();
+    let (effective, source) = match simulated {
+        Some(template) => (template, "_simulate_index"),
+        None => {
+            let names = applied
+                .iter()
+                .flat_map(|t| t.composed_of.iter().flatten().map(|s| s.as_str()))
+                .collect::<Vec<_>>();
+            let components = if names.is_empty() {
+                HashMap::new()
+            } else {
+                fetch_component_templates(es_client, &names).await?
+            };
+            missing_components = missing_component_names(&names, &components);
+            let mut effective = TemplateBody::default();
+            for template in &applied {
+                effective.merge(&template.resolve(&components));
+            }
+            (effective, "resolved locally")
+        }
+    };
+
+    let summary = matching
+        .composable
+        .iter()
+        .chain(matching.legacy.iter())
+        .map(|t| {
+            json!({
+                "name": t.name,
+                "type": t.template_type,
+                "precedence": t.precedence(),
+                "index_patterns": t.index_patterns,
+                "applied": applied.iter().any(|a| std::ptr::eq(*a, *t)),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut result = json!({
+        "applied": applied.iter().map(|t| &t.name).collect::<Vec<_>>(),
+        "matching": summary,
+        "effective": effective,
+        "effective_source": source,
+    });
+    if !missing_components.is_empty() {
+        result["missing_components"] = json!(missing_components);
+    }
+    let data = pack_json_value(&result, max_response_chars())?;
+    Ok(CallToolResult::success(vec![
+        Content::json(json!({ "message": message, "data": data }))?
+    ]))
+}
+
+/// Templates keyed by name. A legacy and a composable template can have the same name, in which
+/// case the legacy one is keyed as `<name> (legacy)`.
+fn templates_by_name(templates: &[TemplateDefinition]) -> BTreeMap<String, &TemplateDefinition> {
+    let composable = templates
+        .iter()
+        .filter(|t| t.template_type == TemplateApi::Composable)
+        .map(|t| t.name.as_str())
+        .collect::<BTreeSet<_>>();
+    templates
+        .iter()
+        .map(|t| {
+            let key = if t.template_type == TemplateApi::Legacy && composable.contains(t.name.as_str()) {
+                format!("{} (legacy)", t.name)
+            } else {
+                t.name.clone()
+            };
+            (key, t)
+        })
+        .collect()
+}
+
+/// Names of requested component templates that don't exist
+fn missing_component_names(names: &[&str], components: &HashMap<String, TemplateBody>) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| !components.contains_key(**name))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Templates matching an index name, following Elasticsearch's precedence rules
+pub struct TemplateMatch<'a> {
+    /// Matching composable templates, highest priority first. Only the first one is applied.
+    pub composable: Vec<&'a TemplateDefinition>,
+    /// Matching legacy templates, highest order first. They are all applied if no composable template matches.
+    pub legacy: Vec<&'a TemplateDefinition>,
+}
+
+impl<'a> TemplateMatch<'a> {
+    pub fn new(templates: &'a [TemplateDefinition], index_name: &str) -> Self {
+        let (mut composable, mut legacy): (Vec<_>, Vec<_>) = templates
+            .iter()
+            .filter(|t| t.index_patterns.iter().any(|p| matches_pattern(index_name, p)))
+            .partition(|t| t.template_type == TemplateApi::Composable);
+
+        // Sort by order or priority (descending - higher value = higher priority)
+        composable.sort_by_key(|t| std::cmp::Reverse(t.precedence()));
+        legacy.sort_by_key(|t| std::cmp::Reverse(t.precedence()));
+
+        TemplateMatch { composable, legacy }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.composable.is_empty() && self.legacy.is_empty()
+    }
+
+    /// Templates applied to the index, in the order they're merged (increasing precedence)
+    pub fn applied(&self) -> Vec<&'a TemplateDefinition> {
+        match self.composable.first() {
+            Some(winner) => vec![*winner],
+            None => self.legacy.iter().rev().copied().collect(),
+        }
+    }
+}
+
+/// Check if an index name matches a pattern (supports * wildcard)
+pub fn matches_pattern(index_name: &str, pattern: &str) -> bool {
+    // Convert ES wildcard pattern to regex
+    // Escape special regex chars except *
+    let escaped = regex::escape(pattern).replace(r"\*", ".*");
+    let regex_pattern = format!("^{}$", escaped);
+
+    if let Ok(re) = Regex::new(&regex_pattern) {
+        re.is_match(index_name)
+    } else {
+        false
+    }
+}
+
+/// Deep-merge `source` into `target`, values from `source` taking precedence
+pub fn merge_json(target: &mut Value, source: &Value) {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => {
+            for (key, value) in source {
+                match target.get_mut(key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, source) => *target = source.clone(),
+    }
+}
+
+fn merge_option(target: &mut Option<Value>, source: &Option<Value>) {
+    if let Some(source) = source {
+        match target {
+            Some(target) => merge_json(target, source),
+            None => *target = Some(source.clone()),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Elasticsearch requests
+
+/// Read a template response, considering a missing template as an empty result since
+/// an exact name will only exist in one of the template kinds.
+async fn read_templates<T: DeserializeOwned + Default>(
+    response: Result<Response, elasticsearch::Error>,
+) -> Result<T, rmcp::Error> {
+    if let Ok(response) = &response
+        && response.status_code() == StatusCode::NOT_FOUND
+    {
+        return Ok(T::default());
+    }
+    read_json(response).await
+}
+
+pub async fn fetch_legacy_templates(
+    es_client: &Elasticsearch,
+    name: &str,
+) -> Result<Vec<TemplateDefinition>, rmcp::Error> {
+    let response = es_client
+        .indices()
+        .get_template(IndicesGetTemplateParts::Name(&[name]))
+        .send()
+        .await;
+    let templates: HashMap<String, TemplateDefinition> = read_templates(response).await?;
+
+    Ok(templates
+        .into_iter()
+        .map(|(name, template)| TemplateDefinition {
+            name,
+            template_type: TemplateApi::Legacy,
+            ..template
+        })
+        .collect())
+}
+
+pub async fn fetch_composable_templates(
+    es_client: &Elasticsearch,
+    name: &str,
+) -> Result<Vec<TemplateDefinition>, rmcp::Error> {
+    let response = es_client
+        .indices()
+        .get_index_template(IndicesGetIndexTemplateParts::Name(name))
+        .send()
+        .await;
+    let response: IndexTemplatesResponse = read_templates(response).await?;
+
+    Ok(response
+        .index_templates
+        .into_iter()
+        .map(|t| TemplateDefinition::from_composable(t.name, t.index_template))
+        .collect())
+}
+
+/// Component templates by name. Missing ones are left out: as the API fails if any of the
+/// requested names is missing, they're then fetched one by one.
+pub async fn fetch_component_templates(
+    es_client: &Elasticsearch,
+    names: &[&str],
+) -> Result<HashMap<String, TemplateBody>, rmcp::Error> {
+    let response = send_get_component_templates(es_client, names).await;
+    if names.len() > 1
+        && let Ok(response) = &response
+        && response.status_code() == StatusCode::NOT_FOUND
+    {
+        let mut components = HashMap::new();
+        for name in names {
+            let response = send_get_component_templates(es_client, &[*name]).await;
+            let response: ComponentTemplatesResponse = read_templates(response).await?;
+            components.extend(response.into_map());
+        }
+        return Ok(components);
+    }
+    let response: ComponentTemplatesResponse = read_templates(response).await?;
+    Ok(response.into_map())
+}
+
+async fn send_get_component_templates(
+    es_client: &Elasticsearch,
+    names: &[&str],
+) -> Result<Response, elasticsearch::Error> {
+    es_client
+        .cluster()
+        .get_component_template(ClusterGetComponentTemplateParts::Name(names))
+        .send()
+        .await
+}
+
+/// Simulate the creation of an index to get the settings, mappings and aliases it would have.
+/// Returns `None` if the API failed, so that the caller can fall back to local resolution.
+async fn simulate_index(es_client: &Elasticsearch, index_name: &str) -> Option<TemplateBody> {
+    // The typed API encodes the index name (e.g. date math) in the path
+    let response = es_client
+        .indices()
+        .simulate_index_template(IndicesSimulateIndexTemplateParts::Name(index_name))
+        .send()
+        .await;
+
+    match read_json::<SimulateIndexResponse>(response).await {
+        Ok(response) => Some(response.template),
+        Err(e) => {
+            tracing::warn!("Simulate index failed, resolving templates locally: {}", e.message);
+            None
+        }
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Type definitions
+
+/// A legacy or composable index template
+#[derive(Serialize, Deserialize)]
+pub struct TemplateDefinition {
+    /// Template name
+    #[serde(default)]
+    pub name: String,
+
+    /// Template type (legacy or composable)
+    #[serde(rename = "type", default)]
+    pub template_type: TemplateApi,
+
+    /// Index patterns that this template applies to
+    pub index_patterns: Vec<String>,
+
+    /// Template priority order (higher = higher priority)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<i32>,
+
+    /// Composable template priority (higher = higher priority)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i64>,
+
+    /// Component templates a composable template is built from, in the order they're merged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub composed_of: Option<Vec<String>>,
+
+    /// Data stream options, if indices matching this composable template are data streams
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_stream: Option<Value>,
+
+    /// Template version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i64>,
+
+    /// Index settings, mappings and aliases
+    #[serde(flatten)]
+    pub template: TemplateBody,
+}
+
+impl TemplateDefinition {
+    pub fn from_composable(name: String, t: IndexTemplate) -> Self {
+        TemplateDefinition {
+            name,
+            template_type: TemplateApi::Composable,
+            index_patterns: t.index_patterns,
+            order: None,
+            priority: Some(t.priority.unwrap_or(0)),
+            composed_of: Some(t.composed_of),
+            data_stream: t.data_stream,
+            version: t.version,
+            template: t.template,
+        }
+    }
+
+    /// Precedence when several templates match an index (`order` for legacy templates, `priority` for composable ones)
+    pub fn precedence(&self) -> i64 {
+        self.order.map(i64::from).or(self.priority).unwrap_or(0)
+    }
+
+    /// Settings, mappings and aliases of this template merged on top of its component templates.
+    pub fn resolve(&self, components: &HashMap<String, TemplateBody>) -> TemplateBody {
+        let mut result = TemplateBody::default();
+        for name in self.composed_of.iter().flatten() {
+            if let Some(component) = components.get(name) {
+                result.merge(component);
+            }
+        }
+        result.merge(&self.template);
+        result
+    }
+}
+
+/// Settings, mappings and aliases of a template
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct TemplateBody {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mappings: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aliases: Option<Value>,
+}
+
+impl TemplateBody {
+    /// Merge another template body into this one, values from `other` taking precedence
+    pub fn merge(&mut self, other: &TemplateBody) {
+        merge_option(&mut self.settings, &other.settings);
+        merge_option(&mut self.mappings, &other.mappings);
+        merge_option(&mut self.aliases, &other.aliases);
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct IndexTemplatesResponse {
+    #[serde(default)]
+    pub index_templates: Vec<NamedIndexTemplate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NamedIndexTemplate {
+    pub name: String,
+    pub index_template: IndexTemplate,
+}
+
+/// A composable index template (`_index_template`)
+#[derive(Serialize, Deserialize)]
+pub struct IndexTemplate {
+    pub index_patterns: Vec<String>,
+    #[serde(default)]
+    pub composed_of: Vec<String>,
+    pub priority: Option<i64>,
+    pub version: Option<i64>,
+    pub data_stream: Option<Value>,
+    #[serde(default)]
+    pub template: TemplateBody,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ComponentTemplatesResponse {
+    #[serde(default)]
+    pub component_templates: Vec<NamedComponentTemplate>,
+}
+
+impl ComponentTemplatesResponse {
+    fn into_map(self) -> HashMap<String, TemplateBody> {
+        self.component_templates
+            .into_iter()
+            .map(|t| (t.name, t.component_template.template))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NamedComponentTemplate {
+    pub name: String,
+    pub component_template: ComponentTemplate,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ComponentTemplate {
+    #[serde(default)]
+    pub template: TemplateBody,
+}
+
+#[derive(Deserialize)]
+struct SimulateIndexResponse {
+    #[serde(default)]
+    template: TemplateBody,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(name: &str, template_type: TemplateApi, pattern: &str, precedence: i64) -> TemplateDefinition {
+        let (order, priority) = match template_type {
+            TemplateApi::Legacy => (Some(precedence as i32), None),
+            TemplateApi::Composable => (None, Some(precedence)),
+        };
+        TemplateDefinition {
+            name: name.to_string(),
+            template_type,
+            index_patterns: vec![pattern.to_string()],
+            order,
+            priority,
+            composed_of: None,
+            data_stream: None,
+            version: None,
+            template: TemplateBody::default(),
+        }
+    }
+
+    #[test]
+    fn templates_keyed_by_name() {
+        let templates = vec![
+            template("logs", TemplateApi::Composable, "logs-*", 100),
+            template("logs", TemplateApi::Legacy, "logs-*", 0),
+            template("metrics", TemplateApi::Legacy, "metrics-*", 0),
+        ];
+        let by_name = templates_by_name(&templates);
+        assert_eq!(by_name.keys().collect::<Vec<_>>(), vec!["logs", "logs (legacy)", "metrics"]);
+        assert_eq!(by_name["logs"].template_type, TemplateApi::Composable);
+        assert_eq!(serde_json::to_value(&by_name).unwrap()["metrics"]["type"], json!("legacy"));
+    }
+
+    #[test]
+    fn missing_components() {
+        let components = HashMap::from([("logs-mappings".to_string(), TemplateBody::default())]);
+        assert_eq!(
+            missing_component_names(&["logs-mappings", "logs-settings"], &components),
+            vec!["logs-settings".to_string()]
+        );
+    }
+
+    #[test]
+    fn composable_templates_override_legacy_ones() {
+        let templates = vec![
+            template("legacy-high", TemplateApi::Legacy, "logs-*", 10),
+            template("legacy-low", TemplateApi::Legacy, "*", 0),
+            template("composable-low", TemplateApi::Composable, "logs-*", 100),
+            template("composable-high", TemplateApi::Composable, "logs-app-*", 200),
+            template("other", TemplateApi::Composable, "metrics-*", 500),
+        ];
+
+        let matching = TemplateMatch::new(&templates, "logs-app-1");
+        assert_eq!(matching.composable.len(), 2);
+        assert_eq!(matching.legacy.len(), 2);
+        let applied = matching.applied().iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(applied, vec!["composable-high"]);
+
+        let matching = TemplateMatch::new(&templates[..2], "logs-app-1");
+        let applied = matching.applied().iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(applied, vec!["legacy-low", "legacy-high"]);
+
+        assert!(TemplateMatch::new(&templates, "traces-1").is_empty());
+    }
+
+    #[test]
+    fn component_templates_are_merged_in_order() {
+        let mut t = template("logs", TemplateApi::Composable, "logs-*", 100);
+        t.composed_of = Some(vec!["settings".to_string(), "mappings".to_string()]);
+        t.template.settings = Some(json!({ "index": { "number_of_replicas": "2" } }));
+
+        let components = HashMap::from([
+            (
+                "settings".to_string(),
+                TemplateBody {
+                    settings: Some(json!({ "index": { "number_of_shards": "3", "number_of_replicas": "1" } })),
+                    ..Default::default()
+                },
+            ),
+            (
+                "mappings".to_string(),
+                TemplateBody {
+                    mappings: Some(json!({ "properties": { "message": { "type": "text" } } })),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let resolved = t.resolve(&components);
+        assert_eq!(
+            resolved.settings,
+            Some(json!({ "index": { "number_of_shards": "3", "number_of_replicas": "2" } }))
+        );
+        assert_eq!(
+            resolved.mappings,
+            Some(json!({ "properties": { "message": { "type": "text" } } }))
+        );
+    }
+}
@@ -0,0 +1,527 @@
+// Licensed to Elasticsearch B.V. under one or more contributor
+// license agreements. See the NOTICE file distributed with
+// this work for additional information regarding copyright
+// ownership. Elasticsearch B.V. licenses this file to you under
+// the Apache License, Version 2.0 (the "License"); you may
+// not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Data freshness monitoring: finds the newest document of each index or data stream and
+//! classifies ingestion as healthy, lagging or stalled.
+
+use crate::servers::elasticsearch::aliases::try_resolve_expression;
+use crate::servers::elasticsearch::base_tools::{
+    EsBaseTools, MappingResponse, MsearchResponse, max_index_list, max_response_chars, pack_json_value,
+};
+use crate::servers::elasticsearch::fields::flatten_fields;
+use crate::servers::elasticsearch::read_json;
+use elasticsearch::http::request::JsonBody;
+use elasticsearch::indices::IndicesGetMappingParts;
+use elasticsearch::{Elasticsearch, MsearchParts};
+use rmcp::RoleServer;
+use rmcp::handler::server::tool::Parameters;
+use rmcp::model::{CallToolResult, Content};
+use rmcp::service::RequestContext;
+use rmcp_macros::{tool, tool_router};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default lag above which ingestion is considered lagging
+const DEFAULT_LAGGING_MINUTES: u64 = 5;
+
+/// Default lag above which ingestion is considered stalled
+const DEFAULT_STALLED_MINUTES: u64 = 30;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+struct CheckIngestionVitalityParams {
+    /// Index pattern of the indices and data streams to check (supports * wildcard)
+    index_pattern: String,
+    /// Optional timestamp field. Detected from the mappings if not provided (date fields, preferring @timestamp and *time* names).
+    timestamp_field: Option<String>,
+    /// Lag in minutes above which ingestion is considered lagging (default: 5)
+    lagging_after_minutes: Option<u64>,
+    /// Lag in minutes above which ingestion is considered stalled (default: 30)
+    stalled_after_minutes: Option<u64>,
+}
+
+#[tool_router(router = vitality_tools_router, vis = pub)]
+impl EsBaseTools {
+    //---------------------------------------------------------------------------------------------
+    /// Tool: check ingestion vitality
+    ///
+    /// Indices are grouped by data stream, and the newest timestamp of each index or data stream is
+    /// fetched with a single multi-search of `size: 1` queries sorted on the timestamp field.
+    #[tool(
+        description = "Check data freshness for indices and data streams matching a pattern: finds the timestamp field, fetches the newest document and classifies each target as healthy, lagging or stalled based on its lag. Use it to spot broken ingestion pipelines.",
+        annotations(title = "Check ingestion vitality", read_only_hint = true)
+    )]
+    async fn check_ingestion_vitality(
+        &self,
+        req_ctx: RequestContext<RoleServer>,
+        Parameters(params): Parameters<CheckIngestionVitalityParams>,
+    ) -> Result<CallToolResult, rmcp::Error> {
+        let es_client = self.es_client.get(req_ctx);
+
+        let thresholds = Thresholds {
+            lagging_ms: params.lagging_after_minutes.unwrap_or(DEFAULT_LAGGING_MINUTES).saturating_mul(60_000),
+            stalled_ms: params.stalled_after_minutes.unwrap_or(DEFAULT_STALLED_MINUTES).saturating_mul(60_000),
+        };
+        if thresholds.lagging_ms > thresholds.stalled_ms {
+            return Err(rmcp::Error::invalid_params(
+                "lagging_after_minutes must not be greater than stalled_after_minutes.",
+                None,
+            ));
+        }
+
+        let mut targets = find_targets(&es_client, &params.index_pattern, params.timestamp_field.as_deref()).await?;
+        if targets.is_empty() {
+            return Err(rmcp::Error::invalid_params(
+                format!("No indices match '{}'.", params.index_pattern),
+                None,
+            ));
+        }
+
+        let total_count = targets.len();
+        let max_list = max_index_list();
+        targets.truncate(max_list);
+
+        let latest = fetch_latest(&es_client, &targets).await?;
+        let now = now_millis();
+
+        let mut results = targets
+            .iter()
+            .zip(latest)
+            .map(|(target, latest)| target_vitality(target, latest, now, &thresholds))
+            .collect::<Vec<_>>();
+
+        // Most urgent first
+        results.sort_by(|a, b| a.status.cmp(&b.status).then_with(|| b.lag_seconds.cmp(&a.lag_seconds)));
+
+        let mut counts = BTreeMap::<VitalityStatus, usize>::new();
+        for result in &results {
+            *counts.entry(result.status).or_default() += 1;
+        }
+        let mut message = format!(
+            "Checked {} target(s): {}.",
+            results.len(),
+            counts
+                .iter()
+                .map(|(status, count)| format!("{count} {}", status.as_str()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if total_count > max_list {
+            message.push_str(&format!(
+                " {} more target(s) not checked, use a narrower index_pattern.",
+                total_count - max_list
+            ));
+        }
+
+        let data = pack_json_value(&results, max_response_chars())?;
+        Ok(CallToolResult::success(vec![
+            Content::json(json!({ "message": message, "data": data }))?
+        ]))
+    }
+}
+
+//------------------------------------------------------------------------------------------------
+// Targets and timestamp detection
+
+/// An index or data stream to check
+#[derive(Debug)]
+struct Target {
+    name: String,
+    kind: TargetKind,
+    timestamp_field: Option<String>,
+    /// Type of the timestamp field, to interpret sort values
+    timestamp_type: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TargetKind {
+    Index,
+    DataStream,
+}
+
+/// Find the indices and data streams matching a pattern, and their timestamp field. Backing indices
+/// of data streams are grouped into their data stream.
+async fn find_targets(
+    es_client: &Elasticsearch,
+    pattern: &str,
+    timestamp_field: Option<&str>,
+) -> Result<Vec<Target>, rmcp::Error> {
+    let response = es_client
+        .indices()
+        .get_mapping(IndicesGetMappingParts::Index(&[pattern]))
+        .send()
+        .await;
+    let mappings: MappingResponse = read_json(response).await?;
+
+    // Resolving data streams may not be supported (e.g. older versions): consider all indices as standalone
+    let resolved = try_resolve_expression(es_client, pattern).await.unwrap_or_default();
+
+    let backing_indices = resolved
+        .data_streams
+        .iter()
+        .flat_map(|ds| ds.backing_indices.iter().map(move |index| (index.as_str(), ds.name.as_str())))
+        .collect::<HashMap<_, _>>();
+
+    // Fields of each target, merged across the backing indices of data streams
+    let mut fields = BTreeMap::<(String, bool), HashMap<String, String>>::new();
+    for (index, mapping) in &mappings {
+        let key = match backing_indices.get(index.as_str()) {
+            Some(data_stream) => (data_stream.to_string(), true),
+            None => (index.clone(), false),
+        };
+        fields
+            .entry(key)
+            .or_default()
+            .extend(flatten_fields(&mapping.mappings.properties).into_iter().map(|f| (f.field, f.field_type)));
+    }
+
+    let targets = fields
+        .into_iter()
+        .map(|((name, is_data_stream), fields)| {
+            let data_stream_field = resolved
+                .data_streams
+                .iter()
+                .find(|ds| is_data_stream && ds.name == name)
+                .and_then(|ds| ds.timestamp_field.as_deref());
+            let field = timestamp_field
+                .or(data_stream_field)
+                .map(|f| f.to_string())
+                .or_else(|| detect_timestamp_field(&fields));
+            let timestamp_type = field
+                .as_ref()
+                .and_then(|f| fields.get(f))
+                .cloned()
+                .unwrap_or_else(|| "date".to_string());
+            Target {
+                name,
+                kind: if is_data_stream { TargetKind::DataStream } else { TargetKind::Index },
+                timestamp_field: field,
+                timestamp_type,
+            }
+        })
+        .collect();
+
+    Ok(targets)
+}
+
+/// Find the most likely timestamp field: date fields are preferred, then fields named `@timestamp`,
+/// then containing `timestamp` or `time`.
+fn detect_timestamp_field(fields: &HashMap<String, String>) -> Option<String> {
+    fields
+        .iter()
+        .filter_map(|(name, field_type)| {
+            let is_date = matches!(field_type.as_str(), "date" | "date_nanos");
+            let lower = name.to_lowercase();
+            let name_rank = if name == "@timestamp" {
+                0
+            } else if lower.contains("timestamp") {
+                1
+            } else if lower.contains("time") {
+                2
+            } else {
+                3
+            };
+            let is_numeric = matches!(field_type.as_str(), "long" | "unsigned_long");
+            match (is_date, name_rank) {
+                (true, rank) => Some((rank, name)),
+                // Epoch values stored as numbers
+                (false, rank) if rank < 3 && is_numeric => Some((rank + 10, name)),
+                _ => None,
+            }
+        })
+        .min()
+        .map(|(_, name)| name.clone())
+}
+
+//------------------------------------------------------------------------------------------------
+// Newest document
+
+/// Fetch the newest timestamp (in epoch milliseconds) of each target with a single multi-search.
+async fn fetch_latest(es_client: &Elasticsearch, targets: &[Target]) -> Result<Vec<Latest>, rmcp::Error> {
+    let searched = targets
+        .iter()
+        .filter_map(|t| t.timestamp_field.as_ref().map(|field| (t, field)))
+        .collect::<Vec<_>>();
+    if searched.is_empty() {
+        return Ok(targets.iter().map(|_| Latest::NoTimestampField).collect());
+    }
+
+    let mut body: Vec<JsonBody<Value>> = Vec::with_capacity(searched.len() * 2);
+    for (target, field) in &searched {
+        body.push(JsonBody::new(json!({ "index": target.name, "ignore_unavailable": true })));
+        body.push(JsonBody::new(json!({
+            "size": 1,
+            "_source": [field],
+            "track_total_hits": false,
+            "query": { "exists": { "field": field } },
+            "sort": [{ field.as_str(): { "order": "desc", "unmapped_type": "date" } }],
+        })));
+    }
+
+    let response = es_client.msearch(MsearchParts::None).body(body).send().await;
+    let response: MsearchResponse = read_json(response).await?;
+    let mut responses = response.responses.into_iter();
+
+    let result = targets
+        .iter()
+        .map(|target| {
+            if target.timestamp_field.is_none() {
+                return Latest::NoTimestampField;
+            }
+            match responses.next() {
+                Some(response) => latest_from_response(response, &target.timestamp_type),
+                None => Latest::Error("missing search response".to_string()),
+            }
+        })
+        .collect();
+    Ok(result)
+}
+
+fn latest_from_response(response: Value, timestamp_type: &str) -> Latest {
+    if let Some(error) = response.get("error") {
+        let reason = error
+            .get("reason")
+            .and_then(|r| r.as_str())
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| error.to_string());
+        return Latest::Error(reason);
+    }
+
+    let Some(hit) = response.pointer("/hits/hits/0") else {
+        return Latest::NoData;
+    };
+    let source_value = hit.get("_source").cloned().unwrap_or(Value::Null);
+    match hit.pointer("/sort/0").and_then(sort_value_to_i64) {
+        Some(value) => Latest::Found {
+            millis: to_millis(value, timestamp_type),
+            source: source_value,
+        },
+        None => Latest::NoData,
+    }
+}
+
+fn sort_value_to_i64(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Convert a sort value to epoch milliseconds. Dates sort on milliseconds and `date_nanos` on
+/// nanoseconds. Numeric fields are assumed to hold epoch seconds if too small to be milliseconds.
+fn to_millis(value: i64, timestamp_type: &str) -> i64 {
+    match timestamp_type {
+        "date" => value,
+        "date_nanos" => value / 1_000_000,
+        _ if value.abs() < 100_000_000_000 => value * 1000,
+        _ => value,
+    }
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+//------------------------------------------------------------------------------------------------
+// Classification
+
+enum Latest {
+    Found { millis: i64, source: Value },
+    NoData,
+    NoTimestampField,
+    Error(String),
+}
+
+struct Thresholds {
+    lagging_ms: u64,
+    stalled_ms: u64,
+}
+
+/// Vitality status, ordered by urgency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum VitalityStatus {
+    Stalled,
+    Lagging,
+    Error,
+    NoData,
+    NoTimestamp,
+    Healthy,
+}
+
+impl VitalityStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            VitalityStatus::Stalled => "stalled",
+            VitalityStatus::Lagging => "lagging",
+            VitalityStatus::Error => "error",
+            VitalityStatus::NoData => "no_data",
+            VitalityStatus::NoTimestamp => "no_timestamp",
+            VitalityStatus::Healthy => "healthy",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TargetVitality {
+    target: String,
+    #[serde(rename = "type")]
+    kind: TargetKind,
+    status: VitalityStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp_field: Option<String>,
+    /// Timestamp value of the newest document, as stored in its source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lag_seconds: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+fn target_vitality(target: &Target, latest: Latest, now: i64, thresholds: &Thresholds) -> TargetVitality {
+    let mut result = TargetVitality {
+        target: target.name.clone(),
+        kind: target.kind,
+        status: VitalityStatus::Healthy,
+        timestamp_field: target.timestamp_field.clone(),
+        latest: None,
+        lag_seconds: None,
+        detail: None,
+    };
+
+    match latest {
+        Latest::Found { millis, source } => {
+            let lag = now - millis;
+            result.status = classify(lag, thresholds);
+            result.lag_seconds = Some(lag / 1000);
+            result.latest = target.timestamp_field.as_deref().and_then(|f| source_field(&source, f));
+            if lag < 0 {
+                result.detail = Some("Newest document is in the future: check clocks and time zones.".to_string());
+            }
+        }
+        Latest::NoData => {
+            result.status = VitalityStatus::NoData;
+            result.detail = Some("No documents with a timestamp.".to_string());
+        }
+        Latest::NoTimestampField => {
+            result.status = VitalityStatus::NoTimestamp;
+            result.detail = Some("No timestamp field found, provide one with 'timestamp_field'.".to_string());
+        }
+        Latest::Error(reason) => {
+            result.status = VitalityStatus::Error;
+            result.detail = Some(reason);
+        }
+    }
+    result
+}
+
+fn classify(lag_ms: i64, thresholds: &Thresholds) -> VitalityStatus {
+    let lag_ms = lag_ms.max(0) as u64;
+    if lag_ms >= thresholds.stalled_ms {
+        VitalityStatus::Stalled
+    } else if lag_ms >= thresholds.lagging_ms {
+        VitalityStatus::Lagging
+    } else {
+        VitalityStatus::Healthy
+    }
+}
+
+/// Get a field from a document source, either as a nested object path or as a dotted key
+fn source_field(source: &Value, field: &str) -> Option<Value> {
+    if let Some(value) = source.get(field) {
+        return Some(value.clone());
+    }
+    field
+        .split('.')
+        .try_fold(source, |value, key| value.get(key))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields.iter().map(|(n, t)| (n.to_string(), t.to_string())).collect()
+    }
+
+    #[test]
+    fn timestamp_field_detection() {
+        assert_eq!(
+            detect_timestamp_field(&fields(&[("created", "date"), ("@timestamp", "date"), ("message", "text")])),
+            Some("@timestamp".to_string())
+        );
+        assert_eq!(
+            detect_timestamp_field(&fields(&[("created", "date"), ("event.time", "date_nanos")])),
+            Some("event.time".to_string())
+        );
+        assert_eq!(
+            detect_timestamp_field(&fields(&[("log_time", "long"), ("message", "text")])),
+            Some("log_time".to_string())
+        );
+        assert_eq!(detect_timestamp_field(&fields(&[("message", "text"), ("count", "long")])), None);
+    }
+
+    #[test]
+    fn lag_classification() {
+        let thresholds = Thresholds {
+            lagging_ms: 5 * 60_000,
+            stalled_ms: 30 * 60_000,
+        };
+        assert_eq!(classify(60_000, &thresholds), VitalityStatus::Healthy);
+        assert_eq!(classify(-60_000, &thresholds), VitalityStatus::Healthy);
+        assert_eq!(classify(10 * 60_000, &thresholds), VitalityStatus::Lagging);
+        assert_eq!(classify(7_200_000, &thresholds), VitalityStatus::Stalled);
+    }
+
+    #[test]
+    fn sort_values_to_millis() {
+        assert_eq!(to_millis(1_700_000_000_000, "date"), 1_700_000_000_000);
+        assert_eq!(to_millis(1_700_000_000_000_000_000, "date_nanos"), 1_700_000_000_000);
+        assert_eq!(to_millis(1_700_000_000, "long"), 1_700_000_000_000);
+        assert_eq!(to_millis(1_700_000_000_000, "long"), 1_700_000_000_000);
+    }
+
+    #[test]
+    fn newest_document_from_response() {
+        let response = json!({ "hits": { "hits": [{ "_source": { "event": { "created": "2024-01-01T00:00:00Z" } }, "sort": [1_704_067_200_000_i64] }] } });
+        let target = Target {
+            name: "logs".to_string(),
+            kind: TargetKind::Index,
+            timestamp_field: Some("event.created".to_string()),
+            timestamp_type: "date".to_string(),
+        };
+        let thresholds = Thresholds {
+            lagging_ms: 1000,
+            stalled_ms: 2000,
+        };
+        let latest = latest_from_response(response, "date");
+        let result = target_vitality(&target, latest, 1_704_067_200_500, &thresholds);
+        assert_eq!(result.status, VitalityStatus::Healthy);
+        assert_eq!(result.latest, Some(json!("2024-01-01T00:00:00Z")));
+
+        let latest = latest_from_response(json!({ "hits": { "hits": [] } }), "date");
+        assert_eq!(target_vitality(&target, latest, 0, &thresholds).status, VitalityStatus::NoData);
+    }
+}